@@ -0,0 +1,147 @@
+use crate::fpv_camera_plugin::{AnalogVideo, FpvCamera, FpvLensMaterial};
+use crate::level_plugin::LevelRoot;
+use bevy::picking::mesh_picking::ray_cast::{MeshRayCast, MeshRayCastSettings, RayCastVisibility};
+use bevy::prelude::*;
+
+/// Degrades the FPV feed like an analog video link: noise and static grow with the distance
+/// to the video transmitter, and the picture breaks up when level geometry blocks the line of
+/// sight between the drone and the transmitter.
+pub struct AnalogVideoPlugin;
+
+impl Plugin for AnalogVideoPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AnalogVideoSettings>()
+            .add_systems(Update, (attach_video_link, update_video_link).chain())
+            .register_type::<AnalogVideoSettings>()
+            .register_type::<VideoLink>();
+    }
+}
+
+#[derive(Resource, Reflect, Clone)]
+#[reflect(Resource)]
+pub struct AnalogVideoSettings {
+    pub enabled: bool,
+    /// World position of the video receiver antenna, usually next to the pilot.
+    pub receiver_position: Vec3,
+    /// The picture stays clean up to this distance, in meters.
+    pub clean_range_m: f32,
+    /// The picture is pure static beyond this distance, in meters.
+    pub max_range_m: f32,
+    /// Signal lost per obstacle between the drone and the receiver, `0..=1`.
+    pub obstruction_loss: f32,
+    /// Horizontal chroma smear, in feed widths.
+    pub color_bleed: f32,
+    /// How quickly the signal level follows the geometry, per second.
+    pub smoothing: f32,
+}
+
+impl Default for AnalogVideoSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            receiver_position: Vec3::new(-2.0, 1.7, -5.0),
+            clean_range_m: 50.0,
+            max_range_m: 400.0,
+            obstruction_loss: 0.35,
+            color_bleed: 0.003,
+            smoothing: 8.0,
+        }
+    }
+}
+
+/// Video link quality of an FPV camera.
+#[derive(Component, Reflect, Debug, Clone)]
+#[reflect(Component)]
+pub struct VideoLink {
+    /// `1` for a clean picture, `0` for pure static.
+    pub signal: f32,
+    pub distance_m: f32,
+    /// Level geometry crossing the line of sight to the receiver.
+    pub obstacles: usize,
+}
+
+impl Default for VideoLink {
+    fn default() -> Self {
+        Self {
+            signal: 1.0,
+            distance_m: 0.0,
+            obstacles: 0,
+        }
+    }
+}
+
+fn attach_video_link(
+    mut commands: Commands,
+    cameras: Query<Entity, (With<FpvCamera>, Without<VideoLink>)>,
+) {
+    for entity in cameras.iter() {
+        commands.entity(entity).insert(VideoLink::default());
+    }
+}
+
+fn update_video_link(
+    settings: Res<AnalogVideoSettings>,
+    mut cameras: Query<(&FpvCamera, &GlobalTransform, &mut VideoLink)>,
+    parents: Query<&ChildOf>,
+    level_roots: Query<(), With<LevelRoot>>,
+    mut ray_cast: MeshRayCast,
+    mut materials: ResMut<Assets<FpvLensMaterial>>,
+    time: Res<Time>,
+) {
+    for (fpv, transform, mut link) in cameras.iter_mut() {
+        let Some(material) = materials.get_mut(&fpv.lens) else {
+            continue;
+        };
+        if !settings.enabled {
+            material.video = AnalogVideo::default();
+            continue;
+        }
+
+        let camera_position = transform.translation();
+        let to_receiver = settings.receiver_position - camera_position;
+        let distance = to_receiver.length();
+        let obstacles = match Dir3::new(to_receiver) {
+            Ok(direction) => {
+                // Only level geometry blocks the signal, not drones, ghosts or gizmos
+                let filter = |hit: Entity| {
+                    level_roots.contains(hit)
+                        || parents
+                            .iter_ancestors(hit)
+                            .any(|ancestor| level_roots.contains(ancestor))
+                };
+                let keep_going = |_: Entity| false;
+                // The receiver is usually behind the camera, where the geometry is culled
+                let ray_settings = MeshRayCastSettings::default()
+                    .with_visibility(RayCastVisibility::Any)
+                    .with_filter(&filter)
+                    .with_early_exit_test(&keep_going);
+                ray_cast
+                    .cast_ray(Ray3d::new(camera_position, direction), &ray_settings)
+                    .iter()
+                    .filter(|(_, hit)| hit.distance < distance)
+                    .count()
+            }
+            Err(_) => 0,
+        };
+
+        let range_loss = ((distance - settings.clean_range_m)
+            / (settings.max_range_m - settings.clean_range_m).max(1.0))
+        .clamp(0.0, 1.0);
+        let target =
+            (1.0 - range_loss - obstacles as f32 * settings.obstruction_loss).clamp(0.0, 1.0);
+
+        let blend = 1.0 - (-settings.smoothing * time.delta_secs()).exp();
+        link.signal += (target - link.signal) * blend;
+        link.distance_m = distance;
+        link.obstacles = obstacles;
+
+        let loss = 1.0 - link.signal;
+        material.video = AnalogVideo {
+            noise: loss,
+            // Analog links hold a usable picture until they fall apart quickly
+            breakup: ((loss - 0.6) / 0.4).clamp(0.0, 1.0),
+            color_bleed: settings.color_bleed,
+            time: time.elapsed_secs_wrapped(),
+        };
+    }
+}
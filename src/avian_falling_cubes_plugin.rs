@@ -1,6 +1,16 @@
+use crate::level_colliders_plugin::AvianLevelCollidersPlugin;
+use crate::prefab_asset_library::PrefabAssetLibrary;
+use crate::save_system_plugin::{ApplyFlow, CaptureFlow, SaveSystemPlugin};
 use avian3d::prelude::*;
 use bevy::prelude::*;
+use bevy_save::prelude::*;
 
+/// Every cube shares one mesh and material from the [`PrefabAssetLibrary`].
+const CUBE_ASSET_KEY: &str = "avian_falling_cube";
+
+/// Cubes falling onto the ground of a level, which needs `LevelPlugin` and
+/// [`AvianLevelCollidersPlugin`] to give the level geometry colliders. Without
+/// [`AvianLevelCollidersPlugin`] the cubes fall onto a plain ground plane instead.
 pub struct FallingCubesPlugin;
 
 impl Plugin for FallingCubesPlugin {
@@ -9,10 +19,39 @@ impl Plugin for FallingCubesPlugin {
             .add_systems(Startup, (setup_scene, spawn_cubes))
             // .add_systems(Update, log_collisions)
             .add_systems(Update, (handle_gravity_type, apply_gravity))
-            .register_type::<WorldGravity>();
+            .init_resource::<PrefabAssetLibrary>()
+            .register_type::<WorldGravity>()
+            .register_type::<FallingCube>();
+    }
+
+    // Every plugin is added by now, whatever order the app added them in
+    fn finish(&self, app: &mut App) {
+        if !app.is_plugin_added::<AvianLevelCollidersPlugin>() {
+            app.add_systems(Startup, spawn_fallback_ground);
+        }
     }
 }
 
+/// Saves the cubes mid-motion, with their velocities, sleeping state, external forces and
+/// impulses, and the gravity settings. Loading despawns the current cubes and respawns the
+/// saved ones.
+pub struct FallingCubesSavePlugin;
+
+impl Plugin for FallingCubesSavePlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<SaveSystemPlugin>() {
+            app.add_plugins(SaveSystemPlugin);
+        }
+        app.add_flows(CaptureFlow, capture_cubes)
+            .add_flows(ApplyFlow, apply_cubes)
+            .register_type::<FallingCubePrefab>();
+    }
+}
+
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+pub struct FallingCube;
+
 #[derive(Resource, Reflect)]
 #[reflect(Resource)]
 struct WorldGravity {
@@ -57,26 +96,9 @@ impl GravityType {
     }
 }
 
-fn setup_scene(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-) {
+fn setup_scene(mut commands: Commands) {
     commands.insert_resource(WorldGravity::default());
 
-    // Ground
-    commands.spawn((
-        Name::new("Ground"),
-        RigidBody::Static,
-        Collider::cuboid(20.0, 0.2, 20.0),
-        Transform::from_xyz(0.0, -0.1, 0.0),
-        Mesh3d(meshes.add(Mesh::from(Cuboid::new(20.0, 0.2, 20.0)))),
-        MeshMaterial3d(materials.add(Color::srgb(0.2, 0.5, 0.2))),
-        // Avian only sends/observes collision events for entities
-        // with this tag:
-        CollisionEventsEnabled,
-    ));
-
     // Add a light source so we can see clearly.
     commands.spawn((
         DirectionalLight {
@@ -95,31 +117,173 @@ fn setup_scene(
         Transform::from_xyz(-10.0, 8.0, 14.0).looking_at(Vec3::Y * 2.0, Dir3::Y),
     ));
 }
+
+fn spawn_fallback_ground(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn((
+        Name::new("Ground"),
+        RigidBody::Static,
+        Collider::cuboid(20.0, 0.2, 20.0),
+        Transform::from_xyz(0.0, -0.1, 0.0),
+        Mesh3d(meshes.add(Mesh::from(Cuboid::new(20.0, 0.2, 20.0)))),
+        MeshMaterial3d(materials.add(Color::srgb(0.2, 0.5, 0.2))),
+        // Avian only sends/observes collision events for entities
+        // with this tag:
+        CollisionEventsEnabled,
+    ));
+}
+
 fn spawn_cubes(
     mut commands: Commands,
+    mut library: ResMut<PrefabAssetLibrary>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
 ) {
     for y in 0..5 {
         for x in 0..5 {
             let pos = Vec3::new(x as f32 - 3.0, 3.0 + y as f32 * 1.2, 0.0);
-            commands.spawn((
-                Name::new(format!("Cube_{x}_{y}")),
-                RigidBody::Dynamic,
-                Collider::cuboid(1.0, 1.0, 1.0),
-                // Avian friction/restitution are on the collider’s material:
-                Friction::new(0.8),
-                Restitution::new(0.4),
+            commands.spawn(cube_bundle(
+                format!("Cube_{x}_{y}"),
                 Transform::from_translation(pos),
-                Mesh3d(meshes.add(Mesh::from(Cuboid::new(1.0, 1.0, 1.0)))),
-                MeshMaterial3d(materials.add(Color::srgb(0.6, 0.7, 1.0))),
-                // enable events for these entities:
-                CollisionEventsEnabled,
+                library.mesh(CUBE_ASSET_KEY, &mut meshes, cube_mesh),
+                library.material(CUBE_ASSET_KEY, &mut materials, cube_material),
             ));
         }
     }
 }
 
+fn cube_mesh() -> Mesh {
+    Mesh::from(Cuboid::new(1.0, 1.0, 1.0))
+}
+
+fn cube_material() -> StandardMaterial {
+    Color::srgb(0.6, 0.7, 1.0).into()
+}
+
+fn cube_bundle(
+    name: String,
+    transform: Transform,
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+) -> impl Bundle {
+    (
+        Name::new(name),
+        FallingCube,
+        RigidBody::Dynamic,
+        Collider::cuboid(1.0, 1.0, 1.0),
+        // Avian friction/restitution are on the collider’s material:
+        Friction::new(0.8),
+        Restitution::new(0.4),
+        transform,
+        Mesh3d(mesh),
+        MeshMaterial3d(material),
+        // enable events for these entities:
+        CollisionEventsEnabled,
+    )
+}
+
+/// Saved state of a cube, enough to resume its motion exactly.
+#[derive(Reflect, Default)]
+struct FallingCubePrefab {
+    name: String,
+    transform: Transform,
+    linear_velocity: Vec3,
+    angular_velocity: Vec3,
+    sleeping: bool,
+    /// Seconds the cube has been slow enough to fall asleep.
+    sleep_timer: f32,
+    external_force: Vec3,
+    /// Whether the force is kept after each physics step, Avian's default, or cleared.
+    persistent_force: bool,
+    external_torque: Vec3,
+    persistent_torque: bool,
+    external_impulse: Vec3,
+    persistent_impulse: bool,
+    external_angular_impulse: Vec3,
+    persistent_angular_impulse: bool,
+}
+
+impl Prefab for FallingCubePrefab {
+    type Marker = FallingCube;
+
+    fn spawn(self, target: Entity, world: &mut World) {
+        let mesh = PrefabAssetLibrary::world_mesh(world, CUBE_ASSET_KEY, cube_mesh);
+        let material = PrefabAssetLibrary::world_material(world, CUBE_ASSET_KEY, cube_material);
+
+        let mut entity = world.entity_mut(target);
+        entity.insert((
+            cube_bundle(self.name, self.transform, mesh, material),
+            LinearVelocity(self.linear_velocity),
+            AngularVelocity(self.angular_velocity),
+            SleepTimer(self.sleep_timer),
+            ExternalForce::new(self.external_force).with_persistence(self.persistent_force),
+            ExternalTorque::new(self.external_torque).with_persistence(self.persistent_torque),
+            ExternalImpulse::new(self.external_impulse).with_persistence(self.persistent_impulse),
+            ExternalAngularImpulse::new(self.external_angular_impulse)
+                .with_persistence(self.persistent_angular_impulse),
+        ));
+        if self.sleeping {
+            entity.insert(Sleeping);
+        }
+    }
+
+    fn extract(builder: BuilderRef) -> BuilderRef {
+        builder.extract_prefab(|entity| {
+            Some(FallingCubePrefab {
+                name: entity.get::<Name>()?.to_string(),
+                transform: entity.get::<Transform>().copied().unwrap_or_default(),
+                linear_velocity: entity.get::<LinearVelocity>().map_or(Vec3::ZERO, |v| v.0),
+                angular_velocity: entity.get::<AngularVelocity>().map_or(Vec3::ZERO, |v| v.0),
+                sleeping: entity.contains::<Sleeping>(),
+                sleep_timer: entity.get::<SleepTimer>().map_or(0.0, |timer| timer.0),
+                external_force: entity
+                    .get::<ExternalForce>()
+                    .map_or(Vec3::ZERO, ExternalForce::force),
+                persistent_force: entity
+                    .get::<ExternalForce>()
+                    .is_none_or(|force| force.persistent),
+                external_torque: entity
+                    .get::<ExternalTorque>()
+                    .map_or(Vec3::ZERO, ExternalTorque::torque),
+                persistent_torque: entity
+                    .get::<ExternalTorque>()
+                    .is_none_or(|torque| torque.persistent),
+                external_impulse: entity
+                    .get::<ExternalImpulse>()
+                    .map_or(Vec3::ZERO, ExternalImpulse::impulse),
+                persistent_impulse: entity
+                    .get::<ExternalImpulse>()
+                    .is_some_and(|impulse| impulse.persistent),
+                external_angular_impulse: entity
+                    .get::<ExternalAngularImpulse>()
+                    .map_or(Vec3::ZERO, ExternalAngularImpulse::impulse),
+                persistent_angular_impulse: entity
+                    .get::<ExternalAngularImpulse>()
+                    .is_some_and(|impulse| impulse.persistent),
+            })
+        })
+    }
+}
+
+fn capture_cubes(In(cap): In<Builder>, world: &World) -> Builder {
+    cap.scope(world, |b| {
+        b.extract_all_prefabs::<FallingCubePrefab>()
+            // Avian's `Gravity` follows it
+            .extract_resource::<WorldGravity>()
+            .clear_empty()
+    })
+}
+
+fn apply_cubes(In(apply): In<Applier<'static>>, world: &mut World) -> Applier<'static> {
+    apply.scope(world, |a| {
+        a.prefab::<FallingCubePrefab>()
+            .despawn::<With<FallingCube>>()
+    })
+}
+
 // Avian’s buffered event type:
 #[allow(dead_code)]
 fn log_collisions(mut started: EventReader<CollisionStarted>) {
@@ -1,17 +1,37 @@
 use avian3d::PhysicsPlugins;
 use avian3d::prelude::PhysicsDebugPlugin;
 use bevy::prelude::*;
-use bevy_drone_sim::avian_falling_cubes_plugin::FallingCubesPlugin;
+use bevy_drone_sim::avian_falling_cubes_plugin::{FallingCubesPlugin, FallingCubesSavePlugin};
 use bevy_drone_sim::free_camera_plugin::FreeCameraPlugin;
+use bevy_drone_sim::level_colliders_plugin::AvianLevelCollidersPlugin;
+use bevy_drone_sim::level_plugin::{LevelPlugin, LevelSettings};
+use bevy_drone_sim::replay_plugin::ReplayPlugin;
+use bevy_drone_sim::save_system_plugin::SaveSlotSettings;
+use bevy_drone_sim::simulation_plugin::SimulationPlugin;
 use bevy_inspector_egui::bevy_egui::EguiPlugin;
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
 
+/// `F5` records the session to `replays/`, `F6` plays the latest recording back.
+/// `F7` pauses, `F8` steps, `F9` restarts and `[`/`]` change the simulation speed.
+/// `F2` saves and loads the cubes mid-fall, velocities and gravity included.
+/// The ground comes from the falling cubes level, with colliders generated from its glTF.
 fn main() {
     App::new()
         .add_plugins(DefaultPlugins)
         .add_plugins((EguiPlugin::default(), WorldInspectorPlugin::new()))
         // Avian’s physics group + Draw colliders, contacts, etc.
         .add_plugins((PhysicsPlugins::default(), PhysicsDebugPlugin::default()))
-        .add_plugins((FreeCameraPlugin, FallingCubesPlugin))
+        .add_plugins((FreeCameraPlugin, FallingCubesPlugin, FallingCubesSavePlugin))
+        .add_plugins((ReplayPlugin, SimulationPlugin))
+        .add_plugins((LevelPlugin, AvianLevelCollidersPlugin))
+        .insert_resource(LevelSettings {
+            startup_level: Some("levels/falling_cubes.level.ron".to_string()),
+            ..default()
+        })
+        // The saves only load in the demo that wrote them
+        .insert_resource(SaveSlotSettings {
+            directory: "saves/avian_engine_demo".to_string(),
+            ..default()
+        })
         .run();
 }
@@ -0,0 +1,64 @@
+use bevy::prelude::*;
+use bevy_drone_sim::analog_video_plugin::AnalogVideoPlugin;
+use bevy_drone_sim::blackbox_plugin::BlackboxPlugin;
+use bevy_drone_sim::camera_modes_plugin::CameraModesPlugin;
+use bevy_drone_sim::drone_plugin::{DronePlugin, DronePosition, PlayerDrone};
+use bevy_drone_sim::flight_controller_plugin::FlightControllerPlugin;
+use bevy_drone_sim::flight_path_plugin::FlightPathPlugin;
+use bevy_drone_sim::fpv_camera_plugin::FpvCameraPlugin;
+use bevy_drone_sim::ghost_plugin::GhostPlugin;
+use bevy_drone_sim::level_plugin::{LevelPlugin, LevelSettings};
+use bevy_drone_sim::msp_server_plugin::MspServerPlugin;
+use bevy_drone_sim::osd_plugin::OsdPlugin;
+use bevy_drone_sim::replay_plugin::ReplayPlugin;
+use bevy_drone_sim::simulation_plugin::SimulationPlugin;
+use bevy_inspector_egui::bevy_egui::EguiPlugin;
+use bevy_inspector_egui::quick::WorldInspectorPlugin;
+
+/// Arm with `M` and fly acro, `V` switches to the FPV camera, `O` edits its OSD and `Tab`
+/// cycles the chase, orbit and line-of-sight cameras. Connect MSP tooling to TCP port 5761.
+/// Flights are logged to `blackbox/` while armed, `F5` records the session and `F6` replays it.
+/// Runs from arming to the gate are saved as ghosts, the fastest ones fly along on the next run.
+/// `G` toggles the flight path gizmos and `C` cycles their colouring.
+/// `F7` pauses, `F8` steps, `F9` restarts and `[`/`]` change the simulation speed.
+/// The hover test level is loaded on startup, `L` lists the other levels.
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_plugins((EguiPlugin::default(), WorldInspectorPlugin::new()))
+        .add_plugins((DronePlugin, FlightControllerPlugin, MspServerPlugin))
+        .add_plugins((
+            FpvCameraPlugin,
+            AnalogVideoPlugin,
+            OsdPlugin,
+            CameraModesPlugin,
+        ))
+        .add_plugins((BlackboxPlugin, ReplayPlugin, GhostPlugin, FlightPathPlugin))
+        .add_plugins((SimulationPlugin, LevelPlugin))
+        .insert_resource(LevelSettings {
+            startup_level: Some("levels/hover_test.level.ron".to_string()),
+            ..default()
+        })
+        .add_systems(Startup, setup)
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn((
+        Name::new("Drone"),
+        PlayerDrone,
+        DronePosition::default(),
+        Mesh3d(meshes.add(Cuboid::new(0.25, 0.05, 0.25))),
+        MeshMaterial3d(materials.add(Color::WHITE)),
+        Transform::from_translation(Vec3::ZERO),
+    ));
+
+    commands.spawn((
+        Camera3d::default(),
+        Transform::from_xyz(-2.0, 2.0, -5.0).looking_at(Vec3::ZERO, Vec3::Y),
+    ));
+}
@@ -1,13 +1,16 @@
 use bevy::prelude::*;
+use bevy_drone_sim::camera_bookmarks_plugin::CameraBookmarksPlugin;
 use bevy_drone_sim::free_camera_plugin::FreeCameraPlugin;
 use bevy_drone_sim::rotating_cube_plugin::RotatingCubePlugin;
 use bevy_inspector_egui::bevy_egui::EguiPlugin;
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
 
+/// `F` toggles the free camera, `Ctrl` + `1`–`9` bookmarks the view and `1`–`9` returns to it.
+/// `P` flies the camera path through the bookmarks, `K` lists them.
 fn main() {
     App::new()
         .add_plugins(DefaultPlugins)
         .add_plugins((EguiPlugin::default(), WorldInspectorPlugin::new()))
-        .add_plugins((RotatingCubePlugin, FreeCameraPlugin))
+        .add_plugins((RotatingCubePlugin, FreeCameraPlugin, CameraBookmarksPlugin))
         .run();
 }
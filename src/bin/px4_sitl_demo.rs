@@ -0,0 +1,59 @@
+use bevy::prelude::*;
+use bevy_drone_sim::camera_bookmarks_plugin::CameraBookmarksPlugin;
+use bevy_drone_sim::camera_modes_plugin::CameraModesPlugin;
+use bevy_drone_sim::drone_plugin::{DronePlugin, DronePosition, PlayerDrone};
+use bevy_drone_sim::flight_path_plugin::FlightPathPlugin;
+use bevy_drone_sim::free_camera_plugin::FreeCameraPlugin;
+use bevy_drone_sim::px4_sitl_plugin::Px4SitlPlugin;
+use bevy_inspector_egui::bevy_egui::EguiPlugin;
+use bevy_inspector_egui::quick::WorldInspectorPlugin;
+
+/// Start PX4 with `make px4_sitl none_iris` and it will connect to this simulator.
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_plugins((EguiPlugin::default(), WorldInspectorPlugin::new()))
+        .add_plugins((DronePlugin, Px4SitlPlugin, FreeCameraPlugin))
+        .add_plugins((FlightPathPlugin, CameraModesPlugin, CameraBookmarksPlugin))
+        .add_systems(Startup, setup)
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn((
+        Name::new("Drone"),
+        PlayerDrone,
+        DronePosition::default(),
+        Mesh3d(meshes.add(Cuboid::new(0.25, 0.05, 0.25))),
+        MeshMaterial3d(materials.add(Color::WHITE)),
+        Transform::from_translation(Vec3::ZERO),
+    ));
+
+    commands.spawn((
+        Name::new("Ground"),
+        Mesh3d(meshes.add(Cuboid::new(20.0, 0.2, 20.0))),
+        MeshMaterial3d(materials.add(Color::srgb(0.2, 0.5, 0.2))),
+        Transform::from_xyz(0.0, -0.1, 0.0),
+    ));
+
+    commands.spawn((
+        Camera3d::default(),
+        Transform::from_xyz(-2.0, 2.0, -5.0).looking_at(Vec3::ZERO, Vec3::Y),
+    ));
+
+    commands.spawn((
+        DirectionalLight {
+            color: Color::Srgba(Srgba::new(1.0, 1.0, 0.0, 1.0)),
+            illuminance: 2000.0,
+            shadows_enabled: false,
+            affects_lightmapped_mesh_diffuse: false,
+            shadow_depth_bias: 0.0,
+            shadow_normal_bias: 0.0,
+        },
+        Transform::from_xyz(3.0, 3.0, 3.0).looking_at(Vec3::ZERO, Vec3::Y),
+    ));
+}
@@ -1,10 +1,17 @@
 use bevy::prelude::*;
 use bevy_drone_sim::free_camera_plugin::FreeCameraPlugin;
-use bevy_drone_sim::rapier_falling_cubes_plugin::FallingCubesPlugin;
+use bevy_drone_sim::level_colliders_plugin::RapierLevelCollidersPlugin;
+use bevy_drone_sim::level_plugin::{LevelPlugin, LevelSettings};
+use bevy_drone_sim::rapier_falling_cubes_plugin::{FallingCubesPlugin, FallingCubesSavePlugin};
+use bevy_drone_sim::save_system_plugin::SaveSlotSettings;
+use bevy_drone_sim::simulation_plugin::SimulationPlugin;
 use bevy_inspector_egui::bevy_egui::EguiPlugin;
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
 use bevy_rapier3d::prelude::*;
 
+/// `F7` pauses, `F8` steps, `F9` restarts and `[`/`]` change the simulation speed.
+/// `F2` saves and loads the cubes mid-fall, velocities and gravity included.
+/// The ground comes from the falling cubes level, with colliders generated from its glTF.
 fn main() {
     App::new()
         .add_plugins(DefaultPlugins)
@@ -14,6 +21,17 @@ fn main() {
             RapierPhysicsPlugin::<NoUserData>::default(),
             RapierDebugRenderPlugin::default(),
         ))
-        .add_plugins((FreeCameraPlugin, FallingCubesPlugin))
+        .add_plugins((FreeCameraPlugin, FallingCubesPlugin, SimulationPlugin))
+        .add_plugins(FallingCubesSavePlugin)
+        .add_plugins((LevelPlugin, RapierLevelCollidersPlugin))
+        .insert_resource(LevelSettings {
+            startup_level: Some("levels/falling_cubes.level.ron".to_string()),
+            ..default()
+        })
+        // The saves only load in the demo that wrote them
+        .insert_resource(SaveSlotSettings {
+            directory: "saves/rapier_engine_demo".to_string(),
+            ..default()
+        })
         .run();
 }
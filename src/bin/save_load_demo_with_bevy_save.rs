@@ -1,11 +1,14 @@
 use bevy::prelude::*;
+use bevy_drone_sim::prefab_asset_library::PrefabAssetLibrary;
 use bevy_drone_sim::rotating_cube_plugin::{Cube, RotatingCubePlugin};
+use bevy_drone_sim::save_migration::{SaveMigrationAppExt, SnapshotData};
 use bevy_drone_sim::save_system_plugin::SaveSystemPlugin;
 use bevy_drone_sim::save_system_plugin::{ApplyFlow, CanSaveLoad, CaptureFlow};
 use bevy_inspector_egui::bevy_egui::EguiPlugin;
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
 use bevy_save::prelude::*;
 
+/// `F2` opens the save browser: save to named slots, load or delete them.
 fn main() {
     App::new()
         .add_plugins(DefaultPlugins)
@@ -16,6 +19,8 @@ fn main() {
         .add_plugins(SaveSystemPlugin)
         .add_flows(CaptureFlow, save_cube)
         .add_flows(ApplyFlow, load_cube)
+        .add_save_migration(0, "version header, snapshot unchanged", |_| {})
+        .add_save_migration(1, "cubes keep their names", add_cube_name)
         .add_plugins(RotatingCubePlugin)
         // types
         .register_type::<Cube>()
@@ -23,6 +28,11 @@ fn main() {
         .run();
 }
 
+/// Cubes from saves before the names were saved get the name loading used to give them.
+fn add_cube_name(snapshot: &mut SnapshotData) {
+    snapshot.add_field(CubePrefab::type_path(), "name", serde_json::json!("Cube"));
+}
+
 fn save_cube(In(cap): In<Builder>, world: &World) -> Builder {
     cap.scope(world, |b| {
         b.extract_all_prefabs::<CubePrefab>().clear_empty()
@@ -39,6 +49,7 @@ fn load_cube(In(apply): In<Applier<'static>>, world: &mut World) -> Applier<'sta
 
 #[derive(Reflect, Default)]
 struct CubePrefab {
+    name: String,
     color: Color,
     transform: Transform,
     rotation_speed_coef: f32,
@@ -49,17 +60,21 @@ impl Prefab for CubePrefab {
 
     /// What components will be added to the entity when it's loaded from the save file.
     fn spawn(self, target: Entity, world: &mut World) {
-        let mut meshes = world.resource_mut::<Assets<Mesh>>();
-        let handle = meshes.add(Cuboid::default()); // TODO: Looks like bad idea to create a new mesh every time. How to reuse it?
-
-        let mut materials = world.resource_mut::<Assets<StandardMaterial>>();
-        let mat_handle = materials.add(self.color); // Looks like a bad idea to create a new material every time. TODO: How to reuse it?
+        // Shared by every load, cubes of the same colour share the material too. It's recreated
+        // if it was edited in the inspector, so the cube gets the saved colour
+        let handle = PrefabAssetLibrary::world_mesh(world, "cube", || Cuboid::default().into());
+        let mat_handle = PrefabAssetLibrary::world_material_matching(
+            world,
+            &format!("cube {}", self.color.to_srgba().to_hex()),
+            |material| material.base_color == self.color,
+            || self.color.into(),
+        );
 
         world.entity_mut(target).insert((
             Cube {
                 rotation_speed_coef: self.rotation_speed_coef,
             },
-            Name::new("Cube"),
+            Name::new(self.name),
             Mesh3d(handle),
             MeshMaterial3d(mat_handle),
             self.transform,
@@ -76,6 +91,9 @@ impl Prefab for CubePrefab {
             let material = materials_asset_server.get(&material_handle)?;
             let transform = entity.get::<Transform>().cloned().unwrap_or_default();
             Some(CubePrefab {
+                name: entity
+                    .get::<Name>()
+                    .map_or_else(|| "Cube".to_string(), |name| name.to_string()),
                 color: material.base_color,
                 transform,
                 rotation_speed_coef: entity
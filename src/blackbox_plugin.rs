@@ -0,0 +1,242 @@
+use crate::drone_plugin::{
+    DroneBattery, DronePosition, DroneSensors, DroneSet, MotorOutputs, PlayerDrone, local_to_frd,
+};
+use crate::flight_controller_plugin::FlightController;
+use bevy::prelude::*;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+/// Records per-loop flight data of the player drone to CSV files using Betaflight's
+/// blackbox field names, so sim flights can be analysed with the same tools as real ones.
+///
+/// Logging starts when the flight controller arms and stops when it disarms, like
+/// Betaflight does. `B` toggles logging manually, e.g. for drones without a flight controller.
+pub struct BlackboxPlugin;
+
+impl Plugin for BlackboxPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BlackboxSettings>()
+            .init_resource::<Blackbox>()
+            .add_systems(Update, (toggle_logging, follow_arming))
+            .add_systems(FixedUpdate, log_flight_data.after(DroneSet::Sensors))
+            .register_type::<BlackboxSettings>();
+    }
+}
+
+#[derive(Resource, Reflect, Clone)]
+#[reflect(Resource)]
+pub struct BlackboxSettings {
+    pub directory: String,
+    /// Log every n-th loop, like Betaflight's `blackbox_sample_rate`.
+    pub sample_divisor: u32,
+}
+
+impl Default for BlackboxSettings {
+    fn default() -> Self {
+        Self {
+            directory: "blackbox".to_string(),
+            sample_divisor: 1,
+        }
+    }
+}
+
+/// The currently open log, if any.
+#[derive(Resource, Default)]
+pub struct Blackbox {
+    log: Option<BlackboxLog>,
+}
+
+impl Blackbox {
+    pub fn is_logging(&self) -> bool {
+        self.log.is_some()
+    }
+}
+
+struct BlackboxLog {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    loop_iteration: u64,
+}
+
+const FIELDS: &[&str] = &[
+    "loopIteration",
+    "time",
+    "axisP[0]",
+    "axisP[1]",
+    "axisP[2]",
+    "axisI[0]",
+    "axisI[1]",
+    "axisI[2]",
+    "axisD[0]",
+    "axisD[1]",
+    "axisD[2]",
+    "axisF[0]",
+    "axisF[1]",
+    "axisF[2]",
+    "rcCommand[0]",
+    "rcCommand[1]",
+    "rcCommand[2]",
+    "rcCommand[3]",
+    "setpoint[0]",
+    "setpoint[1]",
+    "setpoint[2]",
+    "setpoint[3]",
+    "vbatLatest",
+    "amperageLatest",
+    "energyCumulative",
+    "gyroADC[0]",
+    "gyroADC[1]",
+    "gyroADC[2]",
+    "motor[0]",
+    "motor[1]",
+    "motor[2]",
+    "motor[3]",
+];
+
+fn start_log(blackbox: &mut Blackbox, settings: &BlackboxSettings) {
+    if blackbox.is_logging() {
+        return;
+    }
+    if let Err(e) = std::fs::create_dir_all(&settings.directory) {
+        error!(
+            "Failed to create the blackbox directory {}: {e}",
+            settings.directory
+        );
+        return;
+    }
+
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let path = PathBuf::from(&settings.directory).join(format!("LOG_{timestamp}.csv"));
+    // Never truncate an earlier log
+    let mut writer = match File::create_new(&path) {
+        Ok(file) => BufWriter::new(file),
+        Err(e) => {
+            error!("Failed to create blackbox log {}: {e}", path.display());
+            return;
+        }
+    };
+    if let Err(e) = writeln!(writer, "{}", FIELDS.join(",")) {
+        error!("Failed to write blackbox header to {}: {e}", path.display());
+        return;
+    }
+
+    info!("Blackbox logging to {}", path.display());
+    blackbox.log = Some(BlackboxLog {
+        path,
+        writer,
+        loop_iteration: 0,
+    });
+}
+
+fn stop_log(blackbox: &mut Blackbox) {
+    let Some(mut log) = blackbox.log.take() else {
+        return;
+    };
+    match log.writer.flush() {
+        Ok(()) => info!("Blackbox log saved to {}", log.path.display()),
+        Err(e) => error!("Failed to flush blackbox log {}: {e}", log.path.display()),
+    }
+}
+
+fn toggle_logging(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    settings: Res<BlackboxSettings>,
+    mut blackbox: ResMut<Blackbox>,
+) {
+    if !keyboard.just_pressed(KeyCode::KeyB) {
+        return;
+    }
+    if blackbox.is_logging() {
+        stop_log(&mut blackbox);
+    } else {
+        start_log(&mut blackbox, &settings);
+    }
+}
+
+fn follow_arming(
+    settings: Res<BlackboxSettings>,
+    mut blackbox: ResMut<Blackbox>,
+    fc: Query<&FlightController, (With<PlayerDrone>, Changed<FlightController>)>,
+    mut was_armed: Local<bool>,
+) {
+    let Ok(fc) = fc.single() else {
+        return;
+    };
+    if fc.armed && !*was_armed {
+        start_log(&mut blackbox, &settings);
+    } else if !fc.armed && *was_armed {
+        stop_log(&mut blackbox);
+    }
+    *was_armed = fc.armed;
+}
+
+fn log_flight_data(
+    settings: Res<BlackboxSettings>,
+    mut blackbox: ResMut<Blackbox>,
+    drone: Query<
+        (
+            &DronePosition,
+            &DroneSensors,
+            &DroneBattery,
+            Option<&MotorOutputs>,
+            Option<&FlightController>,
+        ),
+        With<PlayerDrone>,
+    >,
+    time: Res<Time>,
+) {
+    let Some(log) = blackbox.log.as_mut() else {
+        return;
+    };
+    let Ok((sticks, sensors, battery, motors, fc)) = drone.single() else {
+        debug!("No drone found to log blackbox data for.");
+        return;
+    };
+
+    log.loop_iteration += 1;
+    if log.loop_iteration % settings.sample_divisor.max(1) as u64 != 0 {
+        return;
+    }
+
+    let pid_terms = fc.map(|fc| fc.pid_terms).unwrap_or_default();
+    let gyro = local_to_frd(sensors.gyro) * 180.0 / std::f32::consts::PI;
+    let setpoint = fc.map_or(Vec3::ZERO, |fc| fc.setpoint);
+    let motors = motors.map(|motors| motors.0).unwrap_or_default();
+
+    let mut row: Vec<String> = vec![
+        log.loop_iteration.to_string(),
+        time.elapsed().as_micros().to_string(),
+    ];
+    row.extend(pid_terms.iter().map(|terms| format!("{:.0}", terms.p)));
+    row.extend(pid_terms.iter().map(|terms| format!("{:.0}", terms.i)));
+    row.extend(pid_terms.iter().map(|terms| format!("{:.0}", terms.d)));
+    row.extend(pid_terms.iter().map(|terms| format!("{:.0}", terms.f)));
+    // rcCommand: -500..500 for the axes and 1000..2000 for the throttle
+    row.extend(
+        [sticks.roll, sticks.pitch, sticks.yaw]
+            .iter()
+            .map(|stick| format!("{:.0}", stick * 500.0)),
+    );
+    row.push(format!("{:.0}", 1000.0 + sticks.throttle * 1000.0));
+    row.extend(setpoint.to_array().iter().map(|rate| format!("{rate:.0}")));
+    row.push(format!("{:.0}", sticks.throttle * 1000.0));
+    // Betaflight logs the battery in 0.01 V and 0.01 A
+    row.push(format!("{:.0}", battery.voltage * 100.0));
+    row.push(format!("{:.0}", battery.current_a * 100.0));
+    row.push(format!("{:.0}", battery.consumed_mah));
+    row.extend(gyro.to_array().iter().map(|rate| format!("{rate:.0}")));
+    row.extend(
+        motors
+            .iter()
+            .map(|motor| format!("{:.0}", 1000.0 + motor * 1000.0)),
+    );
+
+    if let Err(e) = writeln!(log.writer, "{}", row.join(",")) {
+        error!("Failed to write blackbox log {}: {e}", log.path.display());
+        stop_log(&mut blackbox);
+    }
+}
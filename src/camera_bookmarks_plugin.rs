@@ -0,0 +1,485 @@
+use crate::free_camera_plugin::{CameraRotation, SceneCamera, scene_camera};
+use crate::level_plugin::{LevelLoaded, level_file_stem};
+use bevy::prelude::*;
+use bevy_inspector_egui::bevy_egui::{EguiContexts, EguiPrimaryContextPass};
+use bevy_inspector_egui::egui;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Number keys of the bookmark slots, `Digit1` is slot 1.
+const SLOT_KEYS: [KeyCode; 9] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+];
+
+/// Named poses of the scene camera, and spline camera paths flying through them.
+///
+/// `Ctrl` + `1`–`9` bookmarks the current view, `1`–`9` jumps back to it. `P` plays the path
+/// through all bookmarks in list order and `K` opens the bookmark list. Every level keeps its
+/// own bookmarks in [`CameraBookmarkSettings::directory`], they are swapped in when a level
+/// finishes loading.
+pub struct CameraBookmarksPlugin;
+
+impl Plugin for CameraBookmarksPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CameraBookmarkSettings>()
+            .init_resource::<CameraBookmarks>()
+            .init_resource::<CameraPath>()
+            // Also sent without `LevelPlugin`, so apps without levels keep working
+            .add_event::<LevelLoaded>()
+            .add_systems(Startup, load_default_bookmarks)
+            .add_systems(
+                Update,
+                (load_level_bookmarks, handle_bookmark_keys, save_bookmarks).chain(),
+            )
+            // After everything else moving the camera during `Update`
+            .add_systems(
+                PostUpdate,
+                play_camera_path.before(TransformSystem::TransformPropagate),
+            )
+            .add_systems(EguiPrimaryContextPass, bookmarks_window)
+            .register_type::<CameraBookmarkSettings>()
+            .register_type::<CameraBookmarks>()
+            .register_type::<CameraBookmark>()
+            .register_type::<CameraPath>();
+    }
+}
+
+#[derive(Resource, Reflect, Clone)]
+#[reflect(Resource)]
+pub struct CameraBookmarkSettings {
+    /// Directory with a bookmark file per level.
+    pub directory: String,
+    /// Time the camera path takes from one bookmark to the next, in seconds.
+    pub segment_secs: f32,
+    /// Whether the camera path starts over after the last bookmark.
+    pub looping: bool,
+}
+
+impl Default for CameraBookmarkSettings {
+    fn default() -> Self {
+        Self {
+            directory: "saves/camera_bookmarks".to_string(),
+            segment_secs: 3.0,
+            looping: false,
+        }
+    }
+}
+
+impl CameraBookmarkSettings {
+    /// Bookmark file of the level at asset path `level`, or of apps without levels.
+    pub fn file(&self, level: Option<&str>) -> PathBuf {
+        let name = level.map_or_else(|| "default".to_string(), level_file_stem);
+        Path::new(&self.directory).join(format!("{name}.ron"))
+    }
+}
+
+#[derive(Resource, Reflect, Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[reflect(Resource)]
+pub struct CameraBookmarks {
+    /// In camera path order.
+    pub bookmarks: Vec<CameraBookmark>,
+    /// Asset path of the level the bookmarks belong to.
+    #[serde(skip)]
+    pub level: Option<String>,
+}
+
+#[derive(Reflect, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CameraBookmark {
+    pub name: String,
+    /// Number key of the bookmark, if it has one.
+    pub slot: Option<u8>,
+    pub translation: Vec3,
+    pub rotation: Quat,
+}
+
+impl CameraBookmark {
+    fn transform(&self) -> Transform {
+        Transform::from_translation(self.translation).with_rotation(self.rotation)
+    }
+}
+
+/// Playback of the camera path through the bookmarks.
+#[derive(Resource, Reflect, Debug, Default, Clone)]
+#[reflect(Resource)]
+pub struct CameraPath {
+    pub playing: bool,
+    pub elapsed_secs: f32,
+}
+
+impl CameraPath {
+    pub fn play(&mut self) {
+        self.playing = true;
+        self.elapsed_secs = 0.0;
+    }
+}
+
+/// Pose along the Catmull-Rom spline through `bookmarks`, `t` going from `0` at the first
+/// bookmark to `bookmarks.len() - 1` at the last.
+pub fn sample_camera_path(bookmarks: &[CameraBookmark], t: f32) -> Option<Transform> {
+    let segments = bookmarks.len().checked_sub(1).filter(|&n| n > 0)?;
+    let curve = CubicCardinalSpline::new_catmull_rom(bookmarks.iter().map(|b| b.translation))
+        .to_curve()
+        .ok()?;
+    let t = t.clamp(0.0, segments as f32);
+    let index = (t.floor() as usize).min(segments - 1);
+    // Ease the turn in and out of every bookmark
+    let blend = (t - index as f32).clamp(0.0, 1.0);
+    let blend = blend * blend * (3.0 - 2.0 * blend);
+    let rotation = bookmarks[index]
+        .rotation
+        .slerp(bookmarks[index + 1].rotation, blend);
+    Some(Transform::from_translation(curve.position(t)).with_rotation(rotation))
+}
+
+fn load_default_bookmarks(
+    settings: Res<CameraBookmarkSettings>,
+    mut bookmarks: ResMut<CameraBookmarks>,
+) {
+    *bookmarks = read_bookmarks(&settings.file(None)).unwrap_or_default();
+}
+
+fn load_level_bookmarks(
+    settings: Res<CameraBookmarkSettings>,
+    mut bookmarks: ResMut<CameraBookmarks>,
+    mut path: ResMut<CameraPath>,
+    mut loaded: EventReader<LevelLoaded>,
+) {
+    let Some(LevelLoaded(level)) = loaded.read().last() else {
+        return;
+    };
+    path.playing = false;
+    // Not a change, the bookmarks would be written right back
+    *bookmarks.bypass_change_detection() = CameraBookmarks {
+        level: Some(level.clone()),
+        ..read_bookmarks(&settings.file(Some(level))).unwrap_or_default()
+    };
+}
+
+fn read_bookmarks(path: &Path) -> Option<CameraBookmarks> {
+    if !path.exists() {
+        debug!("No camera bookmarks at {}.", path.display());
+        return None;
+    }
+    let loaded: CameraBookmarks = std::fs::read_to_string(path)
+        .inspect_err(|e| {
+            error!(
+                "Failed to read the camera bookmarks {}: {e}",
+                path.display()
+            )
+        })
+        .ok()
+        .and_then(|text| {
+            ron::de::from_str(&text)
+                .inspect_err(|e| error!("Invalid camera bookmarks {}: {e}", path.display()))
+                .ok()
+        })?;
+    info!(
+        "Loaded {} camera bookmarks from {}",
+        loaded.bookmarks.len(),
+        path.display()
+    );
+    Some(loaded)
+}
+
+fn save_bookmarks(settings: Res<CameraBookmarkSettings>, bookmarks: Res<CameraBookmarks>) {
+    // Not when the bookmarks were just loaded or initialised
+    if !bookmarks.is_changed() || bookmarks.is_added() {
+        return;
+    }
+    let path = settings.file(bookmarks.level.as_deref());
+    if let Some(directory) = path.parent() {
+        if let Err(e) = std::fs::create_dir_all(directory) {
+            error!(
+                "Failed to create the bookmark directory {}: {e}",
+                directory.display()
+            );
+            return;
+        }
+    }
+    let text = match ron::ser::to_string_pretty(&*bookmarks, ron::ser::PrettyConfig::default()) {
+        Ok(text) => text,
+        Err(e) => {
+            error!("Failed to serialize the camera bookmarks: {e}");
+            return;
+        }
+    };
+    if let Err(e) = std::fs::write(&path, text) {
+        error!(
+            "Failed to save the camera bookmarks to {}: {e}",
+            path.display()
+        );
+    }
+}
+
+/// Moves the scene camera to `pose`, keeping the free camera's mouse look in sync.
+fn move_camera(
+    cameras: &Query<(Entity, &Camera, Has<SceneCamera>)>,
+    transforms: &mut Query<(&mut Transform, Option<&mut CameraRotation>)>,
+    pose: Transform,
+) {
+    let Some(Ok((mut transform, rotation))) =
+        scene_camera(cameras).map(|entity| transforms.get_mut(entity))
+    else {
+        debug!("No scene camera found to move to the bookmark.");
+        return;
+    };
+    *transform = pose;
+    if let Some(mut rotation) = rotation {
+        rotation.look_along(pose.rotation);
+    }
+}
+
+fn handle_bookmark_keys(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut contexts: EguiContexts,
+    mut bookmarks: ResMut<CameraBookmarks>,
+    mut path: ResMut<CameraPath>,
+    cameras: Query<(Entity, &Camera, Has<SceneCamera>)>,
+    mut transforms: Query<(&mut Transform, Option<&mut CameraRotation>)>,
+) {
+    // Typing a bookmark name must not move the camera
+    if contexts
+        .ctx_mut()
+        .is_ok_and(|ctx| ctx.wants_keyboard_input())
+    {
+        return;
+    }
+    if keyboard.just_pressed(KeyCode::KeyP) {
+        if path.playing {
+            path.playing = false;
+            info!("Camera path stopped");
+        } else if bookmarks.bookmarks.len() < 2 {
+            warn!("A camera path needs at least two bookmarks.");
+        } else {
+            path.play();
+            info!(
+                "Playing the camera path through {} bookmarks",
+                bookmarks.bookmarks.len()
+            );
+        }
+    }
+
+    let Some(slot) = SLOT_KEYS
+        .iter()
+        .position(|key| keyboard.just_pressed(*key))
+        .map(|index| index as u8 + 1)
+    else {
+        return;
+    };
+
+    if keyboard.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+        let Some((transform, _)) =
+            scene_camera(&cameras).and_then(|entity| transforms.get(entity).ok())
+        else {
+            warn!("No scene camera found to bookmark.");
+            return;
+        };
+        let bookmark = CameraBookmark {
+            name: format!("Bookmark {slot}"),
+            slot: Some(slot),
+            translation: transform.translation,
+            rotation: transform.rotation,
+        };
+        match bookmarks
+            .bookmarks
+            .iter_mut()
+            .find(|bookmark| bookmark.slot == Some(slot))
+        {
+            Some(existing) => {
+                existing.translation = bookmark.translation;
+                existing.rotation = bookmark.rotation;
+            }
+            None => bookmarks.bookmarks.push(bookmark),
+        }
+        info!("Camera bookmark {slot} saved");
+    } else if let Some(bookmark) = bookmarks
+        .bookmarks
+        .iter()
+        .find(|bookmark| bookmark.slot == Some(slot))
+    {
+        path.playing = false;
+        move_camera(&cameras, &mut transforms, bookmark.transform());
+        info!("Camera moved to '{}'", bookmark.name);
+    } else {
+        info!("No camera bookmark in slot {slot}, press 'Ctrl+{slot}' to save one.");
+    }
+}
+
+fn play_camera_path(
+    settings: Res<CameraBookmarkSettings>,
+    bookmarks: Res<CameraBookmarks>,
+    mut path: ResMut<CameraPath>,
+    cameras: Query<(Entity, &Camera, Has<SceneCamera>)>,
+    mut transforms: Query<(&mut Transform, Option<&mut CameraRotation>)>,
+    // Real time, so paths also fly through a paused or slowed down simulation
+    time: Res<Time<Real>>,
+) {
+    if !path.playing {
+        return;
+    }
+    let segments = bookmarks.bookmarks.len().saturating_sub(1);
+    let segment_secs = settings.segment_secs.max(0.1);
+    let Some(pose) = sample_camera_path(&bookmarks.bookmarks, path.elapsed_secs / segment_secs)
+    else {
+        path.playing = false;
+        return;
+    };
+    move_camera(&cameras, &mut transforms, pose);
+
+    path.elapsed_secs += time.delta_secs();
+    let duration = segments as f32 * segment_secs;
+    if path.elapsed_secs > duration {
+        if settings.looping {
+            path.elapsed_secs %= duration;
+        } else {
+            path.playing = false;
+            info!("Camera path finished");
+        }
+    }
+}
+
+/// Edits made in the bookmark list, applied after the window is drawn.
+enum BookmarkAction {
+    Go(usize),
+    Update(usize),
+    Remove(usize),
+    MoveUp(usize),
+    Add,
+}
+
+#[allow(clippy::too_many_arguments)]
+fn bookmarks_window(
+    mut contexts: EguiContexts,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<CameraBookmarkSettings>,
+    mut bookmarks: ResMut<CameraBookmarks>,
+    mut path: ResMut<CameraPath>,
+    cameras: Query<(Entity, &Camera, Has<SceneCamera>)>,
+    mut transforms: Query<(&mut Transform, Option<&mut CameraRotation>)>,
+    mut open: Local<bool>,
+) {
+    let Ok(ctx) = contexts.ctx_mut() else {
+        debug!("No Egui context found for the camera bookmarks.");
+        return;
+    };
+    if keyboard.just_pressed(KeyCode::KeyK) && !ctx.wants_keyboard_input() {
+        *open = !*open;
+    }
+    if !*open {
+        return;
+    }
+
+    // Rename copies so the bookmarks are only saved when a name actually changed
+    let mut names: Vec<String> = bookmarks.bookmarks.iter().map(|b| b.name.clone()).collect();
+    let mut actions = Vec::new();
+    let mut play = None;
+    let mut still_open = true;
+    egui::Window::new("Camera Bookmarks")
+        .open(&mut still_open)
+        .show(ctx, |ui| {
+            for (index, name) in names.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    let slot = bookmarks.bookmarks[index]
+                        .slot
+                        .map_or("-".to_string(), |slot| slot.to_string());
+                    ui.label(slot);
+                    ui.add(egui::TextEdit::singleline(name).desired_width(120.0));
+                    if ui.button("Go").clicked() {
+                        actions.push(BookmarkAction::Go(index));
+                    }
+                    if ui.button("Update").clicked() {
+                        actions.push(BookmarkAction::Update(index));
+                    }
+                    if index > 0 && ui.button("Up").clicked() {
+                        actions.push(BookmarkAction::MoveUp(index));
+                    }
+                    if ui.button("Remove").clicked() {
+                        actions.push(BookmarkAction::Remove(index));
+                    }
+                });
+            }
+            if ui.button("Add current view").clicked() {
+                actions.push(BookmarkAction::Add);
+            }
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label("Seconds per bookmark");
+                ui.add(egui::DragValue::new(&mut settings.segment_secs).range(0.1..=60.0));
+                ui.checkbox(&mut settings.looping, "Loop");
+            });
+            let label = if path.playing {
+                "Stop path"
+            } else {
+                "Play path"
+            };
+            if ui
+                .add_enabled(bookmarks.bookmarks.len() >= 2, egui::Button::new(label))
+                .clicked()
+            {
+                play = Some(!path.playing);
+            }
+        });
+    *open &= still_open;
+
+    if bookmarks
+        .bookmarks
+        .iter()
+        .zip(&names)
+        .any(|(bookmark, name)| bookmark.name != *name)
+    {
+        for (bookmark, name) in bookmarks.bookmarks.iter_mut().zip(names) {
+            bookmark.name = name;
+        }
+    }
+
+    let current_pose = scene_camera(&cameras)
+        .and_then(|entity| transforms.get(entity).ok())
+        .map(|(transform, _)| *transform);
+    for action in actions {
+        match action {
+            BookmarkAction::Go(index) => {
+                path.playing = false;
+                let pose = bookmarks.bookmarks[index].transform();
+                move_camera(&cameras, &mut transforms, pose);
+            }
+            BookmarkAction::Update(index) => {
+                if let Some(pose) = current_pose {
+                    bookmarks.bookmarks[index].translation = pose.translation;
+                    bookmarks.bookmarks[index].rotation = pose.rotation;
+                }
+            }
+            BookmarkAction::Remove(index) => {
+                bookmarks.bookmarks.remove(index);
+            }
+            BookmarkAction::MoveUp(index) => {
+                bookmarks.bookmarks.swap(index - 1, index);
+            }
+            BookmarkAction::Add => {
+                if let Some(pose) = current_pose {
+                    let name = format!("View {}", bookmarks.bookmarks.len() + 1);
+                    bookmarks.bookmarks.push(CameraBookmark {
+                        name,
+                        slot: None,
+                        translation: pose.translation,
+                        rotation: pose.rotation,
+                    });
+                }
+            }
+        }
+    }
+
+    match play {
+        Some(true) => path.play(),
+        Some(false) => path.playing = false,
+        None => {}
+    }
+}
@@ -0,0 +1,155 @@
+use crate::drone_plugin::PlayerDrone;
+use crate::free_camera_plugin::{FreeCameraMode, SceneCamera, scene_camera};
+use bevy::prelude::*;
+
+/// Chase, orbit and line-of-sight modes for the scene camera, cycled with `Tab`.
+///
+/// The camera is left alone in [`CameraMode::Fixed`] and while the free camera controls of
+/// `FreeCameraPlugin` are enabled.
+pub struct CameraModesPlugin;
+
+impl Plugin for CameraModesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CameraMode>()
+            .init_resource::<CameraModeSettings>()
+            .add_systems(Update, (cycle_camera_mode, move_scene_camera).chain())
+            .register_type::<CameraMode>()
+            .register_type::<CameraModeSettings>();
+    }
+}
+
+#[derive(Resource, Reflect, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[reflect(Resource)]
+pub enum CameraMode {
+    /// The camera stays where the scene placed it.
+    #[default]
+    Fixed,
+    /// Follows behind the drone, smoothed.
+    Chase,
+    /// Circles around the target.
+    Orbit,
+    /// Stands at the pilot position and turns to keep the drone in view.
+    LineOfSight,
+}
+
+impl CameraMode {
+    fn next(self) -> Self {
+        match self {
+            CameraMode::Fixed => CameraMode::Chase,
+            CameraMode::Chase => CameraMode::Orbit,
+            CameraMode::Orbit => CameraMode::LineOfSight,
+            CameraMode::LineOfSight => CameraMode::Fixed,
+        }
+    }
+}
+
+#[derive(Resource, Reflect, Clone)]
+#[reflect(Resource)]
+pub struct CameraModeSettings {
+    /// `Name` of the entity the camera follows, the player drone if unset.
+    pub target: Option<String>,
+    /// Chase offset behind and above the target, in meters.
+    pub chase_distance: f32,
+    pub chase_height: f32,
+    /// How quickly the chase camera catches up, per second.
+    pub chase_smoothing: f32,
+    pub orbit_radius: f32,
+    pub orbit_height: f32,
+    pub orbit_speed_degrees: f32,
+    /// Where the pilot stands in line-of-sight mode, at eye height.
+    pub pilot_position: Vec3,
+}
+
+impl Default for CameraModeSettings {
+    fn default() -> Self {
+        Self {
+            target: None,
+            chase_distance: 2.0,
+            chase_height: 0.7,
+            chase_smoothing: 5.0,
+            orbit_radius: 5.0,
+            orbit_height: 2.0,
+            orbit_speed_degrees: 20.0,
+            pilot_position: Vec3::new(-2.0, 1.7, -5.0),
+        }
+    }
+}
+
+fn cycle_camera_mode(keyboard: Res<ButtonInput<KeyCode>>, mut mode: ResMut<CameraMode>) {
+    if keyboard.just_pressed(KeyCode::Tab) {
+        *mode = mode.next();
+        info!("Camera mode: {:?}", *mode);
+    }
+}
+
+fn move_scene_camera(
+    mode: Res<CameraMode>,
+    settings: Res<CameraModeSettings>,
+    free_camera: Option<Res<FreeCameraMode>>,
+    cameras: Query<(Entity, &Camera, Has<SceneCamera>)>,
+    mut transforms: Query<&mut Transform>,
+    targets: Query<(Entity, &Name, Has<PlayerDrone>)>,
+    time: Res<Time>,
+    mut orbit_angle: Local<f32>,
+    mut fixed_pose: Local<Option<Transform>>,
+) {
+    if free_camera.is_some_and(|free_camera| free_camera.enabled) {
+        return;
+    }
+    let Some(camera) = scene_camera(&cameras) else {
+        debug!("No scene camera found to move.");
+        return;
+    };
+
+    // Put the camera back where the scene placed it when cycling back to fixed
+    if *mode == CameraMode::Fixed {
+        if let (Some(pose), Ok(mut transform)) = (fixed_pose.take(), transforms.get_mut(camera)) {
+            *transform = pose;
+        }
+        return;
+    }
+
+    let target = targets
+        .iter()
+        .find(|(_, name, is_player)| match &settings.target {
+            Some(target) => name.as_str() == target,
+            None => *is_player,
+        })
+        .and_then(|(entity, ..)| transforms.get(entity).ok().copied());
+    let Some(target) = target else {
+        debug!("No camera target {:?} found.", settings.target);
+        return;
+    };
+    let Ok(mut transform) = transforms.get_mut(camera) else {
+        return;
+    };
+    if fixed_pose.is_none() {
+        *fixed_pose = Some(*transform);
+    }
+
+    let dt = time.delta_secs();
+    match *mode {
+        CameraMode::Fixed => {}
+        CameraMode::Chase => {
+            // Follow the heading only, so the view doesn't roll and pitch with the drone
+            let forward = target.forward().with_y(0.0).normalize_or(Vec3::NEG_Z);
+            let desired = target.translation - forward * settings.chase_distance
+                + Vec3::Y * settings.chase_height;
+            let blend = 1.0 - (-settings.chase_smoothing * dt).exp();
+            transform.translation = transform.translation.lerp(desired, blend);
+            transform.look_at(target.translation, Vec3::Y);
+        }
+        CameraMode::Orbit => {
+            *orbit_angle += settings.orbit_speed_degrees.to_radians() * dt;
+            let offset = Vec3::new(orbit_angle.cos(), 0.0, orbit_angle.sin())
+                * settings.orbit_radius
+                + Vec3::Y * settings.orbit_height;
+            transform.translation = target.translation + offset;
+            transform.look_at(target.translation, Vec3::Y);
+        }
+        CameraMode::LineOfSight => {
+            transform.translation = settings.pilot_position;
+            transform.look_at(target.translation, Vec3::Y);
+        }
+    }
+}
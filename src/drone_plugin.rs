@@ -0,0 +1,464 @@
+use bevy::input::gamepad::GamepadInput;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Standard gravity used by the drone dynamics and the simulated accelerometer.
+pub const GRAVITY_MPS2: f32 = 9.81;
+
+/// Drone entity, its stick inputs, motor dynamics and simulated sensors.
+pub struct DronePlugin;
+
+impl Plugin for DronePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GeoOrigin>()
+            .init_resource::<DroneInputSource>()
+            .add_systems(
+                Update,
+                (
+                    update_drone_controls
+                        .run_if(resource_equals(DroneInputSource::Gamepad))
+                        .in_set(DroneInputSet),
+                    rotate_drone_system.after(DroneInputSet),
+                ),
+            )
+            .configure_sets(FixedUpdate, (DroneSet::Dynamics, DroneSet::Sensors).chain())
+            .add_systems(
+                FixedUpdate,
+                (
+                    (integrate_motor_dynamics, derive_kinematics_from_transform)
+                        .chain()
+                        .in_set(DroneSet::Dynamics),
+                    (update_drone_sensors, drain_battery).in_set(DroneSet::Sensors),
+                ),
+            )
+            // Register types for reflection
+            .register_type::<PlayerDrone>()
+            .register_type::<DronePosition>()
+            .register_type::<ExternallyPositioned>()
+            .register_type::<MotorOutputs>()
+            .register_type::<DroneFrame>()
+            .register_type::<DroneKinematics>()
+            .register_type::<DroneSensors>()
+            .register_type::<DroneBattery>()
+            .register_type::<GeoOrigin>()
+            .register_type::<DroneInputSource>();
+    }
+}
+
+/// Fixed-step stages of the drone simulation, used to order external controllers around them.
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DroneSet {
+    /// Motor commands are turned into motion.
+    Dynamics,
+    /// Simulated sensors are updated from the new state.
+    Sensors,
+}
+
+/// Systems writing [`DronePosition`] from an input source run in this set during `Update`.
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DroneInputSet;
+
+/// Marks the drone controlled by the local pilot.
+#[derive(Component, Reflect)]
+pub struct PlayerDrone;
+
+/// Where the player drone's [`DronePosition`] comes from.
+#[derive(Resource, Reflect, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[reflect(Resource)]
+pub enum DroneInputSource {
+    #[default]
+    Gamepad,
+    /// CRSF or SBUS frames from a radio, see `RcInputPlugin`.
+    RcReceiver,
+    /// Control packets over UDP or WebSocket, see `NetworkInputPlugin`.
+    Network,
+    /// A recorded session played back by `ReplayPlugin`.
+    Replay,
+}
+
+/// Marks a drone whose pose is set by an autopilot, so the sticks don't move it.
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+pub struct ExternallyPositioned;
+
+/// Stick positions of the drone. Throttle is in `0..=1`, the other axes in `-1..=1`.
+#[derive(Debug, Default, Clone, Copy, Component, Reflect, Serialize, Deserialize)]
+#[require(DroneKinematics, DroneSensors, DroneBattery)]
+pub struct DronePosition {
+    pub throttle: f32,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub roll: f32,
+}
+
+/// Normalized (`0..=1`) motor commands in PX4 quad-x order:
+/// front-right (CCW), rear-left (CCW), front-left (CW), rear-right (CW).
+///
+/// Drones with this component are moved by [`integrate_motor_dynamics`] instead of the sticks.
+#[derive(Debug, Default, Clone, Copy, Component, Reflect)]
+#[reflect(Component)]
+#[require(DroneFrame)]
+pub struct MotorOutputs(pub [f32; 4]);
+
+/// Motor positions in the drone's local frame (x right, z back) and their spin direction
+/// (`1.0` for CCW seen from above), matching the [`MotorOutputs`] order.
+pub const MOTOR_LAYOUT: [(Vec2, f32); 4] = [
+    (Vec2::new(1.0, -1.0), 1.0),
+    (Vec2::new(-1.0, 1.0), 1.0),
+    (Vec2::new(-1.0, -1.0), -1.0),
+    (Vec2::new(1.0, 1.0), -1.0),
+];
+
+/// Physical parameters of the airframe used by the motor dynamics.
+#[derive(Debug, Clone, Component, Reflect)]
+#[reflect(Component)]
+pub struct DroneFrame {
+    pub mass_kg: f32,
+    /// Distance from the center to each motor, in meters.
+    pub arm_length_m: f32,
+    /// Thrust of a single motor at full command, in newtons.
+    pub max_motor_thrust_n: f32,
+    /// Reaction torque per newton of thrust, in meters.
+    pub yaw_torque_coef: f32,
+    /// Diagonal of the inertia tensor in the local frame, in kg*m^2.
+    pub inertia: Vec3,
+    pub linear_drag_coef: f32,
+    pub angular_drag_coef: f32,
+}
+
+impl Default for DroneFrame {
+    fn default() -> Self {
+        Self {
+            mass_kg: 0.65,
+            arm_length_m: 0.12,
+            max_motor_thrust_n: 7.0,
+            yaw_torque_coef: 0.016,
+            inertia: Vec3::new(0.003, 0.005, 0.003),
+            linear_drag_coef: 0.1,
+            angular_drag_coef: 0.002,
+        }
+    }
+}
+
+/// Velocities and acceleration of the drone, updated every fixed step.
+#[derive(Debug, Default, Clone, Component, Reflect, Serialize, Deserialize)]
+#[reflect(Component)]
+pub struct DroneKinematics {
+    /// World frame, m/s.
+    pub linear_velocity: Vec3,
+    /// Local frame, rad/s.
+    pub angular_velocity: Vec3,
+    /// World frame, m/s^2.
+    pub linear_acceleration: Vec3,
+    /// Transform at the previous fixed step, used to differentiate stick-driven motion.
+    last_transform: Option<Transform>,
+}
+
+/// Readings of the simulated onboard sensors, in the drone's local frame.
+#[derive(Debug, Default, Clone, Component, Reflect)]
+#[reflect(Component)]
+pub struct DroneSensors {
+    /// Specific force measured by the accelerometer, m/s^2.
+    pub accelerometer: Vec3,
+    /// Angular rate measured by the gyro, rad/s.
+    pub gyro: Vec3,
+    /// Earth magnetic field, gauss.
+    pub magnetometer: Vec3,
+    /// Static pressure, hPa.
+    pub pressure_hpa: f32,
+    /// Altitude above mean sea level, meters.
+    pub altitude_amsl_m: f32,
+    pub temperature_c: f32,
+}
+
+/// LiPo pack powering the drone, drained by the motors.
+#[derive(Debug, Clone, Component, Reflect, Serialize, Deserialize)]
+#[reflect(Component)]
+pub struct DroneBattery {
+    pub cell_count: u8,
+    pub capacity_mah: f32,
+    pub consumed_mah: f32,
+    /// Pack voltage under load, volts.
+    pub voltage: f32,
+    /// Current draw, amperes.
+    pub current_a: f32,
+    /// Current drawn by all motors at full command, amperes.
+    pub max_current_a: f32,
+    /// Internal resistance of the pack, ohms.
+    pub internal_resistance: f32,
+}
+
+impl Default for DroneBattery {
+    fn default() -> Self {
+        Self {
+            cell_count: 4,
+            capacity_mah: 1500.0,
+            consumed_mah: 0.0,
+            voltage: 4.2 * 4.0,
+            current_a: 0.0,
+            max_current_a: 120.0,
+            internal_resistance: 0.02,
+        }
+    }
+}
+
+impl DroneBattery {
+    const CELL_FULL_V: f32 = 4.2;
+    const CELL_EMPTY_V: f32 = 3.3;
+
+    /// Remaining charge in `0..=1`.
+    pub fn remaining(&self) -> f32 {
+        (1.0 - self.consumed_mah / self.capacity_mah).clamp(0.0, 1.0)
+    }
+
+    pub fn cell_voltage(&self) -> f32 {
+        self.voltage / self.cell_count as f32
+    }
+}
+
+/// Geographic location of the world origin, used to convert positions to GPS coordinates.
+#[derive(Debug, Clone, Resource, Reflect)]
+#[reflect(Resource)]
+pub struct GeoOrigin {
+    pub latitude_deg: f64,
+    pub longitude_deg: f64,
+    pub altitude_m: f32,
+    /// Earth magnetic field at the origin in NED, gauss.
+    pub magnetic_field_ned: Vec3,
+}
+
+impl Default for GeoOrigin {
+    fn default() -> Self {
+        // Same home as PX4's default SITL location (Zurich).
+        Self {
+            latitude_deg: 47.397742,
+            longitude_deg: 8.545594,
+            altitude_m: 488.0,
+            magnetic_field_ned: Vec3::new(0.21, 0.015, 0.43),
+        }
+    }
+}
+
+impl GeoOrigin {
+    const EARTH_RADIUS_M: f64 = 6_371_000.0;
+
+    /// Converts a world position to latitude, longitude (degrees) and altitude (meters, AMSL).
+    pub fn to_geodetic(&self, position: Vec3) -> (f64, f64, f32) {
+        let ned = world_to_ned(position);
+        let lat = self.latitude_deg + (ned.x as f64 / Self::EARTH_RADIUS_M).to_degrees();
+        let lon = self.longitude_deg
+            + (ned.y as f64 / (Self::EARTH_RADIUS_M * self.latitude_deg.to_radians().cos()))
+                .to_degrees();
+        (lat, lon, self.altitude_m - ned.z)
+    }
+}
+
+/// Converts a world vector (x east, y up, z south) to north-east-down.
+pub fn world_to_ned(v: Vec3) -> Vec3 {
+    Vec3::new(-v.z, v.x, -v.y)
+}
+
+/// Converts a local vector (x right, y up, z back) to forward-right-down.
+pub fn local_to_frd(v: Vec3) -> Vec3 {
+    Vec3::new(-v.z, v.x, -v.y)
+}
+
+/// Converts a drone rotation to the body FRD -> NED quaternion used by flight controllers.
+pub fn rotation_to_ned(rotation: Quat) -> Quat {
+    let world_to_ned = Mat3::from_cols(
+        Vec3::new(0.0, 1.0, 0.0),
+        Vec3::new(0.0, 0.0, -1.0),
+        Vec3::new(-1.0, 0.0, 0.0),
+    );
+    Quat::from_mat3(&(world_to_ned * Mat3::from_quat(rotation) * world_to_ned.transpose()))
+}
+
+/// Returns roll, pitch and yaw (radians) of a drone rotation in the aerospace convention.
+pub fn attitude_euler(rotation: Quat) -> Vec3 {
+    let (yaw, pitch, roll) = rotation_to_ned(rotation).to_euler(EulerRot::ZYX);
+    Vec3::new(roll, pitch, yaw)
+}
+
+fn update_drone_controls(
+    gamepad: Query<&Gamepad>,
+    mut controls: Query<&mut DronePosition, With<PlayerDrone>>,
+) {
+    let Ok(gamepad) = gamepad.single() else {
+        debug!("No gamepad found for drone controls.");
+        return;
+    };
+    let Ok(mut controls) = controls.single_mut() else {
+        debug!("No drone controls found.");
+        return;
+    };
+
+    let left_stick = gamepad.left_stick();
+    let right_stick = gamepad.right_stick();
+    let right_trigger2 = gamepad
+        .get(GamepadInput::Button(GamepadButton::RightTrigger2))
+        .unwrap_or(0.);
+
+    controls.throttle = right_trigger2;
+    controls.yaw = right_stick.x;
+    controls.pitch = -left_stick.y;
+    controls.roll = left_stick.x;
+}
+
+fn rotate_drone_system(
+    mut drone: Query<
+        (&mut Transform, &DronePosition),
+        (
+            With<PlayerDrone>,
+            Without<MotorOutputs>,
+            Without<ExternallyPositioned>,
+        ),
+    >,
+) {
+    let Ok((mut transform, drone_controls)) = drone.single_mut() else {
+        debug!("No drone entity found.");
+        return;
+    };
+
+    trace!("{drone_controls:?}");
+    // Get the gamepad's left stick input.
+    let DronePosition {
+        throttle: thrust,
+        pitch,
+        roll,
+        yaw,
+    } = drone_controls;
+
+    let coef = 1.0;
+    // Apply rotation based on the gamepad input.
+    transform.rotation = Quat::from_euler(EulerRot::YXZ, -yaw * coef, pitch * coef, roll * coef);
+    transform.translation.y = *thrust;
+}
+
+/// Moves drones driven by [`MotorOutputs`] with a simple rigid-body quadrotor model.
+fn integrate_motor_dynamics(
+    mut drones: Query<(
+        &mut Transform,
+        &mut DroneKinematics,
+        &MotorOutputs,
+        &DroneFrame,
+    )>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_secs();
+    if dt <= 0.0 {
+        return;
+    }
+
+    for (mut transform, mut kinematics, motors, frame) in drones.iter_mut() {
+        let mut thrust = 0.0;
+        let mut torque = Vec3::ZERO;
+        for (command, (position, spin)) in motors.0.iter().zip(MOTOR_LAYOUT) {
+            let motor_thrust = command.clamp(0.0, 1.0) * frame.max_motor_thrust_n;
+            let arm = position.normalize() * frame.arm_length_m;
+            // r x F for F along +Y, plus the reaction torque opposing the propeller spin
+            torque += Vec3::new(-arm.y * motor_thrust, 0.0, arm.x * motor_thrust);
+            torque.y -= spin * frame.yaw_torque_coef * motor_thrust;
+            thrust += motor_thrust;
+        }
+
+        // Linear motion in the world frame
+        let force = transform.rotation * Vec3::Y * thrust
+            - kinematics.linear_velocity * frame.linear_drag_coef;
+        let acceleration = force / frame.mass_kg + Vec3::NEG_Y * GRAVITY_MPS2;
+        kinematics.linear_velocity += acceleration * dt;
+        kinematics.linear_acceleration = acceleration;
+        transform.translation += kinematics.linear_velocity * dt;
+
+        // Angular motion in the local frame
+        let omega = kinematics.angular_velocity;
+        let gyroscopic = omega.cross(frame.inertia * omega);
+        let torque = torque - gyroscopic - omega * frame.angular_drag_coef;
+        kinematics.angular_velocity += torque / frame.inertia * dt;
+        let delta = Quat::from_scaled_axis(kinematics.angular_velocity * dt);
+        transform.rotation = (transform.rotation * delta).normalize();
+
+        // Keep the drone on the ground instead of falling through it
+        if transform.translation.y < 0.0 {
+            transform.translation.y = 0.0;
+            kinematics.linear_velocity = Vec3::ZERO;
+            kinematics.linear_acceleration = Vec3::ZERO;
+            kinematics.angular_velocity = Vec3::ZERO;
+        }
+        kinematics.last_transform = Some(*transform);
+    }
+}
+
+/// Differentiates the transform of drones that are not driven by motors (e.g. stick-driven).
+fn derive_kinematics_from_transform(
+    mut drones: Query<(&Transform, &mut DroneKinematics), Without<MotorOutputs>>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_secs();
+    if dt <= 0.0 {
+        return;
+    }
+
+    for (transform, mut kinematics) in drones.iter_mut() {
+        if let Some(last) = kinematics.last_transform {
+            let velocity = (transform.translation - last.translation) / dt;
+            let delta = last.rotation.inverse() * transform.rotation;
+            kinematics.linear_acceleration = (velocity - kinematics.linear_velocity) / dt;
+            kinematics.linear_velocity = velocity;
+            kinematics.angular_velocity = delta.to_scaled_axis() / dt;
+        }
+        kinematics.last_transform = Some(*transform);
+    }
+}
+
+fn update_drone_sensors(
+    mut drones: Query<(&Transform, &DroneKinematics, &mut DroneSensors)>,
+    origin: Res<GeoOrigin>,
+) {
+    for (transform, kinematics, mut sensors) in drones.iter_mut() {
+        let to_local = transform.rotation.inverse();
+        let specific_force = kinematics.linear_acceleration + Vec3::Y * GRAVITY_MPS2;
+        let magnetic_field_world = Vec3::new(
+            origin.magnetic_field_ned.y,
+            -origin.magnetic_field_ned.z,
+            -origin.magnetic_field_ned.x,
+        );
+        let altitude = origin.altitude_m + transform.translation.y;
+
+        sensors.accelerometer = to_local * specific_force;
+        sensors.gyro = kinematics.angular_velocity;
+        sensors.magnetometer = to_local * magnetic_field_world;
+        // International standard atmosphere
+        sensors.pressure_hpa = 1013.25 * (1.0 - 2.25577e-5 * altitude).powf(5.25588);
+        sensors.altitude_amsl_m = altitude;
+        sensors.temperature_c = 15.0 - 0.0065 * altitude;
+    }
+}
+
+fn drain_battery(
+    mut drones: Query<(&mut DroneBattery, &DronePosition, Option<&MotorOutputs>)>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_secs();
+    for (mut battery, sticks, motors) in drones.iter_mut() {
+        // Motor current grows faster than linearly with the command
+        let load = match motors {
+            Some(motors) => {
+                motors
+                    .0
+                    .iter()
+                    .map(|m| m.clamp(0.0, 1.0).powf(1.5))
+                    .sum::<f32>()
+                    / 4.0
+            }
+            None => sticks.throttle.clamp(0.0, 1.0).powf(1.5),
+        };
+        let idle_current_a = 0.5;
+        battery.current_a = idle_current_a + load * battery.max_current_a;
+        battery.consumed_mah += battery.current_a * dt * 1000.0 / 3600.0;
+
+        let cell_open_circuit = DroneBattery::CELL_EMPTY_V
+            + (DroneBattery::CELL_FULL_V - DroneBattery::CELL_EMPTY_V) * battery.remaining();
+        battery.voltage = cell_open_circuit * battery.cell_count as f32
+            - battery.current_a * battery.internal_resistance;
+    }
+}
@@ -0,0 +1,268 @@
+use crate::drone_plugin::{
+    DronePosition, DroneSensors, DroneSet, MOTOR_LAYOUT, MotorOutputs, PlayerDrone, local_to_frd,
+};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Betaflight-style acro flight controller: stick rates, rate PIDs and a quad-x mixer
+/// writing the drone's [`MotorOutputs`].
+pub struct FlightControllerPlugin;
+
+impl Plugin for FlightControllerPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Time::<Fixed>::from_hz(500.0))
+            .add_systems(Update, (attach_flight_controller, toggle_arming))
+            .add_systems(
+                FixedUpdate,
+                run_flight_controller.before(DroneSet::Dynamics),
+            )
+            .register_type::<FlightController>()
+            .register_type::<PidGains>()
+            .register_type::<AxisRates>()
+            .register_type::<PidTerms>();
+    }
+}
+
+/// PID gains in Betaflight's integer units, so tuning values can be copied between
+/// the simulator and real quads.
+#[derive(Debug, Clone, Copy, Reflect, Serialize, Deserialize)]
+pub struct PidGains {
+    pub p: u8,
+    pub i: u8,
+    pub d: u8,
+    pub f: u8,
+}
+
+/// Betaflight rates of a single axis.
+#[derive(Debug, Clone, Copy, Reflect, Serialize, Deserialize)]
+pub struct AxisRates {
+    pub rc_rate: f32,
+    pub super_rate: f32,
+    pub expo: f32,
+}
+
+impl AxisRates {
+    /// Converts a stick deflection in `-1..=1` to an angular rate in deg/s.
+    pub fn setpoint(&self, stick: f32) -> f32 {
+        let stick = stick.clamp(-1.0, 1.0);
+        let abs = stick.abs();
+        let stick = stick * abs.powi(3) * self.expo + stick * (1.0 - self.expo);
+        let mut rate = 200.0 * self.rc_rate * stick;
+        if self.super_rate > 0.0 {
+            rate /= (1.0 - abs * self.super_rate).clamp(0.01, 1.0);
+        }
+        rate
+    }
+}
+
+/// Contribution of each PID term on one axis during the last loop.
+#[derive(Debug, Default, Clone, Copy, Reflect, Serialize, Deserialize)]
+pub struct PidTerms {
+    pub p: f32,
+    pub i: f32,
+    pub d: f32,
+    pub f: f32,
+}
+
+impl PidTerms {
+    pub fn sum(&self) -> f32 {
+        self.p + self.i + self.d + self.f
+    }
+}
+
+#[derive(Component, Debug, Clone, Reflect, Serialize, Deserialize)]
+#[reflect(Component)]
+#[require(MotorOutputs)]
+pub struct FlightController {
+    pub armed: bool,
+    /// Roll, pitch, yaw.
+    pub pids: [PidGains; 3],
+    /// Roll, pitch, yaw.
+    pub rates: [AxisRates; 3],
+    /// Motor command while armed at zero throttle.
+    pub motor_idle: f32,
+    /// Rate setpoints of the last loop in FRD, deg/s.
+    pub setpoint: Vec3,
+    /// Measured rates of the last loop in FRD, deg/s.
+    pub gyro: Vec3,
+    pub pid_terms: [PidTerms; 3],
+    pub loop_iteration: u64,
+    previous_setpoint: Vec3,
+    previous_gyro: Vec3,
+}
+
+impl Default for FlightController {
+    fn default() -> Self {
+        Self {
+            armed: false,
+            // Betaflight 4.5 defaults
+            pids: [
+                PidGains {
+                    p: 45,
+                    i: 80,
+                    d: 30,
+                    f: 120,
+                },
+                PidGains {
+                    p: 47,
+                    i: 84,
+                    d: 34,
+                    f: 125,
+                },
+                PidGains {
+                    p: 45,
+                    i: 80,
+                    d: 0,
+                    f: 120,
+                },
+            ],
+            rates: [AxisRates {
+                rc_rate: 1.0,
+                super_rate: 0.7,
+                expo: 0.0,
+            }; 3],
+            motor_idle: 0.055,
+            setpoint: Vec3::ZERO,
+            gyro: Vec3::ZERO,
+            pid_terms: [PidTerms::default(); 3],
+            loop_iteration: 0,
+            previous_setpoint: Vec3::ZERO,
+            previous_gyro: Vec3::ZERO,
+        }
+    }
+}
+
+impl FlightController {
+    // Scales from Betaflight's integer gains to its internal PID units
+    const P_SCALE: f32 = 0.032029;
+    const I_SCALE: f32 = 0.244381;
+    const D_SCALE: f32 = 0.000529;
+    const F_SCALE: f32 = 0.013754;
+    /// Betaflight's PID output range, mapped to a full motor command.
+    const PID_OUTPUT_RANGE: f32 = 1000.0;
+    const I_TERM_LIMIT: f32 = 400.0;
+
+    fn reset_integrators(&mut self) {
+        for terms in self.pid_terms.iter_mut() {
+            terms.i = 0.0;
+        }
+    }
+}
+
+/// The player drone gets a flight controller, which switches it to motor dynamics.
+fn attach_flight_controller(
+    mut commands: Commands,
+    drones: Query<Entity, (With<PlayerDrone>, Without<FlightController>)>,
+) {
+    for entity in drones.iter() {
+        info!("Drone {entity} is now flown by the flight controller");
+        commands.entity(entity).insert(FlightController::default());
+    }
+}
+
+/// Arms with `M` or the gamepad's start button, only at low throttle.
+fn toggle_arming(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    mut drone: Query<(&mut FlightController, &DronePosition), With<PlayerDrone>>,
+) {
+    let pressed = keyboard.just_pressed(KeyCode::KeyM)
+        || gamepads
+            .iter()
+            .any(|gamepad| gamepad.just_pressed(GamepadButton::Start));
+    if !pressed {
+        return;
+    }
+    let Ok((mut fc, sticks)) = drone.single_mut() else {
+        debug!("No flight controller found to arm.");
+        return;
+    };
+
+    if fc.armed {
+        fc.armed = false;
+        info!("Flight controller disarmed");
+    } else if sticks.throttle < 0.05 {
+        fc.armed = true;
+        fc.reset_integrators();
+        info!("Flight controller armed");
+    } else {
+        warn!("Lower the throttle to arm");
+    }
+}
+
+fn run_flight_controller(
+    mut drones: Query<(
+        &mut FlightController,
+        &mut MotorOutputs,
+        &DronePosition,
+        &DroneSensors,
+    )>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_secs();
+    if dt <= 0.0 {
+        return;
+    }
+
+    for (mut fc, mut motors, sticks, sensors) in drones.iter_mut() {
+        let fc = &mut *fc;
+        fc.loop_iteration += 1;
+        fc.setpoint = Vec3::new(
+            fc.rates[0].setpoint(sticks.roll),
+            fc.rates[1].setpoint(sticks.pitch),
+            fc.rates[2].setpoint(sticks.yaw),
+        );
+        fc.gyro = local_to_frd(sensors.gyro) * 180.0 / std::f32::consts::PI;
+
+        if !fc.armed {
+            fc.reset_integrators();
+            fc.previous_setpoint = fc.setpoint;
+            fc.previous_gyro = fc.gyro;
+            motors.0 = [0.0; 4];
+            continue;
+        }
+
+        let mut axis_output = [0.0; 3];
+        for axis in 0..3 {
+            let gains = fc.pids[axis];
+            let error = fc.setpoint[axis] - fc.gyro[axis];
+            let terms = &mut fc.pid_terms[axis];
+            terms.p = FlightController::P_SCALE * gains.p as f32 * error;
+            terms.i = (terms.i + FlightController::I_SCALE * gains.i as f32 * error * dt).clamp(
+                -FlightController::I_TERM_LIMIT,
+                FlightController::I_TERM_LIMIT,
+            );
+            // Derivative on measurement, so stick moves are handled by the feedforward
+            terms.d = -FlightController::D_SCALE
+                * gains.d as f32
+                * (fc.gyro[axis] - fc.previous_gyro[axis])
+                / dt;
+            terms.f = FlightController::F_SCALE
+                * gains.f as f32
+                * 0.01
+                * (fc.setpoint[axis] - fc.previous_setpoint[axis])
+                / dt;
+            axis_output[axis] = terms.sum() / FlightController::PID_OUTPUT_RANGE;
+        }
+        fc.previous_setpoint = fc.setpoint;
+        fc.previous_gyro = fc.gyro;
+
+        let throttle = fc.motor_idle + sticks.throttle.clamp(0.0, 1.0) * (1.0 - fc.motor_idle);
+        motors.0 = mix(throttle, axis_output, fc.motor_idle);
+    }
+}
+
+/// Quad-x mixer. Shifts the throttle when a motor saturates so the attitude authority is kept.
+fn mix(throttle: f32, [roll, pitch, yaw]: [f32; 3], motor_idle: f32) -> [f32; 4] {
+    let mut outputs = [0.0; 4];
+    for (output, (position, spin)) in outputs.iter_mut().zip(MOTOR_LAYOUT) {
+        // Right motors slow down to roll right, front motors speed up to pitch up and
+        // the CCW motors speed up to yaw right.
+        *output = -position.x * roll - position.y * pitch + spin * yaw;
+    }
+
+    let min = outputs.iter().copied().fold(f32::MAX, f32::min);
+    let max = outputs.iter().copied().fold(f32::MIN, f32::max);
+    let throttle = throttle.clamp(motor_idle - min, (1.0 - max).max(motor_idle - min));
+    outputs.map(|output| (output + throttle).clamp(motor_idle, 1.0))
+}
@@ -0,0 +1,205 @@
+use crate::drone_plugin::{DroneFrame, DroneKinematics, DronePosition, GRAVITY_MPS2, MotorOutputs};
+use bevy::prelude::*;
+use std::collections::VecDeque;
+
+/// Draws each drone's recent flight path as a fading line, coloured by speed, throttle or
+/// altitude, with velocity (cyan) and thrust (orange) vectors at the body.
+///
+/// `G` toggles the gizmos and `C` cycles the trail colouring, like the physics debug renderers
+/// they are drawn on top of.
+pub struct FlightPathPlugin;
+
+impl Plugin for FlightPathPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_gizmo_group::<FlightPathGizmos>()
+            .add_systems(
+                Update,
+                (
+                    handle_flight_path_keys,
+                    (
+                        attach_flight_trails,
+                        record_flight_trails,
+                        draw_flight_paths,
+                    )
+                        .chain(),
+                ),
+            )
+            .register_type::<FlightPathGizmos>()
+            .register_type::<TrailColorMode>();
+    }
+}
+
+/// Gizmo group of the flight paths, configured through `GizmoConfigStore`.
+#[derive(Reflect, GizmoConfigGroup)]
+pub struct FlightPathGizmos {
+    pub color_mode: TrailColorMode,
+    /// How long the trail is kept, in seconds.
+    pub trail_secs: f32,
+    pub show_vectors: bool,
+    /// Length of the velocity vector per m/s.
+    pub velocity_scale: f32,
+}
+
+impl Default for FlightPathGizmos {
+    fn default() -> Self {
+        Self {
+            color_mode: TrailColorMode::Speed,
+            trail_secs: 5.0,
+            show_vectors: true,
+            velocity_scale: 0.1,
+        }
+    }
+}
+
+#[derive(Reflect, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrailColorMode {
+    /// 0 to 20 m/s.
+    Speed,
+    Throttle,
+    /// 0 to 30 m above the origin.
+    Altitude,
+}
+
+impl TrailColorMode {
+    fn next(self) -> Self {
+        match self {
+            TrailColorMode::Speed => TrailColorMode::Throttle,
+            TrailColorMode::Throttle => TrailColorMode::Altitude,
+            TrailColorMode::Altitude => TrailColorMode::Speed,
+        }
+    }
+
+    /// The colouring value of a trail point, normalized to `0..=1`.
+    fn value(self, point: &TrailPoint) -> f32 {
+        let value = match self {
+            TrailColorMode::Speed => point.speed / 20.0,
+            TrailColorMode::Throttle => point.throttle,
+            TrailColorMode::Altitude => point.position.y / 30.0,
+        };
+        value.clamp(0.0, 1.0)
+    }
+}
+
+/// Recent positions of a drone.
+#[derive(Component, Default)]
+struct FlightTrail {
+    points: VecDeque<TrailPoint>,
+}
+
+struct TrailPoint {
+    position: Vec3,
+    time_secs: f32,
+    speed: f32,
+    throttle: f32,
+}
+
+fn handle_flight_path_keys(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut config_store: ResMut<GizmoConfigStore>,
+) {
+    let (config, gizmos) = config_store.config_mut::<FlightPathGizmos>();
+    if keyboard.just_pressed(KeyCode::KeyG) {
+        config.enabled = !config.enabled;
+        info!(
+            "Flight path gizmos {}",
+            if config.enabled {
+                "enabled"
+            } else {
+                "disabled"
+            }
+        );
+    }
+    if keyboard.just_pressed(KeyCode::KeyC) {
+        gizmos.color_mode = gizmos.color_mode.next();
+        info!("Flight path coloured by {:?}", gizmos.color_mode);
+    }
+}
+
+fn attach_flight_trails(
+    mut commands: Commands,
+    drones: Query<Entity, (With<DronePosition>, Without<FlightTrail>)>,
+) {
+    for entity in drones.iter() {
+        commands.entity(entity).insert(FlightTrail::default());
+    }
+}
+
+fn record_flight_trails(
+    config_store: Res<GizmoConfigStore>,
+    mut drones: Query<(
+        &Transform,
+        &DroneKinematics,
+        &DronePosition,
+        &mut FlightTrail,
+    )>,
+    time: Res<Time>,
+) {
+    let (_, gizmos) = config_store.config::<FlightPathGizmos>();
+    let now = time.elapsed_secs();
+    for (transform, kinematics, sticks, mut trail) in drones.iter_mut() {
+        trail.points.push_back(TrailPoint {
+            position: transform.translation,
+            time_secs: now,
+            speed: kinematics.linear_velocity.length(),
+            throttle: sticks.throttle,
+        });
+        while trail
+            .points
+            .front()
+            .is_some_and(|point| now - point.time_secs > gizmos.trail_secs)
+        {
+            trail.points.pop_front();
+        }
+    }
+}
+
+fn draw_flight_paths(
+    mut gizmos: Gizmos<FlightPathGizmos>,
+    drones: Query<(
+        &Transform,
+        &DroneKinematics,
+        &DronePosition,
+        &FlightTrail,
+        Option<(&MotorOutputs, &DroneFrame)>,
+    )>,
+    time: Res<Time>,
+) {
+    let config = gizmos.config_ext;
+    let now = time.elapsed_secs();
+    for (transform, kinematics, sticks, trail, motors) in drones.iter() {
+        gizmos.linestrip_gradient(trail.points.iter().map(|point| {
+            // Blue for low values to red for high ones, fading out with age
+            let hue = 240.0 * (1.0 - config.color_mode.value(point));
+            let age = (now - point.time_secs) / config.trail_secs.max(f32::EPSILON);
+            (point.position, Color::hsla(hue, 1.0, 0.5, 1.0 - age))
+        }));
+
+        if !config.show_vectors {
+            continue;
+        }
+        let position = transform.translation;
+        gizmos.arrow(
+            position,
+            position + kinematics.linear_velocity * config.velocity_scale,
+            Color::srgb(0.0, 1.0, 1.0),
+        );
+        // Thrust as a fraction of hover thrust, a unit arrow holds the drone in the air
+        let thrust_ratio = match motors {
+            Some((motors, frame)) => {
+                motors
+                    .0
+                    .iter()
+                    .map(|command| command.clamp(0.0, 1.0))
+                    .sum::<f32>()
+                    * frame.max_motor_thrust_n
+                    / (frame.mass_kg * GRAVITY_MPS2)
+            }
+            None => sticks.throttle,
+        };
+        gizmos.arrow(
+            position,
+            position + transform.up() * thrust_ratio,
+            Color::srgb(1.0, 0.5, 0.0),
+        );
+    }
+}
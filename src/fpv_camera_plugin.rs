@@ -0,0 +1,242 @@
+use crate::drone_plugin::PlayerDrone;
+use bevy::asset::{RenderAssetUsages, embedded_asset};
+use bevy::prelude::*;
+use bevy::render::render_resource::{
+    AsBindGroup, Extent3d, ShaderRef, ShaderType, TextureDimension, TextureFormat, TextureUsages,
+};
+
+/// FPV camera mounted on the player drone with a configurable uptilt, field of view and
+/// barrel lens distortion. `V` switches between the FPV feed and the scene's other cameras.
+///
+/// The FPV camera renders into an image that is shown full screen through the lens material,
+/// behind the rest of the UI.
+pub struct FpvCameraPlugin;
+
+impl Plugin for FpvCameraPlugin {
+    fn build(&self, app: &mut App) {
+        embedded_asset!(app, "shaders/fpv_lens.wgsl");
+
+        app.add_plugins(UiMaterialPlugin::<FpvLensMaterial>::default())
+            .init_resource::<FpvCameraSettings>()
+            .add_systems(
+                Update,
+                (attach_fpv_camera, toggle_fpv_view, apply_fpv_settings).chain(),
+            )
+            .register_type::<FpvCameraSettings>()
+            .register_type::<FpvCamera>();
+    }
+}
+
+#[derive(Resource, Reflect, Clone)]
+#[reflect(Resource)]
+pub struct FpvCameraSettings {
+    /// Whether the FPV feed is shown instead of the other cameras.
+    pub enabled: bool,
+    /// Camera tilt above the drone's horizontal plane, in degrees.
+    pub uptilt_degrees: f32,
+    /// Horizontal field of view, in degrees.
+    pub fov_degrees: f32,
+    /// Barrel distortion, `0` for a rectilinear lens, around `0.3` for a typical FPV lens.
+    pub distortion: f32,
+    /// Resolution of the camera feed.
+    pub resolution: UVec2,
+    /// Camera position in the drone's local frame.
+    pub offset: Vec3,
+}
+
+impl Default for FpvCameraSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            uptilt_degrees: 25.0,
+            fov_degrees: 120.0,
+            distortion: 0.3,
+            resolution: UVec2::new(1280, 720),
+            offset: Vec3::new(0.0, 0.03, -0.1),
+        }
+    }
+}
+
+/// Camera mounted on a drone, rendering into `feed` which is shown through `lens`.
+#[derive(Component, Reflect, Debug, Clone)]
+#[reflect(Component)]
+pub struct FpvCamera {
+    pub feed: Handle<Image>,
+    pub lens: Handle<FpvLensMaterial>,
+}
+
+/// Full screen node showing the FPV feed.
+#[derive(Component)]
+struct FpvOverlay;
+
+#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+pub struct FpvLensMaterial {
+    #[uniform(0)]
+    pub lens: FpvLens,
+    #[texture(1)]
+    #[sampler(2)]
+    pub feed: Handle<Image>,
+    #[uniform(3)]
+    pub video: AnalogVideo,
+}
+
+#[derive(ShaderType, Debug, Clone, Copy, Default)]
+pub struct FpvLens {
+    pub distortion: f32,
+}
+
+/// Analog video artefacts applied to the feed, all `0` for a clean digital image.
+#[derive(ShaderType, Debug, Clone, Copy, Default)]
+pub struct AnalogVideo {
+    /// Static noise and rolling lines, `0..=1`.
+    pub noise: f32,
+    /// Torn, shifted lines and dropouts of a failing link, `0..=1`.
+    pub breakup: f32,
+    /// Horizontal chroma smear, in feed widths.
+    pub color_bleed: f32,
+    /// Seconds, animates the noise.
+    pub time: f32,
+}
+
+impl UiMaterial for FpvLensMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "embedded://bevy_drone_sim/shaders/fpv_lens.wgsl".into()
+    }
+}
+
+fn feed_size(resolution: UVec2) -> Extent3d {
+    Extent3d {
+        width: resolution.x.max(1),
+        height: resolution.y.max(1),
+        ..default()
+    }
+}
+
+fn overlay_visibility(settings: &FpvCameraSettings) -> Visibility {
+    if settings.enabled {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    }
+}
+
+fn attach_fpv_camera(
+    mut commands: Commands,
+    settings: Res<FpvCameraSettings>,
+    drones: Query<Entity, Added<PlayerDrone>>,
+    mut images: ResMut<Assets<Image>>,
+    mut materials: ResMut<Assets<FpvLensMaterial>>,
+) {
+    for drone in drones.iter() {
+        let mut feed = Image::new_fill(
+            feed_size(settings.resolution),
+            TextureDimension::D2,
+            &[0, 0, 0, 255],
+            TextureFormat::Bgra8UnormSrgb,
+            RenderAssetUsages::default(),
+        );
+        feed.texture_descriptor.usage = TextureUsages::TEXTURE_BINDING
+            | TextureUsages::COPY_DST
+            | TextureUsages::RENDER_ATTACHMENT;
+        let feed = images.add(feed);
+        let lens = materials.add(FpvLensMaterial {
+            lens: FpvLens {
+                distortion: settings.distortion.max(0.0),
+            },
+            feed: feed.clone(),
+            video: AnalogVideo::default(),
+        });
+
+        commands.entity(drone).with_child((
+            Name::new("FPV Camera"),
+            FpvCamera {
+                feed: feed.clone(),
+                lens: lens.clone(),
+            },
+            Camera3d::default(),
+            Camera {
+                target: feed.clone().into(),
+                is_active: settings.enabled,
+                // Render the feed before the window cameras showing it
+                order: -1,
+                ..default()
+            },
+            Projection::Perspective(PerspectiveProjection {
+                near: 0.01,
+                ..default()
+            }),
+        ));
+
+        commands.spawn((
+            Name::new("FPV Overlay"),
+            FpvOverlay,
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                ..default()
+            },
+            // Keep the rest of the UI on top of the feed
+            GlobalZIndex(-1),
+            overlay_visibility(&settings),
+            MaterialNode(lens),
+        ));
+        info!("FPV camera attached, press 'V' to fly FPV.");
+    }
+}
+
+fn toggle_fpv_view(keyboard: Res<ButtonInput<KeyCode>>, mut settings: ResMut<FpvCameraSettings>) {
+    if keyboard.just_pressed(KeyCode::KeyV) {
+        settings.enabled = !settings.enabled;
+        info!(
+            "FPV view {}",
+            if settings.enabled {
+                "enabled"
+            } else {
+                "disabled"
+            }
+        );
+    }
+}
+
+fn apply_fpv_settings(
+    settings: Res<FpvCameraSettings>,
+    mut cameras: Query<(Ref<FpvCamera>, &mut Camera, &mut Projection, &mut Transform)>,
+    mut overlays: Query<(&MaterialNode<FpvLensMaterial>, &mut Visibility), With<FpvOverlay>>,
+    mut images: ResMut<Assets<Image>>,
+    mut materials: ResMut<Assets<FpvLensMaterial>>,
+) {
+    for (fpv, mut camera, mut projection, mut transform) in cameras.iter_mut() {
+        if !settings.is_changed() && !fpv.is_added() {
+            continue;
+        }
+
+        camera.is_active = settings.enabled;
+        *transform = Transform::from_translation(settings.offset)
+            .with_rotation(Quat::from_rotation_x(settings.uptilt_degrees.to_radians()));
+
+        let aspect = settings.resolution.x.max(1) as f32 / settings.resolution.y.max(1) as f32;
+        let horizontal_fov = settings.fov_degrees.clamp(1.0, 170.0).to_radians();
+        if let Projection::Perspective(perspective) = projection.as_mut() {
+            perspective.fov = 2.0 * ((horizontal_fov / 2.0).tan() / aspect).atan();
+            perspective.aspect_ratio = aspect;
+        }
+
+        if let Some(feed) = images.get_mut(&fpv.feed) {
+            let size = feed_size(settings.resolution);
+            if feed.texture_descriptor.size != size {
+                feed.resize(size);
+            }
+        }
+    }
+
+    if !settings.is_changed() {
+        return;
+    }
+    for (material, mut visibility) in overlays.iter_mut() {
+        *visibility = overlay_visibility(&settings);
+        if let Some(material) = materials.get_mut(&material.0) {
+            material.lens.distortion = settings.distortion.max(0.0);
+        }
+    }
+}
@@ -1,29 +1,50 @@
-use bevy::input::mouse::MouseMotion;
+use bevy::input::mouse::{AccumulatedMouseScroll, MouseMotion, MouseScrollUnit};
 use bevy::prelude::*;
+use bevy::render::camera::RenderTarget;
+use bevy::window::{CursorGrabMode, PrimaryWindow};
 
 /// Used to fly around a scene with a free camera.
+///
+/// `WASDQE` or the gamepad sticks and triggers move, the mouse or the right stick looks around.
+/// The scroll wheel changes the speed, `Shift` boosts it and `Ctrl` slows it down.
 pub struct FreeCameraPlugin;
 
 impl Plugin for FreeCameraPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Update, handle_input)
             .add_systems(Startup, setup_ui)
-            .add_systems(Update, (toggle_controls, update_ui))
+            .add_systems(Update, (toggle_controls, update_ui, grab_cursor))
             // Initialize resources
             .init_resource::<FreeCameraMode>()
             // Register types for reflection
             .register_type::<FreeCameraMode>()
-            .register_type::<CameraRotation>();
+            .register_type::<CameraRotation>()
+            .register_type::<SceneCamera>();
     }
 }
 
 #[derive(Resource, Reflect)]
 #[reflect(Resource)]
-struct FreeCameraMode {
+pub struct FreeCameraMode {
     /// Whether the free camera controls are enabled.
-    enabled: bool,
+    pub enabled: bool,
     /// Speed of the camera movement in meters per second.
-    speed_mps: f32,
+    pub speed_mps: f32,
+    /// Speed range the scroll wheel adjusts `speed_mps` within.
+    pub min_speed_mps: f32,
+    pub max_speed_mps: f32,
+    /// Speed factor per scroll wheel line.
+    pub scroll_speed_factor: f32,
+    /// Speed factor while `Shift` is held.
+    pub boost_multiplier: f32,
+    /// Speed factor while `Ctrl` is held.
+    pub slow_multiplier: f32,
+    /// How quickly the camera reaches the target speed, per second. `0` moves instantly.
+    pub acceleration: f32,
+    /// Turn rate at full right stick deflection, in degrees per second.
+    pub gamepad_look_degrees: f32,
+    /// Whether the cursor is locked and hidden while the controls are enabled.
+    pub grab_cursor: bool,
 }
 
 impl Default for FreeCameraMode {
@@ -31,6 +52,14 @@ impl Default for FreeCameraMode {
         Self {
             enabled: false,
             speed_mps: 5.0,
+            min_speed_mps: 0.5,
+            max_speed_mps: 100.0,
+            scroll_speed_factor: 1.2,
+            boost_multiplier: 4.0,
+            slow_multiplier: 0.25,
+            acceleration: 10.0,
+            gamepad_look_degrees: 120.0,
+            grab_cursor: true,
         }
     }
 }
@@ -38,9 +67,10 @@ impl Default for FreeCameraMode {
 /// Track yaw/pitch for mouse look
 #[derive(Component, Reflect)]
 #[reflect(Component)]
-struct CameraRotation {
+pub(crate) struct CameraRotation {
     yaw: f32,
     pitch: f32,
+    /// Degrees turned per pixel of mouse motion.
     sensitivity: f32,
 }
 
@@ -54,6 +84,35 @@ impl Default for CameraRotation {
     }
 }
 
+impl CameraRotation {
+    /// Continues mouse look from `rotation`, after something else turned the camera.
+    pub(crate) fn look_along(&mut self, rotation: Quat) {
+        let (yaw, pitch, _roll) = rotation.to_euler(EulerRot::YXZ);
+        self.yaw = yaw;
+        self.pitch = pitch;
+    }
+}
+
+/// Marks the camera showing the scene when there are several window cameras.
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+pub struct SceneCamera;
+
+/// The camera showing the scene in the window: the one marked [`SceneCamera`], otherwise the
+/// active window camera with the highest order. Cameras rendering to images are never picked.
+pub fn scene_camera(cameras: &Query<(Entity, &Camera, Has<SceneCamera>)>) -> Option<Entity> {
+    if let Some((entity, ..)) = cameras.iter().find(|(_, _, marked)| *marked) {
+        return Some(entity);
+    }
+    cameras
+        .iter()
+        .filter(|(_, camera, _)| {
+            camera.is_active && matches!(camera.target, RenderTarget::Window(_))
+        })
+        .max_by_key(|(_, camera, _)| camera.order)
+        .map(|(entity, ..)| entity)
+}
+
 #[derive(Component, Reflect)]
 #[reflect(Component)]
 struct FreeCameraUiSwitchMarker;
@@ -95,9 +154,11 @@ fn setup_ui(mut commands: Commands) {
 fn update_ui(
     free_camera_mode: Res<FreeCameraMode>,
     mut query: Query<&mut Text, With<FreeCameraUiSwitchMarker>>,
-    camera: Query<&Transform, With<Camera>>,
+    cameras: Query<(Entity, &Camera, Has<SceneCamera>)>,
+    transforms: Query<&Transform>,
 ) {
-    let Ok(transform) = camera.single() else {
+    let Some(transform) = scene_camera(&cameras).and_then(|entity| transforms.get(entity).ok())
+    else {
         warn!("No camera found to control with free camera plugin.");
         return;
     };
@@ -132,14 +193,19 @@ fn update_ui(
 fn toggle_controls(
     keyboard: Res<ButtonInput<KeyCode>>,
     mut free_camera_mode: ResMut<FreeCameraMode>,
-    mut query: Query<(Entity, &Transform, Option<&mut CameraRotation>), With<Camera>>,
+    cameras: Query<(Entity, &Camera, Has<SceneCamera>)>,
+    query: Query<(&Transform, Option<&CameraRotation>)>,
     mut commands: Commands,
 ) {
     if keyboard.just_pressed(KeyCode::KeyF) {
+        let Some((entity, (transform, cam_rot_opt))) = scene_camera(&cameras)
+            .and_then(|entity| query.get(entity).ok().map(|camera| (entity, camera)))
+        else {
+            warn!("No camera found to control with free camera plugin.");
+            return;
+        };
         free_camera_mode.enabled = !free_camera_mode.enabled;
 
-        let (entity, transform, cam_rot_opt) = query.single_mut().expect("Need exactly one camera");
-
         if free_camera_mode.enabled {
             // On enable: add component if missing
             if cam_rot_opt.is_none() {
@@ -166,32 +232,65 @@ fn toggle_controls(
     }
 }
 
+/// Locks the cursor to the window while the free camera controls are enabled.
+fn grab_cursor(
+    free_camera_mode: Res<FreeCameraMode>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if !free_camera_mode.is_changed() {
+        return;
+    }
+    let Ok(mut window) = windows.single_mut() else {
+        debug!("No primary window found to grab the cursor.");
+        return;
+    };
+    let grab = free_camera_mode.enabled && free_camera_mode.grab_cursor;
+    window.cursor_options.grab_mode = if grab {
+        CursorGrabMode::Locked
+    } else {
+        CursorGrabMode::None
+    };
+    window.cursor_options.visible = !grab;
+}
+
+#[allow(clippy::too_many_arguments)]
 fn handle_input(
     keyboard: Res<ButtonInput<KeyCode>>,
     mut mouse_motion_events: EventReader<MouseMotion>,
-    free_camera_mode: ResMut<FreeCameraMode>,
-    mut query: Query<(&mut Transform, &mut CameraRotation), With<Camera>>,
+    mouse_scroll: Res<AccumulatedMouseScroll>,
+    gamepads: Query<&Gamepad>,
+    mut free_camera_mode: ResMut<FreeCameraMode>,
+    cameras: Query<(Entity, &Camera, Has<SceneCamera>)>,
+    mut query: Query<(&mut Transform, &mut CameraRotation)>,
     time: Res<Time>,
+    mut velocity: Local<Vec3>,
 ) {
     if !free_camera_mode.enabled {
+        mouse_motion_events.clear();
+        *velocity = Vec3::ZERO;
         return;
     }
 
-    let Ok((mut transform, mut cam_rot)) = query.single_mut() else {
+    let Some(Ok((mut transform, mut cam_rot))) =
+        scene_camera(&cameras).map(|entity| query.get_mut(entity))
+    else {
         info!("No free camera found to control.");
         return;
     };
+    let gamepad = gamepads.iter().next();
+    let dt = time.delta_secs();
 
     let mut mouse_updated = false;
     // --- 1. Mouse look ---
+    // Mouse motion is a distance already, scaling it by the frame time would make the
+    // sensitivity depend on the framerate
     for ev in mouse_motion_events.read() {
         debug!(
             "Free camera controls: Mouse motion detected: {:?}",
             ev.delta
         );
-        cam_rot.yaw -= ev.delta.x * cam_rot.sensitivity * time.delta_secs();
-        cam_rot.pitch -= ev.delta.y * cam_rot.sensitivity * time.delta_secs();
-        cam_rot.pitch = cam_rot.pitch.clamp(-1.54, 1.54); // avoid flipping (±~89°)
+        cam_rot.yaw -= (ev.delta.x * cam_rot.sensitivity).to_radians();
+        cam_rot.pitch -= (ev.delta.y * cam_rot.sensitivity).to_radians();
         mouse_updated = true;
     }
 
@@ -202,10 +301,43 @@ fn handle_input(
         );
     }
 
+    // The right stick is a turn rate, so it does scale with the frame time
+    if let Some(gamepad) = gamepad {
+        let look = gamepad.right_stick() * free_camera_mode.gamepad_look_degrees.to_radians() * dt;
+        cam_rot.yaw -= look.x;
+        cam_rot.pitch += look.y;
+    }
+    cam_rot.pitch = cam_rot.pitch.clamp(-1.54, 1.54); // avoid flipping (±~89°)
+
     transform.rotation =
         Quat::from_axis_angle(Vec3::Y, cam_rot.yaw) * Quat::from_axis_angle(Vec3::X, cam_rot.pitch);
 
-    // --- 2. Movement ---
+    // --- 2. Speed ---
+    if mouse_scroll.delta.y != 0.0 {
+        let lines = match mouse_scroll.unit {
+            MouseScrollUnit::Line => mouse_scroll.delta.y,
+            // Touchpads scroll in pixels, roughly 20 of them per wheel line
+            MouseScrollUnit::Pixel => mouse_scroll.delta.y / 20.0,
+        };
+        let speed = free_camera_mode.speed_mps * free_camera_mode.scroll_speed_factor.powf(lines);
+        free_camera_mode.speed_mps = speed.clamp(
+            free_camera_mode.min_speed_mps,
+            free_camera_mode
+                .max_speed_mps
+                .max(free_camera_mode.min_speed_mps),
+        );
+        info!("Free camera speed: {:.1} m/s", free_camera_mode.speed_mps);
+    }
+
+    let mut speed = free_camera_mode.speed_mps;
+    if keyboard.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        speed *= free_camera_mode.boost_multiplier;
+    }
+    if keyboard.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+        speed *= free_camera_mode.slow_multiplier;
+    }
+
+    // --- 3. Movement ---
     let mut movement = Vec3::ZERO;
 
     if keyboard.pressed(KeyCode::KeyW) {
@@ -226,17 +358,31 @@ fn handle_input(
     if keyboard.pressed(KeyCode::KeyE) {
         movement.y += 1.0;
     }
+    if let Some(gamepad) = gamepad {
+        let stick = gamepad.left_stick();
+        let rise = gamepad.get(GamepadButton::RightTrigger2).unwrap_or(0.0)
+            - gamepad.get(GamepadButton::LeftTrigger2).unwrap_or(0.0);
+        movement += Vec3::new(stick.x, rise, stick.y);
+    }
 
+    // Keys move at full speed, the sticks proportionally to their deflection
+    let movement = movement.clamp_length_max(1.0);
     if movement != Vec3::ZERO {
         debug!(
             "Free camera controls: Moving {:?} at speed {} m/s",
-            movement, free_camera_mode.speed_mps
+            movement, speed
         );
-        let movement = movement.normalize();
-        let forward = transform.forward();
-        let right = transform.right();
-        let up = Vec3::Y;
-        let world_movement = movement.z * forward + movement.x * right + movement.y * up;
-        transform.translation += world_movement * free_camera_mode.speed_mps * time.delta_secs();
     }
+    let forward = transform.forward();
+    let right = transform.right();
+    let up = Vec3::Y;
+    let target_velocity = (movement.z * forward + movement.x * right + movement.y * up) * speed;
+
+    let blend = if free_camera_mode.acceleration > 0.0 {
+        1.0 - (-free_camera_mode.acceleration * dt).exp()
+    } else {
+        1.0
+    };
+    *velocity = velocity.lerp(target_velocity, blend);
+    transform.translation += *velocity * dt;
 }
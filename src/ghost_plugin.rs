@@ -0,0 +1,424 @@
+use crate::drone_plugin::PlayerDrone;
+use crate::flight_controller_plugin::FlightController;
+use crate::level_plugin::{CurrentLevel, FinishGate, level_file_stem};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Records the player drone's trajectory during a run and replays the best previous runs as
+/// translucent ghost drones, in sync with the current run's timer.
+///
+/// A run starts when the flight controller arms and finishes when the drone reaches a level's
+/// [`FinishGate`]. `T` starts and finishes runs manually, e.g. on levels without a gate.
+/// Disarming aborts the run, so crashes never end up as ghosts, and runs finished with `T` are
+/// kept but never ranked. Every level has its own ghosts, see [`GhostSettings::level_directory`].
+pub struct GhostPlugin;
+
+impl Plugin for GhostPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GhostSettings>()
+            .init_resource::<RunTimer>()
+            .init_resource::<TrajectoryRecorder>()
+            .add_event::<RunEvent>()
+            .add_systems(
+                Update,
+                (
+                    (
+                        toggle_run,
+                        follow_arming,
+                        cross_finish_gates,
+                        advance_run_timer,
+                    )
+                        .chain(),
+                    (
+                        spawn_ghosts,
+                        save_trajectory,
+                        record_trajectory,
+                        move_ghosts,
+                    )
+                        .chain(),
+                )
+                    .chain(),
+            )
+            .register_type::<GhostSettings>()
+            .register_type::<RunTimer>()
+            .register_type::<Ghost>();
+    }
+}
+
+#[derive(Resource, Reflect, Clone)]
+#[reflect(Resource)]
+pub struct GhostSettings {
+    /// Ghosts of each level are kept in a folder of their own in here.
+    pub directory: String,
+    /// Trajectory samples recorded per second.
+    pub sample_rate_hz: f32,
+    /// How many of the fastest previous runs are shown as ghosts.
+    pub ghost_count: usize,
+    pub ghost_color: Color,
+}
+
+impl Default for GhostSettings {
+    fn default() -> Self {
+        Self {
+            directory: "saves/ghosts".to_string(),
+            sample_rate_hz: 30.0,
+            ghost_count: 3,
+            ghost_color: Color::srgba(0.6, 0.8, 1.0, 0.35),
+        }
+    }
+}
+
+impl GhostSettings {
+    /// Ghost folder of the level at asset path `level`, or of apps without levels.
+    pub fn level_directory(&self, level: Option<&str>) -> PathBuf {
+        let name = level.map_or_else(|| "default".to_string(), level_file_stem);
+        Path::new(&self.directory).join(name)
+    }
+}
+
+/// Asset path of the loaded level, if any.
+fn current_level_path(level: Option<Res<CurrentLevel>>) -> Option<String> {
+    level
+        .map(|level| level.path.clone())
+        .filter(|path| !path.is_empty())
+}
+
+/// Time since the current run started.
+#[derive(Resource, Reflect, Debug, Default, Clone)]
+#[reflect(Resource)]
+pub struct RunTimer {
+    pub elapsed_secs: f32,
+    pub running: bool,
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub enum RunEvent {
+    Started,
+    Finished {
+        duration_secs: f32,
+        /// Whether a [`FinishGate`] was reached, rather than the run finished by hand.
+        completed: bool,
+    },
+    /// The run ended without finishing, e.g. by disarming after a crash.
+    Aborted,
+}
+
+/// A drone replaying a recorded trajectory.
+#[derive(Component, Reflect, Debug, Clone)]
+#[reflect(Component)]
+pub struct Ghost {
+    /// Duration of the recorded run.
+    pub duration_secs: f32,
+    #[reflect(ignore)]
+    trajectory: Vec<TrajectorySample>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Trajectory {
+    duration_secs: f32,
+    /// Ghosts saved before runs needed a finish are arm to disarm spans, never ranked.
+    #[serde(default)]
+    completed: bool,
+    samples: Vec<TrajectorySample>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+struct TrajectorySample {
+    time_secs: f32,
+    translation: Vec3,
+    rotation: Quat,
+}
+
+impl Ghost {
+    /// Interpolated pose at `time_secs` into the run, `None` once the run is over.
+    fn pose_at(&self, time_secs: f32) -> Option<Transform> {
+        let next = self
+            .trajectory
+            .iter()
+            .position(|sample| sample.time_secs >= time_secs)?;
+        let after = self.trajectory[next];
+        let Some(before) = next.checked_sub(1).map(|index| self.trajectory[index]) else {
+            return Some(
+                Transform::from_translation(after.translation).with_rotation(after.rotation),
+            );
+        };
+        let t =
+            (time_secs - before.time_secs) / (after.time_secs - before.time_secs).max(f32::EPSILON);
+        Some(
+            Transform::from_translation(before.translation.lerp(after.translation, t))
+                .with_rotation(before.rotation.slerp(after.rotation, t)),
+        )
+    }
+}
+
+#[derive(Resource, Default)]
+struct TrajectoryRecorder {
+    samples: Vec<TrajectorySample>,
+}
+
+fn toggle_run(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut timer: ResMut<RunTimer>,
+    mut events: EventWriter<RunEvent>,
+) {
+    if !keyboard.just_pressed(KeyCode::KeyT) {
+        return;
+    }
+    if timer.running {
+        finish_run(&mut timer, &mut events, false);
+    } else {
+        start_run(&mut timer, &mut events);
+    }
+}
+
+fn follow_arming(
+    mut timer: ResMut<RunTimer>,
+    mut events: EventWriter<RunEvent>,
+    fc: Query<&FlightController, (With<PlayerDrone>, Changed<FlightController>)>,
+    mut was_armed: Local<bool>,
+) {
+    let Ok(fc) = fc.single() else {
+        return;
+    };
+    if fc.armed && !*was_armed {
+        start_run(&mut timer, &mut events);
+    } else if !fc.armed && *was_armed {
+        abort_run(&mut timer, &mut events);
+    }
+    *was_armed = fc.armed;
+}
+
+fn start_run(timer: &mut RunTimer, events: &mut EventWriter<RunEvent>) {
+    if timer.running {
+        return;
+    }
+    *timer = RunTimer {
+        elapsed_secs: 0.0,
+        running: true,
+    };
+    events.write(RunEvent::Started);
+    info!("Run started");
+}
+
+fn finish_run(timer: &mut RunTimer, events: &mut EventWriter<RunEvent>, completed: bool) {
+    if !timer.running {
+        return;
+    }
+    timer.running = false;
+    events.write(RunEvent::Finished {
+        duration_secs: timer.elapsed_secs,
+        completed,
+    });
+    info!("Run finished in {:.2}s", timer.elapsed_secs);
+}
+
+fn abort_run(timer: &mut RunTimer, events: &mut EventWriter<RunEvent>) {
+    if !timer.running {
+        return;
+    }
+    timer.running = false;
+    events.write(RunEvent::Aborted);
+    info!("Run aborted after {:.2}s", timer.elapsed_secs);
+}
+
+fn cross_finish_gates(
+    mut timer: ResMut<RunTimer>,
+    mut events: EventWriter<RunEvent>,
+    drone: Query<&GlobalTransform, With<PlayerDrone>>,
+    gates: Query<(&GlobalTransform, &FinishGate)>,
+    mut was_inside: Local<bool>,
+) {
+    let Ok(drone) = drone.single() else {
+        return;
+    };
+    let inside = gates
+        .iter()
+        .any(|(gate, finish)| gate.translation().distance(drone.translation()) <= finish.radius);
+    // Only entering the gate finishes, so runs starting inside it don't end right away
+    if inside && !*was_inside {
+        finish_run(&mut timer, &mut events, true);
+    }
+    *was_inside = inside;
+}
+
+fn advance_run_timer(mut timer: ResMut<RunTimer>, time: Res<Time>) {
+    if timer.running {
+        timer.elapsed_secs += time.delta_secs();
+    }
+}
+
+/// Previous trajectories in `directory` that reached a finish gate, fastest first.
+fn load_trajectories(directory: &Path) -> Vec<Trajectory> {
+    let Ok(entries) = std::fs::read_dir(directory) else {
+        return Vec::new();
+    };
+    let mut trajectories: Vec<Trajectory> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "ron"))
+        .filter_map(|path| {
+            let text = std::fs::read_to_string(&path).ok()?;
+            ron::de::from_str::<Trajectory>(&text)
+                .inspect_err(|e| warn!("Skipping invalid ghost {}: {e}", path.display()))
+                .ok()
+        })
+        .filter(|trajectory| trajectory.completed)
+        .collect();
+    trajectories.sort_by(|a, b| a.duration_secs.total_cmp(&b.duration_secs));
+    trajectories
+}
+
+fn spawn_ghosts(
+    mut commands: Commands,
+    mut events: EventReader<RunEvent>,
+    settings: Res<GhostSettings>,
+    level: Option<Res<CurrentLevel>>,
+    ghosts: Query<Entity, With<Ghost>>,
+    drone: Query<&Mesh3d, With<PlayerDrone>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut recorder: ResMut<TrajectoryRecorder>,
+) {
+    if !events
+        .read()
+        .any(|event| matches!(event, RunEvent::Started))
+    {
+        return;
+    }
+    recorder.samples.clear();
+    for entity in ghosts.iter() {
+        commands.entity(entity).despawn();
+    }
+    let Ok(mesh) = drone.single() else {
+        debug!("No drone mesh found to spawn ghosts with.");
+        return;
+    };
+
+    let material = materials.add(StandardMaterial {
+        base_color: settings.ghost_color,
+        alpha_mode: AlphaMode::Blend,
+        unlit: true,
+        ..default()
+    });
+    let directory = settings.level_directory(current_level_path(level).as_deref());
+    let trajectories = load_trajectories(&directory);
+    for (rank, trajectory) in trajectories
+        .into_iter()
+        .take(settings.ghost_count)
+        .enumerate()
+    {
+        let Some(first) = trajectory.samples.first() else {
+            continue;
+        };
+        commands.spawn((
+            Name::new(format!(
+                "Ghost {} ({:.2}s)",
+                rank + 1,
+                trajectory.duration_secs
+            )),
+            Ghost {
+                duration_secs: trajectory.duration_secs,
+                trajectory: trajectory.samples.clone(),
+            },
+            mesh.clone(),
+            MeshMaterial3d(material.clone()),
+            Transform::from_translation(first.translation).with_rotation(first.rotation),
+        ));
+    }
+}
+
+fn save_trajectory(
+    mut events: EventReader<RunEvent>,
+    settings: Res<GhostSettings>,
+    level: Option<Res<CurrentLevel>>,
+    mut recorder: ResMut<TrajectoryRecorder>,
+) {
+    let Some(finish) = events.read().find_map(|event| match event {
+        RunEvent::Finished {
+            duration_secs,
+            completed,
+        } => Some(Some((*duration_secs, *completed))),
+        RunEvent::Aborted => Some(None),
+        RunEvent::Started => None,
+    }) else {
+        return;
+    };
+    let Some((duration_secs, completed)) = finish else {
+        recorder.samples.clear();
+        return;
+    };
+    let trajectory = Trajectory {
+        duration_secs,
+        completed,
+        samples: std::mem::take(&mut recorder.samples),
+    };
+    if trajectory.samples.is_empty() {
+        return;
+    }
+
+    let directory = settings.level_directory(current_level_path(level).as_deref());
+    if let Err(e) = std::fs::create_dir_all(&directory) {
+        error!(
+            "Failed to create the ghost directory {}: {e}",
+            directory.display()
+        );
+        return;
+    }
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let path = directory.join(format!("ghost_{timestamp}.ron"));
+    let text = match ron::ser::to_string(&trajectory) {
+        Ok(text) => text,
+        Err(e) => {
+            error!("Failed to serialize the trajectory: {e}");
+            return;
+        }
+    };
+    match std::fs::write(&path, text) {
+        Ok(()) => info!("Ghost saved to {}", path.display()),
+        Err(e) => error!("Failed to save ghost {}: {e}", path.display()),
+    }
+}
+
+fn record_trajectory(
+    timer: Res<RunTimer>,
+    settings: Res<GhostSettings>,
+    mut recorder: ResMut<TrajectoryRecorder>,
+    drone: Query<&Transform, With<PlayerDrone>>,
+) {
+    if !timer.running {
+        return;
+    }
+    let Ok(transform) = drone.single() else {
+        debug!("No drone found to record a trajectory for.");
+        return;
+    };
+
+    let interval = 1.0 / settings.sample_rate_hz.max(1.0);
+    let due = recorder
+        .samples
+        .last()
+        .is_none_or(|last| timer.elapsed_secs - last.time_secs >= interval);
+    if due {
+        recorder.samples.push(TrajectorySample {
+            time_secs: timer.elapsed_secs,
+            translation: transform.translation,
+            rotation: transform.rotation,
+        });
+    }
+}
+
+fn move_ghosts(timer: Res<RunTimer>, mut ghosts: Query<(&Ghost, &mut Transform, &mut Visibility)>) {
+    for (ghost, mut transform, mut visibility) in ghosts.iter_mut() {
+        match ghost.pose_at(timer.elapsed_secs) {
+            Some(pose) => {
+                *transform = pose;
+                *visibility = Visibility::Inherited;
+            }
+            // The ghost finished its run
+            None => *visibility = Visibility::Hidden,
+        }
+    }
+}
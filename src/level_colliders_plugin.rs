@@ -0,0 +1,347 @@
+use crate::level_plugin::LevelRoot;
+use avian3d::prelude as avian;
+use bevy::gltf::{GltfExtras, GltfMeshExtras};
+use bevy::platform::collections::HashSet;
+use bevy::prelude::*;
+use bevy::render::primitives::Aabb;
+use bevy::scene::SceneInstanceReady;
+use bevy_rapier3d::prelude as rapier;
+use serde::Deserialize;
+
+/// Picks a [`LevelCollider`] for the meshes of a level's glTF geometry once it spawns, which
+/// [`AvianLevelCollidersPlugin`] or [`RapierLevelCollidersPlugin`] turn into static colliders of
+/// their physics engine. Both add this plugin.
+///
+/// Terrain, nodes named like [`LevelColliderSettings::terrain_prefixes`], gets trimesh colliders
+/// and everything else [`LevelColliderSettings::prop_shape`]. A node name can pick the shape with
+/// a tag after a dash, like `Gate-box` or `Rock-decomp.001`, and `-sensor` makes it a sensor.
+/// glTF extras on the node or mesh, e.g. Blender custom properties, override the name:
+/// `{"collider": "box", "sensor": true, "friction": 0.5, "restitution": 0.1}`.
+pub struct LevelCollidersPlugin;
+
+impl Plugin for LevelCollidersPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LevelColliderSettings>()
+            .add_observer(pick_level_colliders)
+            .register_type::<LevelColliderSettings>()
+            .register_type::<LevelColliderShape>()
+            .register_type::<LevelCollider>();
+    }
+}
+
+/// Avian colliders for level geometry, see [`LevelCollidersPlugin`].
+pub struct AvianLevelCollidersPlugin;
+
+impl Plugin for AvianLevelCollidersPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<LevelCollidersPlugin>() {
+            app.add_plugins(LevelCollidersPlugin);
+        }
+        app.add_observer(insert_avian_collider);
+    }
+}
+
+/// Rapier colliders for level geometry, see [`LevelCollidersPlugin`].
+pub struct RapierLevelCollidersPlugin;
+
+impl Plugin for RapierLevelCollidersPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<LevelCollidersPlugin>() {
+            app.add_plugins(LevelCollidersPlugin);
+        }
+        app.add_observer(insert_rapier_collider);
+    }
+}
+
+#[derive(Resource, Reflect, Clone)]
+#[reflect(Resource)]
+pub struct LevelColliderSettings {
+    pub enabled: bool,
+    /// Nodes whose name starts with one of these are terrain.
+    pub terrain_prefixes: Vec<String>,
+    pub terrain_shape: LevelColliderShape,
+    pub prop_shape: LevelColliderShape,
+}
+
+impl Default for LevelColliderSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            terrain_prefixes: vec!["Terrain".to_string(), "Ground".to_string()],
+            terrain_shape: LevelColliderShape::Trimesh,
+            prop_shape: LevelColliderShape::ConvexHull,
+        }
+    }
+}
+
+/// Collider generated for a level mesh.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LevelColliderShape {
+    /// No collider.
+    None,
+    /// The mesh's bounding box.
+    Box,
+    /// Sphere around the mesh's bounding box.
+    Sphere,
+    ConvexHull,
+    /// Convex parts approximating a concave mesh, slower to generate than a hull.
+    ConvexDecomposition,
+    /// The exact triangles, for terrain and other static concave geometry.
+    Trimesh,
+}
+
+impl LevelColliderShape {
+    fn from_tag(tag: &str) -> Option<Self> {
+        match tag.to_ascii_lowercase().as_str() {
+            "nocol" => Some(Self::None),
+            "box" => Some(Self::Box),
+            "sphere" => Some(Self::Sphere),
+            "convex" => Some(Self::ConvexHull),
+            "decomp" => Some(Self::ConvexDecomposition),
+            "trimesh" => Some(Self::Trimesh),
+            _ => None,
+        }
+    }
+}
+
+/// Collider picked for a level mesh. The mesh's node becomes the static body.
+#[derive(Component, Reflect, Debug, Clone)]
+#[reflect(Component)]
+pub struct LevelCollider {
+    /// Never [`LevelColliderShape::None`], those meshes get no collider.
+    pub shape: LevelColliderShape,
+    pub sensor: bool,
+    pub friction: Option<f32>,
+    pub restitution: Option<f32>,
+}
+
+/// Collider properties from glTF extras, unset fields fall back to the node name.
+#[derive(Debug, Default, Clone, Deserialize)]
+struct ColliderExtras {
+    collider: Option<LevelColliderShape>,
+    sensor: Option<bool>,
+    friction: Option<f32>,
+    restitution: Option<f32>,
+}
+
+impl ColliderExtras {
+    fn parse(json: &str) -> Self {
+        serde_json::from_str(json)
+            .inspect_err(|e| warn!("Ignoring invalid collider extras {json}: {e}"))
+            .unwrap_or_default()
+    }
+
+    /// Fields set in `other` win.
+    fn merge(self, other: Self) -> Self {
+        Self {
+            collider: other.collider.or(self.collider),
+            sensor: other.sensor.or(self.sensor),
+            friction: other.friction.or(self.friction),
+            restitution: other.restitution.or(self.restitution),
+        }
+    }
+}
+
+/// Collider properties from the naming convention, `Name-tag-tag.001`.
+fn name_extras(name: &str, settings: &LevelColliderSettings) -> ColliderExtras {
+    // Blender numbers duplicates with a `.001` suffix
+    let name = match name.rsplit_once('.') {
+        Some((name, number)) if number.chars().all(|c| c.is_ascii_digit()) => name,
+        _ => name,
+    };
+    let mut parts = name.split('-');
+    let base = parts.next().unwrap_or_default();
+    let mut extras = ColliderExtras::default();
+    for tag in parts {
+        if tag.eq_ignore_ascii_case("sensor") {
+            extras.sensor = Some(true);
+        } else if let Some(shape) = LevelColliderShape::from_tag(tag) {
+            extras.collider = Some(shape);
+        }
+    }
+    if extras.collider.is_none() {
+        let terrain = settings
+            .terrain_prefixes
+            .iter()
+            .any(|prefix| base.starts_with(prefix.as_str()));
+        extras.collider = Some(if terrain {
+            settings.terrain_shape
+        } else {
+            settings.prop_shape
+        });
+    }
+    extras
+}
+
+fn pick_level_colliders(
+    trigger: Trigger<SceneInstanceReady>,
+    mut commands: Commands,
+    settings: Res<LevelColliderSettings>,
+    roots: Query<(), With<LevelRoot>>,
+    children: Query<&Children>,
+    meshes: Query<
+        (&ChildOf, Option<&GltfExtras>, Option<&GltfMeshExtras>),
+        (With<Mesh3d>, Without<LevelCollider>),
+    >,
+    nodes: Query<(Option<&Name>, Option<&GltfExtras>)>,
+) {
+    let root = trigger.target();
+    if !settings.enabled || !roots.contains(root) {
+        return;
+    }
+
+    let mut bodies = HashSet::new();
+    let mut generated = 0;
+    for entity in children.iter_descendants(root) {
+        let Ok((child_of, primitive_extras, mesh_extras)) = meshes.get(entity) else {
+            continue;
+        };
+        // glTF meshes are spawned as children of the node carrying the name and transform
+        let node = child_of.parent();
+        let Ok((name, node_extras)) = nodes.get(node) else {
+            continue;
+        };
+
+        let name = name.map(Name::as_str).unwrap_or_default();
+        let extras = [
+            node_extras.map(|extras| extras.value.as_str()),
+            mesh_extras.map(|extras| extras.value.as_str()),
+            primitive_extras.map(|extras| extras.value.as_str()),
+        ]
+        .into_iter()
+        .flatten()
+        .map(ColliderExtras::parse)
+        .fold(name_extras(name, &settings), ColliderExtras::merge);
+
+        let shape = extras.collider.unwrap_or(settings.prop_shape);
+        if shape == LevelColliderShape::None {
+            continue;
+        }
+        commands.entity(entity).insert(LevelCollider {
+            shape,
+            sensor: extras.sensor == Some(true),
+            friction: extras.friction,
+            restitution: extras.restitution,
+        });
+        bodies.insert(node);
+        generated += 1;
+    }
+    info!(
+        "Generated {generated} colliders on {} level nodes",
+        bodies.len()
+    );
+}
+
+fn insert_avian_collider(
+    trigger: Trigger<OnAdd, LevelCollider>,
+    mut commands: Commands,
+    colliders: Query<(&LevelCollider, &Aabb, &ChildOf)>,
+) {
+    let entity = trigger.target();
+    let Ok((level_collider, aabb, child_of)) = colliders.get(entity) else {
+        return;
+    };
+    let mut collider = commands.entity(entity);
+    match level_collider.shape {
+        LevelColliderShape::None => return,
+        LevelColliderShape::Box => {
+            let size = Vec3::from(aabb.half_extents) * 2.0;
+            collider.insert(avian::Collider::compound(vec![(
+                Vec3::from(aabb.center),
+                Quat::IDENTITY,
+                avian::Collider::cuboid(size.x, size.y, size.z),
+            )]));
+        }
+        LevelColliderShape::Sphere => {
+            collider.insert(avian::Collider::compound(vec![(
+                Vec3::from(aabb.center),
+                Quat::IDENTITY,
+                avian::Collider::sphere(aabb.half_extents.length()),
+            )]));
+        }
+        LevelColliderShape::ConvexHull => {
+            collider.insert(avian::ColliderConstructor::ConvexHullFromMesh);
+        }
+        LevelColliderShape::ConvexDecomposition => {
+            collider.insert(avian::ColliderConstructor::ConvexDecompositionFromMesh);
+        }
+        LevelColliderShape::Trimesh => {
+            collider.insert(avian::ColliderConstructor::TrimeshFromMesh);
+        }
+    }
+    if level_collider.sensor {
+        collider.insert(avian::Sensor);
+    }
+    if let Some(friction) = level_collider.friction {
+        collider.insert(avian::Friction::new(friction));
+    }
+    if let Some(restitution) = level_collider.restitution {
+        collider.insert(avian::Restitution::new(restitution));
+    }
+    // One static body per node, its primitives are the colliders
+    commands
+        .entity(child_of.parent())
+        .insert(avian::RigidBody::Static);
+}
+
+fn insert_rapier_collider(
+    trigger: Trigger<OnAdd, LevelCollider>,
+    mut commands: Commands,
+    colliders: Query<(&LevelCollider, &Aabb, &ChildOf)>,
+) {
+    let entity = trigger.target();
+    let Ok((level_collider, aabb, child_of)) = colliders.get(entity) else {
+        return;
+    };
+    let mut collider = commands.entity(entity);
+    match level_collider.shape {
+        LevelColliderShape::None => return,
+        LevelColliderShape::Box => {
+            let half_extents = Vec3::from(aabb.half_extents);
+            collider.insert(rapier::Collider::compound(vec![(
+                Vec3::from(aabb.center),
+                Quat::IDENTITY,
+                rapier::Collider::cuboid(half_extents.x, half_extents.y, half_extents.z),
+            )]));
+        }
+        LevelColliderShape::Sphere => {
+            collider.insert(rapier::Collider::compound(vec![(
+                Vec3::from(aabb.center),
+                Quat::IDENTITY,
+                rapier::Collider::ball(aabb.half_extents.length()),
+            )]));
+        }
+        // Built from the mesh once it's loaded, like Avian's `ColliderConstructor`
+        LevelColliderShape::ConvexHull => {
+            collider.insert(rapier::AsyncCollider(
+                rapier::ComputedColliderShape::ConvexHull,
+            ));
+        }
+        LevelColliderShape::ConvexDecomposition => {
+            collider.insert(rapier::AsyncCollider(
+                rapier::ComputedColliderShape::ConvexDecomposition(
+                    rapier::VHACDParameters::default(),
+                ),
+            ));
+        }
+        LevelColliderShape::Trimesh => {
+            collider.insert(rapier::AsyncCollider(
+                rapier::ComputedColliderShape::TriMesh(rapier::TriMeshFlags::default()),
+            ));
+        }
+    }
+    if level_collider.sensor {
+        collider.insert(rapier::Sensor);
+    }
+    if let Some(friction) = level_collider.friction {
+        collider.insert(rapier::Friction::coefficient(friction));
+    }
+    if let Some(restitution) = level_collider.restitution {
+        collider.insert(rapier::Restitution::coefficient(restitution));
+    }
+    // One fixed body per node, its primitives are the colliders
+    commands
+        .entity(child_of.parent())
+        .insert(rapier::RigidBody::Fixed);
+}
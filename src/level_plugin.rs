@@ -0,0 +1,511 @@
+use crate::drone_plugin::{DroneKinematics, PlayerDrone};
+use crate::simulation_plugin::SimulationCommand;
+use bevy::asset::io::Reader;
+use bevy::asset::io::file::FileAssetReader;
+use bevy::asset::{AssetLoader, LoadContext, RecursiveDependencyLoadState};
+use bevy::ecs::entity::EntityHashMap;
+use bevy::platform::collections::{HashMap, HashSet};
+use bevy::prelude::*;
+use bevy::scene::SceneInstanceReady;
+use bevy_inspector_egui::bevy_egui::{EguiContexts, EguiPrimaryContextPass};
+use bevy_inspector_egui::egui;
+use serde::Deserialize;
+
+/// File extension of level assets.
+const LEVEL_EXTENSION: &str = "level.ron";
+
+/// Loads levels from `levels/*.level.ron` assets instead of hard-coded setup systems.
+///
+/// A level is static geometry from a glTF scene plus an overlay in Bevy's `.scn.ron` scene
+/// format. Overlay entities with a `Name` that already exists in the world, from the level
+/// geometry or the app's own setup, get their components patched. The others are spawned as
+/// new level entities. Loading a level unloads the previous one.
+///
+/// Pick the first level with [`LevelSettings::startup_level`], send [`LoadLevel`] from other
+/// UI, or press `L` for the level list.
+pub struct LevelPlugin;
+
+impl Plugin for LevelPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<Level>()
+            .init_asset_loader::<LevelLoader>()
+            .init_resource::<LevelSettings>()
+            .init_resource::<CurrentLevel>()
+            .add_event::<LoadLevel>()
+            .add_event::<UnloadLevel>()
+            .add_event::<LevelLoaded>()
+            .add_systems(Startup, load_startup_level)
+            .add_systems(
+                Update,
+                (
+                    unload_level,
+                    start_loading_level,
+                    spawn_level_geometry,
+                    apply_level_entities.run_if(level_geometry_ready),
+                )
+                    .chain()
+                    .in_set(LevelLoadingSet),
+            )
+            .add_systems(EguiPrimaryContextPass, levels_window)
+            .register_type::<LevelSettings>()
+            .register_type::<LevelRoot>()
+            .register_type::<LevelEntity>()
+            .register_type::<FinishGate>();
+    }
+}
+
+/// Level loading and unloading run in this set during `Update`.
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LevelLoadingSet;
+
+#[derive(Resource, Reflect, Clone)]
+#[reflect(Resource)]
+pub struct LevelSettings {
+    /// The asset folder, as set in `AssetPlugin::file_path`.
+    pub asset_folder: String,
+    /// Directory of the level files, inside the asset folder.
+    pub directory: String,
+    /// Level loaded on startup, as an asset path like `levels/hover_test.level.ron`.
+    pub startup_level: Option<String>,
+}
+
+impl Default for LevelSettings {
+    fn default() -> Self {
+        Self {
+            asset_folder: "assets".to_string(),
+            directory: "levels".to_string(),
+            startup_level: None,
+        }
+    }
+}
+
+/// A level: static geometry, an entity overlay and the player's start position.
+#[derive(Asset, TypePath, Debug)]
+pub struct Level {
+    pub name: String,
+    pub description: String,
+    /// Static geometry, a glTF scene.
+    pub geometry: Option<Handle<Scene>>,
+    /// Component state patched onto named entities, and dynamic entities to spawn.
+    pub entities: Option<Handle<DynamicScene>>,
+    /// Where the player drone starts.
+    pub spawn: Option<Transform>,
+    /// Whether the level is made for flying, the main menu doesn't offer the others.
+    pub flight: bool,
+}
+
+/// Contents of a `.level.ron` file. Paths are relative to the level file.
+#[derive(Deserialize)]
+struct LevelFile {
+    name: String,
+    #[serde(default)]
+    description: String,
+    /// glTF file, `#Scene0` is used unless the path names a scene.
+    #[serde(default)]
+    geometry: Option<String>,
+    /// `.scn.ron` scene.
+    #[serde(default)]
+    entities: Option<String>,
+    #[serde(default)]
+    spawn: Option<Vec3>,
+    /// Heading of the player drone at the spawn, degrees clockwise from north (-Z).
+    #[serde(default)]
+    spawn_heading_deg: f32,
+    /// `false` for levels of the physics engine demos.
+    #[serde(default = "default_flight")]
+    flight: bool,
+}
+
+fn default_flight() -> bool {
+    true
+}
+
+#[derive(Default)]
+struct LevelLoader;
+
+impl AssetLoader for LevelLoader {
+    type Asset = Level;
+    type Settings = ();
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Level, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let file: LevelFile = ron::de::from_bytes(&bytes)?;
+
+        let geometry = match file.geometry {
+            Some(path) => {
+                let path = if path.contains('#') {
+                    path
+                } else {
+                    format!("{path}#Scene0")
+                };
+                let path = load_context.asset_path().resolve_embed(&path)?;
+                Some(load_context.load(path))
+            }
+            None => None,
+        };
+        let entities = match file.entities {
+            Some(path) => {
+                let path = load_context.asset_path().resolve_embed(&path)?;
+                Some(load_context.load(path))
+            }
+            None => None,
+        };
+        let spawn = file.spawn.map(|position| {
+            Transform::from_translation(position)
+                .with_rotation(Quat::from_rotation_y(-file.spawn_heading_deg.to_radians()))
+        });
+
+        Ok(Level {
+            name: file.name,
+            description: file.description,
+            geometry,
+            entities,
+            spawn,
+            flight: file.flight,
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &[LEVEL_EXTENSION]
+    }
+}
+
+/// Unloads the current level and loads the level at this asset path.
+#[derive(Event, Debug, Clone)]
+pub struct LoadLevel(pub String);
+
+/// Despawns the current level, e.g. when going back to a menu.
+#[derive(Event, Debug, Clone)]
+pub struct UnloadLevel;
+
+/// Sent once a level is fully spawned, with its asset path.
+#[derive(Event, Debug, Clone)]
+pub struct LevelLoaded(pub String);
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LevelStatus {
+    #[default]
+    None,
+    /// Waiting for the level file and everything it references.
+    Loading,
+    /// Waiting for the glTF scene to spawn.
+    SpawningGeometry,
+    /// The geometry is in the world, the overlay is applied next.
+    GeometryReady,
+    Loaded,
+    Failed,
+}
+
+#[derive(Resource, Default)]
+pub struct CurrentLevel {
+    /// Asset path of the level.
+    pub path: String,
+    pub handle: Handle<Level>,
+    pub status: LevelStatus,
+    root: Option<Entity>,
+}
+
+/// Parent of the level geometry.
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+pub struct LevelRoot;
+
+/// Finish line of a timed run, reached when the player drone flies within `radius` meters of
+/// the entity. Added to level geometry through the entity overlay.
+#[derive(Component, Reflect, Debug, Clone)]
+#[reflect(Component, Default)]
+pub struct FinishGate {
+    pub radius: f32,
+}
+
+impl Default for FinishGate {
+    fn default() -> Self {
+        Self { radius: 2.0 }
+    }
+}
+
+/// Entity spawned by a level overlay, despawned with the level.
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+pub struct LevelEntity;
+
+/// File name stem for data kept per level, `levels/hover_test.level.ron` is
+/// `levels_hover_test`.
+pub fn level_file_stem(path: &str) -> String {
+    path.replace(['/', '\\'], "_")
+        .split('.')
+        .next()
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Asset paths of the level files in [`LevelSettings::directory`], sorted by name. The asset
+/// folder is resolved like the asset server does, so this works from any working directory.
+pub fn available_levels(settings: &LevelSettings) -> Vec<String> {
+    let directory = &settings.directory;
+    let folder = FileAssetReader::new(&settings.asset_folder)
+        .root_path()
+        .join(directory);
+    let Ok(entries) = std::fs::read_dir(&folder) else {
+        warn!("No level directory {}", folder.display());
+        return Vec::new();
+    };
+    let mut levels: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| name.ends_with(&format!(".{LEVEL_EXTENSION}")))
+        .map(|name| format!("{directory}/{name}"))
+        .collect();
+    levels.sort();
+    levels
+}
+
+fn load_startup_level(settings: Res<LevelSettings>, mut load: EventWriter<LoadLevel>) {
+    if let Some(path) = &settings.startup_level {
+        load.write(LoadLevel(path.clone()));
+    }
+}
+
+fn despawn_level(
+    commands: &mut Commands,
+    current: &mut CurrentLevel,
+    level_entities: &Query<Entity, With<LevelEntity>>,
+) {
+    if let Some(root) = current.root.take() {
+        commands.entity(root).despawn();
+    }
+    for entity in level_entities.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+fn unload_level(
+    mut commands: Commands,
+    mut requests: EventReader<UnloadLevel>,
+    mut current: ResMut<CurrentLevel>,
+    level_entities: Query<Entity, With<LevelEntity>>,
+) {
+    if requests.read().last().is_none() {
+        return;
+    }
+    despawn_level(&mut commands, &mut current, &level_entities);
+    *current = CurrentLevel::default();
+}
+
+fn start_loading_level(
+    mut commands: Commands,
+    mut requests: EventReader<LoadLevel>,
+    asset_server: Res<AssetServer>,
+    mut current: ResMut<CurrentLevel>,
+    level_entities: Query<Entity, With<LevelEntity>>,
+) {
+    let Some(LoadLevel(path)) = requests.read().last() else {
+        return;
+    };
+
+    despawn_level(&mut commands, &mut current, &level_entities);
+    info!("Loading level {path}");
+    current.path = path.clone();
+    current.handle = asset_server.load(path.clone());
+    current.status = LevelStatus::Loading;
+}
+
+fn spawn_level_geometry(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    levels: Res<Assets<Level>>,
+    mut current: ResMut<CurrentLevel>,
+) {
+    if current.status != LevelStatus::Loading {
+        return;
+    }
+    match asset_server.recursive_dependency_load_state(&current.handle) {
+        RecursiveDependencyLoadState::Loaded => {}
+        RecursiveDependencyLoadState::Failed(e) => {
+            error!("Failed to load the level {}: {e}", current.path);
+            current.status = LevelStatus::Failed;
+            return;
+        }
+        _ => return,
+    }
+    let Some(level) = levels.get(&current.handle) else {
+        return;
+    };
+
+    let Some(geometry) = level.geometry.clone() else {
+        current.status = LevelStatus::GeometryReady;
+        return;
+    };
+    let root = commands
+        .spawn((
+            Name::new(format!("Level {}", level.name)),
+            LevelRoot,
+            Transform::default(),
+            Visibility::default(),
+            SceneRoot(geometry),
+        ))
+        .observe(
+            |_: Trigger<SceneInstanceReady>, mut current: ResMut<CurrentLevel>| {
+                if current.status == LevelStatus::SpawningGeometry {
+                    current.status = LevelStatus::GeometryReady;
+                }
+            },
+        )
+        .id();
+    current.root = Some(root);
+    current.status = LevelStatus::SpawningGeometry;
+}
+
+fn level_geometry_ready(current: Res<CurrentLevel>) -> bool {
+    current.status == LevelStatus::GeometryReady
+}
+
+fn apply_level_entities(world: &mut World) {
+    let handle = world.resource::<CurrentLevel>().handle.clone();
+    let Some(level) = world.resource::<Assets<Level>>().get(&handle) else {
+        error!("The level asset was unloaded before it was spawned.");
+        world.resource_mut::<CurrentLevel>().status = LevelStatus::Failed;
+        return;
+    };
+    let overlay = level.entities.clone();
+    let spawn = level.spawn;
+    let name = level.name.clone();
+
+    if let Some(overlay) = overlay {
+        let mut named = world.query::<(Entity, &Name)>();
+        let by_name: HashMap<String, Entity> = named
+            .iter(world)
+            .map(|(entity, name)| (name.as_str().to_string(), entity))
+            .collect();
+
+        world.resource_scope(|world, scenes: Mut<Assets<DynamicScene>>| {
+            let Some(scene) = scenes.get(&overlay) else {
+                error!("The entity overlay of level {name} isn't loaded.");
+                return;
+            };
+
+            // Overlay entities named like existing ones patch those instead of spawning
+            let mut entity_map = EntityHashMap::default();
+            for scene_entity in &scene.entities {
+                let target = scene_entity
+                    .components
+                    .iter()
+                    .find_map(|component| Name::from_reflect(component.as_partial_reflect()))
+                    .and_then(|name| by_name.get(name.as_str()));
+                if let Some(target) = target {
+                    entity_map.insert(scene_entity.entity, *target);
+                }
+            }
+            let patched: HashSet<Entity> = entity_map.values().copied().collect();
+
+            if let Err(e) = scene.write_to_world(world, &mut entity_map) {
+                error!("Failed to apply the entity overlay of level {name}: {e}");
+            }
+
+            let spawned: Vec<Entity> = entity_map
+                .values()
+                .filter(|entity| !patched.contains(*entity))
+                .copied()
+                .collect();
+            for entity in &spawned {
+                world.entity_mut(*entity).insert(LevelEntity);
+            }
+            info!(
+                "Level {name}: patched {} entities, spawned {}",
+                patched.len(),
+                spawned.len()
+            );
+        });
+    }
+
+    if let Some(spawn) = spawn {
+        let mut drones = world.query_filtered::<Entity, With<PlayerDrone>>();
+        let drones: Vec<Entity> = drones.iter(world).collect();
+        for drone in drones {
+            // Fresh kinematics, so the jump to the spawn doesn't read as velocity
+            world
+                .entity_mut(drone)
+                .insert((spawn, DroneKinematics::default()));
+        }
+    }
+
+    let mut current = world.resource_mut::<CurrentLevel>();
+    current.status = LevelStatus::Loaded;
+    let path = current.path.clone();
+    info!("Loaded level {name}");
+
+    // Restart goes back to the level as loaded, not as it was on startup
+    if let Some(mut commands) = world.get_resource_mut::<Events<SimulationCommand>>() {
+        commands.send(SimulationCommand::CaptureSnapshot);
+    }
+    world.send_event(LevelLoaded(path));
+}
+
+fn levels_window(
+    mut contexts: EguiContexts,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    settings: Res<LevelSettings>,
+    current: Res<CurrentLevel>,
+    levels: Res<Assets<Level>>,
+    mut load: EventWriter<LoadLevel>,
+    mut open: Local<bool>,
+    mut available: Local<Vec<String>>,
+) {
+    let Ok(ctx) = contexts.ctx_mut() else {
+        debug!("No Egui context found for the level list.");
+        return;
+    };
+    if keyboard.just_pressed(KeyCode::KeyL) && !ctx.wants_keyboard_input() {
+        *open = !*open;
+        if *open {
+            *available = available_levels(&settings);
+        }
+    }
+    if !*open {
+        return;
+    }
+
+    let mut still_open = true;
+    egui::Window::new("Levels")
+        .open(&mut still_open)
+        .show(ctx, |ui| {
+            match levels.get(&current.handle) {
+                Some(level) => {
+                    ui.heading(&level.name);
+                    if !level.description.is_empty() {
+                        ui.label(&level.description);
+                    }
+                }
+                None => {
+                    ui.label("No level loaded");
+                }
+            }
+            ui.label(format!("Status: {:?}", current.status));
+
+            ui.separator();
+            for path in available.iter() {
+                ui.horizontal(|ui| {
+                    ui.label(path);
+                    let label = if *path == current.path {
+                        "Reload"
+                    } else {
+                        "Load"
+                    };
+                    if ui.button(label).clicked() {
+                        load.write(LoadLevel(path.clone()));
+                    }
+                });
+            }
+            if ui.button("Refresh").clicked() {
+                *available = available_levels(&settings);
+            }
+        });
+    *open &= still_open;
+}
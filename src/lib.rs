@@ -1,5 +1,32 @@
+pub mod analog_video_plugin;
 pub mod avian_falling_cubes_plugin;
+pub mod blackbox_plugin;
+pub mod camera_bookmarks_plugin;
+pub mod camera_modes_plugin;
+pub mod drone_plugin;
+pub mod flight_controller_plugin;
+pub mod flight_path_plugin;
+pub mod fpv_camera_plugin;
 pub mod free_camera_plugin;
+pub mod ghost_plugin;
+pub mod level_colliders_plugin;
+pub mod level_plugin;
+pub mod main_menu_plugin;
+pub mod mavlink_link;
+pub mod mavlink_telemetry_plugin;
+pub mod msp_server_plugin;
+pub mod network_input_plugin;
+pub mod osd_plugin;
+pub mod prefab_asset_library;
+pub mod px4_sitl_plugin;
 pub mod rapier_falling_cubes_plugin;
+pub mod rc_input_plugin;
+pub mod rc_protocol;
+pub mod replay_plugin;
 pub mod rotating_cube_plugin;
+pub mod save_format;
+pub mod save_migration;
 pub mod save_system_plugin;
+pub mod simulation_plugin;
+pub mod snapshot_tree;
+pub mod toast_plugin;
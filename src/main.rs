@@ -1,8 +1,22 @@
-use bevy::input::gamepad::GamepadInput;
 use bevy::prelude::*;
+use bevy_drone_sim::analog_video_plugin::AnalogVideoPlugin;
+use bevy_drone_sim::camera_modes_plugin::CameraModesPlugin;
+use bevy_drone_sim::drone_plugin::{DronePlugin, DronePosition, PlayerDrone};
+use bevy_drone_sim::flight_controller_plugin::FlightControllerPlugin;
+use bevy_drone_sim::fpv_camera_plugin::FpvCameraPlugin;
+use bevy_drone_sim::level_plugin::LevelPlugin;
+use bevy_drone_sim::main_menu_plugin::MainMenuPlugin;
+use bevy_drone_sim::mavlink_telemetry_plugin::MavlinkTelemetryPlugin;
+use bevy_drone_sim::network_input_plugin::NetworkInputPlugin;
+use bevy_drone_sim::osd_plugin::OsdPlugin;
+use bevy_drone_sim::rc_input_plugin::RcInputPlugin;
+use bevy_drone_sim::simulation_plugin::SimulationPlugin;
 use bevy_inspector_egui::bevy_egui::EguiPlugin;
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
 
+/// The simulator: pick a level, an airframe and an input profile in the menu and fly.
+/// Arm with `M`, `Escape` opens the pause menu. The physics engine, camera and save demos are
+/// separate binaries, see `src/bin`.
 fn main() {
     println!("Starting...");
 
@@ -12,21 +26,20 @@ fn main() {
         // Egui and World Inspector Plugins
         .add_plugins(EguiPlugin::default())
         .add_plugins(WorldInspectorPlugin::new())
-        // Game types
-        .register_type::<PlayerDrone>()
-        .register_type::<DronePosition>()
+        // Game plugins
+        .add_plugins((DronePlugin, FlightControllerPlugin))
+        .add_plugins((RcInputPlugin, NetworkInputPlugin, MavlinkTelemetryPlugin))
+        .add_plugins((
+            FpvCameraPlugin,
+            AnalogVideoPlugin,
+            CameraModesPlugin,
+            OsdPlugin,
+        ))
+        .add_plugins((SimulationPlugin, LevelPlugin, MainMenuPlugin))
         // Game resources
         // Game systems
-        .add_systems(Startup, (setup, setup_ui, spawn_stick_position_ui))
-        .add_systems(
-            Update,
-            (
-                update_drone_controls,
-                update_drone_controls_ui,
-                update_stick_position,
-            ),
-        )
-        .add_systems(Update, rotate_drone_system)
+        .add_systems(Startup, (setup, spawn_stick_position_ui))
+        .add_systems(Update, update_stick_position)
         // .add_systems(Update, list_gamepads)
         .run();
     info!("App exited with: {:?}", exit);
@@ -121,113 +134,17 @@ fn update_stick_position(
     }
 }
 
-#[derive(Resource)]
-struct DroneControlsText(Entity);
-
-fn setup_ui(mut commands: Commands) {
-    let text_entity = commands
-        .spawn((
-            Node {
-                width: Val::Percent(100.),
-                height: Val::Px(100.),
-                align_self: AlignSelf::FlexEnd,
-                justify_self: JustifySelf::End,
-                ..Default::default()
-            },
-            Name::new("Drone Controls Text"),
-            Text::new("Drone Controls: "),
-        ))
-        .id();
-
-    commands.insert_resource(DroneControlsText(text_entity));
-}
-
-fn update_drone_controls_ui(
-    controls: Query<&DronePosition, With<PlayerDrone>>,
-    text_res: Res<DroneControlsText>,
-    mut query: Query<&mut Text>,
-) {
-    let Some(controls) = controls.single().ok() else {
-        info!("No drone controls found.");
-        return;
-    };
-    let Some(mut text) = query.get_mut(text_res.0).ok() else {
-        info!("No text entity found for drone controls.");
-        return;
-    };
-    text.0 = format!(
-        "Thrust: {:.2}\nPitch: {:.2}\nRoll: {:.2}\nYaw: {:.2}",
-        controls.throttle, controls.pitch, controls.roll, controls.yaw
-    );
-}
-#[derive(Debug, Default, Component, Reflect)]
-pub struct DronePosition {
-    pub throttle: f32,
-    pub yaw: f32,
-    pub pitch: f32,
-    pub roll: f32,
-}
-
-fn update_drone_controls(
-    gamepad: Query<&Gamepad>,
-    mut controls: Query<&mut DronePosition, With<PlayerDrone>>,
-) {
-    let Ok(gamepad) = gamepad.single() else {
-        debug!("No gamepad found for drone controls.");
-        return;
-    };
-    let Ok(mut controls) = controls.single_mut() else {
-        debug!("No drone controls found.");
-        return;
-    };
-
-    let left_stick = gamepad.left_stick();
-    let right_stick = gamepad.right_stick();
-    let right_trigger2 = gamepad
-        .get(GamepadInput::Button(GamepadButton::RightTrigger2))
-        .unwrap_or(0.);
-
-    controls.throttle = right_trigger2;
-    controls.yaw = right_stick.x;
-    controls.pitch = -left_stick.y;
-    controls.roll = left_stick.x;
-}
-
-fn rotate_drone_system(mut drone: Query<(&mut Transform, &DronePosition), With<PlayerDrone>>) {
-    let Ok((mut transform, drone_controls)) = drone.single_mut() else {
-        debug!("No drone entity found.");
-        return;
-    };
-
-    trace!("{drone_controls:?}");
-    // Get the gamepad's left stick input.
-    let DronePosition {
-        throttle: thrust,
-        pitch,
-        roll,
-        yaw,
-    } = drone_controls;
-
-    let coef = 1.0;
-    // Apply rotation based on the gamepad input.
-    transform.rotation = Quat::from_euler(EulerRot::YXZ, -yaw * coef, pitch * coef, roll * coef);
-    transform.translation.y = *thrust;
-}
-
-#[derive(Component, Reflect)]
-struct PlayerDrone;
-
 fn setup(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
 ) {
-    // Spawn a cube to rotate.
+    // Levels place the drone at their spawn
     commands.spawn((
         Name::new("Drone"),
         PlayerDrone,
         DronePosition::default(),
-        Mesh3d(meshes.add(Cuboid::default())),
+        Mesh3d(meshes.add(Cuboid::new(0.25, 0.05, 0.25))),
         MeshMaterial3d(materials.add(Color::WHITE)),
         Transform::from_translation(Vec3::ZERO),
     ));
@@ -237,19 +154,6 @@ fn setup(
         Camera3d::default(),
         Transform::from_xyz(-2.0, 2.0, -5.0).looking_at(Vec3::ZERO, Vec3::Y),
     ));
-
-    // Add a light source so we can see clearly.
-    commands.spawn((
-        DirectionalLight {
-            color: Color::Srgba(Srgba::new(1.0, 0.0, 0.0, 1.0)),
-            illuminance: 200.0,
-            shadows_enabled: false,
-            affects_lightmapped_mesh_diffuse: false,
-            shadow_depth_bias: 0.0,
-            shadow_normal_bias: 0.0,
-        },
-        Transform::from_xyz(3.0, 3.0, 3.0).looking_at(Vec3::ZERO, Vec3::Y),
-    ));
 }
 
 // #[allow(dead_code)]
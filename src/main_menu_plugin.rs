@@ -0,0 +1,538 @@
+use crate::drone_plugin::{
+    DroneBattery, DroneFrame, DroneInputSource, DroneKinematics, PlayerDrone,
+};
+use crate::level_plugin::{
+    CurrentLevel, Level, LevelLoaded, LevelLoadingSet, LevelSettings, LevelStatus, LoadLevel,
+    UnloadLevel, available_levels,
+};
+use crate::simulation_plugin::{SimulationCommand, SimulationInputSet};
+use bevy::prelude::*;
+use bevy_inspector_egui::bevy_egui::{EguiContexts, EguiPrimaryContextPass};
+use bevy_inspector_egui::egui;
+
+/// Main menu, level selection and the flight loop of the simulator app.
+///
+/// The app starts in [`AppState::MainMenu`]. Level select picks a level, an airframe from
+/// [`Airframes`] and an input profile from [`InputProfiles`], then the level loads and the
+/// flight starts. `Escape` pauses the flight, ending it shows the results.
+///
+/// Needs `LevelPlugin` and `SimulationPlugin`, which load the levels and pause the simulation
+/// while a menu is open. The simulation keys only work in flight, so `F7` can't resume the
+/// simulation behind a menu.
+///
+/// Only levels made for flying are offered. The physics engine demos stay separate binaries,
+/// Avian or Rapier is picked when their app is built and can't be swapped from a menu.
+pub struct MainMenuPlugin;
+
+impl Plugin for MainMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_state::<AppState>()
+            .init_resource::<Airframes>()
+            .init_resource::<InputProfiles>()
+            .init_resource::<FlightSelection>()
+            .init_resource::<FlightStats>()
+            .init_resource::<LevelCatalog>()
+            .add_systems(
+                OnEnter(AppState::MainMenu),
+                (unload_level, pause_simulation),
+            )
+            .configure_sets(
+                Update,
+                SimulationInputSet.run_if(in_state(AppState::InFlight)),
+            )
+            .add_systems(OnEnter(AppState::LevelSelect), refresh_level_catalog)
+            .add_systems(
+                OnEnter(AppState::Loading),
+                (load_selected_level, pause_simulation),
+            )
+            .add_systems(
+                OnTransition {
+                    exited: AppState::Loading,
+                    entered: AppState::InFlight,
+                },
+                start_flight,
+            )
+            .add_systems(OnEnter(AppState::InFlight), resume_simulation)
+            .add_systems(OnEnter(AppState::Paused), pause_simulation)
+            .add_systems(OnEnter(AppState::Results), pause_simulation)
+            .add_systems(
+                Update,
+                (
+                    finish_loading
+                        .after(LevelLoadingSet)
+                        .run_if(in_state(AppState::Loading)),
+                    (track_flight_stats, pause_on_escape).run_if(in_state(AppState::InFlight)),
+                    resume_on_escape.run_if(in_state(AppState::Paused)),
+                ),
+            )
+            .add_systems(
+                EguiPrimaryContextPass,
+                (
+                    main_menu.run_if(in_state(AppState::MainMenu)),
+                    level_select_menu.run_if(in_state(AppState::LevelSelect)),
+                    loading_screen.run_if(in_state(AppState::Loading)),
+                    pause_menu.run_if(in_state(AppState::Paused)),
+                    results_screen.run_if(in_state(AppState::Results)),
+                ),
+            )
+            .register_type::<FlightStats>();
+    }
+}
+
+#[derive(States, Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AppState {
+    #[default]
+    MainMenu,
+    LevelSelect,
+    /// The selected level is loading, the simulation is paused.
+    Loading,
+    InFlight,
+    /// The pause menu is open, the simulation is paused.
+    Paused,
+    /// Statistics of the flight that just ended.
+    Results,
+}
+
+/// Airframe the player can pick before a flight.
+#[derive(Debug, Clone)]
+pub struct Airframe {
+    pub name: String,
+    pub frame: DroneFrame,
+    pub battery: DroneBattery,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct Airframes(pub Vec<Airframe>);
+
+impl Default for Airframes {
+    fn default() -> Self {
+        Self(vec![
+            Airframe {
+                name: "5\" freestyle".to_string(),
+                frame: DroneFrame::default(),
+                battery: DroneBattery::default(),
+            },
+            Airframe {
+                name: "3\" cinewhoop".to_string(),
+                frame: DroneFrame {
+                    mass_kg: 0.3,
+                    arm_length_m: 0.07,
+                    max_motor_thrust_n: 2.5,
+                    yaw_torque_coef: 0.012,
+                    inertia: Vec3::new(0.0008, 0.0014, 0.0008),
+                    linear_drag_coef: 0.15,
+                    angular_drag_coef: 0.001,
+                },
+                battery: DroneBattery {
+                    capacity_mah: 650.0,
+                    max_current_a: 50.0,
+                    internal_resistance: 0.04,
+                    ..default()
+                },
+            },
+            Airframe {
+                name: "7\" long range".to_string(),
+                frame: DroneFrame {
+                    mass_kg: 0.9,
+                    arm_length_m: 0.16,
+                    max_motor_thrust_n: 9.5,
+                    yaw_torque_coef: 0.02,
+                    inertia: Vec3::new(0.006, 0.011, 0.006),
+                    linear_drag_coef: 0.08,
+                    angular_drag_coef: 0.003,
+                },
+                battery: DroneBattery {
+                    cell_count: 6,
+                    capacity_mah: 1800.0,
+                    voltage: 4.2 * 6.0,
+                    max_current_a: 90.0,
+                    ..default()
+                },
+            },
+        ])
+    }
+}
+
+/// Where the sticks come from during a flight.
+#[derive(Debug, Clone)]
+pub struct InputProfile {
+    pub name: String,
+    pub source: DroneInputSource,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct InputProfiles(pub Vec<InputProfile>);
+
+impl Default for InputProfiles {
+    fn default() -> Self {
+        Self(vec![
+            InputProfile {
+                name: "Gamepad".to_string(),
+                source: DroneInputSource::Gamepad,
+            },
+            InputProfile {
+                name: "Radio (CRSF/SBUS)".to_string(),
+                source: DroneInputSource::RcReceiver,
+            },
+            InputProfile {
+                name: "Network".to_string(),
+                source: DroneInputSource::Network,
+            },
+        ])
+    }
+}
+
+/// What the next flight uses. Indices point into [`Airframes`] and [`InputProfiles`].
+#[derive(Resource, Debug, Default, Clone)]
+pub struct FlightSelection {
+    /// Asset path of the level.
+    pub level: Option<String>,
+    pub airframe: usize,
+    pub input_profile: usize,
+}
+
+/// Statistics of the current or last flight.
+#[derive(Resource, Reflect, Debug, Default, Clone)]
+#[reflect(Resource)]
+pub struct FlightStats {
+    pub flight_time_secs: f32,
+    pub distance_m: f32,
+    pub max_speed_mps: f32,
+}
+
+/// Levels found in the level directory, loaded to show their names and descriptions.
+#[derive(Resource, Default)]
+struct LevelCatalog {
+    levels: Vec<(String, Handle<Level>)>,
+    /// Why the last level failed to load.
+    error: Option<String>,
+}
+
+fn refresh_level_catalog(
+    asset_server: Res<AssetServer>,
+    settings: Res<LevelSettings>,
+    mut catalog: ResMut<LevelCatalog>,
+    mut selection: ResMut<FlightSelection>,
+) {
+    catalog.levels = available_levels(&settings)
+        .into_iter()
+        .map(|path| {
+            let handle = asset_server.load(path.clone());
+            (path, handle)
+        })
+        .collect();
+    let selected_exists = selection
+        .level
+        .as_ref()
+        .is_some_and(|level| catalog.levels.iter().any(|(path, _)| path == level));
+    if !selected_exists {
+        selection.level = catalog.levels.first().map(|(path, _)| path.clone());
+    }
+}
+
+fn unload_level(mut unload: EventWriter<UnloadLevel>) {
+    unload.write(UnloadLevel);
+}
+
+fn pause_simulation(mut commands: EventWriter<SimulationCommand>) {
+    commands.write(SimulationCommand::Pause);
+}
+
+fn resume_simulation(mut commands: EventWriter<SimulationCommand>) {
+    commands.write(SimulationCommand::Resume);
+}
+
+fn load_selected_level(
+    selection: Res<FlightSelection>,
+    mut load: EventWriter<LoadLevel>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let Some(level) = &selection.level else {
+        warn!("No level selected.");
+        next_state.set(AppState::LevelSelect);
+        return;
+    };
+    load.write(LoadLevel(level.clone()));
+}
+
+fn finish_loading(
+    mut loaded: EventReader<LevelLoaded>,
+    current: Res<CurrentLevel>,
+    mut catalog: ResMut<LevelCatalog>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if loaded.read().last().is_some() {
+        catalog.error = None;
+        next_state.set(AppState::InFlight);
+    } else if current.status == LevelStatus::Failed {
+        catalog.error = Some(format!("Failed to load {}, see the log.", current.path));
+        next_state.set(AppState::LevelSelect);
+    }
+}
+
+/// Applies the selected airframe and input profile once the level is in place, so they win
+/// over the level's own drone setup.
+fn start_flight(
+    mut commands: Commands,
+    selection: Res<FlightSelection>,
+    airframes: Res<Airframes>,
+    profiles: Res<InputProfiles>,
+    mut input_source: ResMut<DroneInputSource>,
+    mut stats: ResMut<FlightStats>,
+    drones: Query<Entity, With<PlayerDrone>>,
+    mut simulation: EventWriter<SimulationCommand>,
+) {
+    if let Some(airframe) = airframes.0.get(selection.airframe) {
+        for drone in drones.iter() {
+            commands.entity(drone).insert((
+                airframe.frame.clone(),
+                airframe.battery.clone(),
+                DroneKinematics::default(),
+            ));
+        }
+        info!("Flying the {}", airframe.name);
+    }
+    if let Some(profile) = profiles.0.get(selection.input_profile) {
+        *input_source = profile.source;
+    }
+    *stats = FlightStats::default();
+    // Restart goes back to the chosen airframe, not the level's defaults
+    simulation.write(SimulationCommand::CaptureSnapshot);
+}
+
+fn track_flight_stats(
+    time: Res<Time>,
+    drones: Query<&DroneKinematics, With<PlayerDrone>>,
+    mut stats: ResMut<FlightStats>,
+) {
+    let Ok(kinematics) = drones.single() else {
+        debug!("No player drone found for the flight stats.");
+        return;
+    };
+    let speed = kinematics.linear_velocity.length();
+    stats.flight_time_secs += time.delta_secs();
+    stats.distance_m += speed * time.delta_secs();
+    stats.max_speed_mps = stats.max_speed_mps.max(speed);
+}
+
+fn pause_on_escape(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if keyboard.just_pressed(KeyCode::Escape) {
+        next_state.set(AppState::Paused);
+    }
+}
+
+fn resume_on_escape(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if keyboard.just_pressed(KeyCode::Escape) {
+        next_state.set(AppState::InFlight);
+    }
+}
+
+/// Centered window without decorations for the menus.
+fn menu_window(title: &str) -> egui::Window<'static> {
+    egui::Window::new(title)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .collapsible(false)
+        .resizable(false)
+}
+
+fn main_menu(
+    mut contexts: EguiContexts,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut exit: EventWriter<AppExit>,
+) {
+    let Ok(ctx) = contexts.ctx_mut() else {
+        debug!("No Egui context found for the main menu.");
+        return;
+    };
+    menu_window("Rusty Rotor").show(ctx, |ui| {
+        ui.vertical_centered(|ui| {
+            if ui.button("Fly").clicked() {
+                next_state.set(AppState::LevelSelect);
+            }
+            if ui.button("Quit").clicked() {
+                exit.write(AppExit::Success);
+            }
+        });
+    });
+}
+
+fn level_select_menu(
+    mut contexts: EguiContexts,
+    catalog: Res<LevelCatalog>,
+    levels: Res<Assets<Level>>,
+    airframes: Res<Airframes>,
+    profiles: Res<InputProfiles>,
+    mut selection: ResMut<FlightSelection>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let Ok(ctx) = contexts.ctx_mut() else {
+        debug!("No Egui context found for the level select menu.");
+        return;
+    };
+    menu_window("Level Select").show(ctx, |ui| {
+        if let Some(error) = &catalog.error {
+            ui.colored_label(egui::Color32::RED, error);
+        }
+
+        ui.heading("Level");
+        if catalog.levels.is_empty() {
+            ui.label("No levels found.");
+        }
+        // Levels still loading are listed until they turn out not to be flight levels
+        let flight_levels: Vec<_> = catalog
+            .levels
+            .iter()
+            .filter(|(_, handle)| levels.get(handle).is_none_or(|level| level.flight))
+            .collect();
+        if !flight_levels
+            .iter()
+            .any(|(path, _)| selection.level.as_ref() == Some(path))
+        {
+            selection.level = flight_levels.first().map(|(path, _)| path.clone());
+        }
+        for (path, handle) in flight_levels {
+            let selected = selection.level.as_ref() == Some(path);
+            let level = levels.get(handle);
+            let name = level.map_or(path.as_str(), |level| level.name.as_str());
+            if ui.selectable_label(selected, name).clicked() {
+                selection.level = Some(path.clone());
+            }
+            if let Some(level) = level.filter(|level| !level.description.is_empty()) {
+                ui.small(&level.description);
+            }
+        }
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.label("Airframe");
+            let current = airframes
+                .0
+                .get(selection.airframe)
+                .map_or("-", |airframe| airframe.name.as_str());
+            egui::ComboBox::from_id_salt("airframe")
+                .selected_text(current)
+                .show_ui(ui, |ui| {
+                    for (index, airframe) in airframes.0.iter().enumerate() {
+                        ui.selectable_value(&mut selection.airframe, index, &airframe.name);
+                    }
+                });
+        });
+        ui.horizontal(|ui| {
+            ui.label("Input");
+            let current = profiles
+                .0
+                .get(selection.input_profile)
+                .map_or("-", |profile| profile.name.as_str());
+            egui::ComboBox::from_id_salt("input_profile")
+                .selected_text(current)
+                .show_ui(ui, |ui| {
+                    for (index, profile) in profiles.0.iter().enumerate() {
+                        ui.selectable_value(&mut selection.input_profile, index, &profile.name);
+                    }
+                });
+        });
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("Back").clicked() {
+                next_state.set(AppState::MainMenu);
+            }
+            if ui
+                .add_enabled(selection.level.is_some(), egui::Button::new("Fly"))
+                .clicked()
+            {
+                next_state.set(AppState::Loading);
+            }
+        });
+    });
+}
+
+fn loading_screen(mut contexts: EguiContexts, current: Res<CurrentLevel>) {
+    let Ok(ctx) = contexts.ctx_mut() else {
+        debug!("No Egui context found for the loading screen.");
+        return;
+    };
+    menu_window("Loading").show(ctx, |ui| {
+        ui.horizontal(|ui| {
+            ui.spinner();
+            ui.label(&current.path);
+        });
+    });
+}
+
+fn pause_menu(
+    mut contexts: EguiContexts,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut simulation: EventWriter<SimulationCommand>,
+) {
+    let Ok(ctx) = contexts.ctx_mut() else {
+        debug!("No Egui context found for the pause menu.");
+        return;
+    };
+    menu_window("Paused").show(ctx, |ui| {
+        ui.vertical_centered(|ui| {
+            if ui.button("Resume").clicked() {
+                next_state.set(AppState::InFlight);
+            }
+            if ui.button("Restart").clicked() {
+                simulation.write(SimulationCommand::Restart);
+                next_state.set(AppState::InFlight);
+            }
+            if ui.button("End flight").clicked() {
+                next_state.set(AppState::Results);
+            }
+            if ui.button("Main menu").clicked() {
+                next_state.set(AppState::MainMenu);
+            }
+        });
+    });
+}
+
+fn results_screen(
+    mut contexts: EguiContexts,
+    stats: Res<FlightStats>,
+    drones: Query<&DroneBattery, With<PlayerDrone>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let Ok(ctx) = contexts.ctx_mut() else {
+        debug!("No Egui context found for the results screen.");
+        return;
+    };
+    let consumed_mah = drones.single().map_or(0.0, |battery| battery.consumed_mah);
+    menu_window("Results").show(ctx, |ui| {
+        egui::Grid::new("flight_stats").show(ui, |ui| {
+            ui.label("Flight time");
+            let seconds = stats.flight_time_secs as u32;
+            ui.label(format!("{}:{:02}", seconds / 60, seconds % 60));
+            ui.end_row();
+            ui.label("Distance");
+            ui.label(format!("{:.0} m", stats.distance_m));
+            ui.end_row();
+            ui.label("Top speed");
+            ui.label(format!("{:.1} km/h", stats.max_speed_mps * 3.6));
+            ui.end_row();
+            ui.label("Consumed");
+            ui.label(format!("{consumed_mah:.0} mAh"));
+            ui.end_row();
+        });
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("Fly again").clicked() {
+                next_state.set(AppState::Loading);
+            }
+            if ui.button("Level select").clicked() {
+                next_state.set(AppState::LevelSelect);
+            }
+            if ui.button("Main menu").clicked() {
+                next_state.set(AppState::MainMenu);
+            }
+        });
+    });
+}
@@ -0,0 +1,79 @@
+use bevy::prelude::*;
+use crossbeam_channel::{Receiver, Sender};
+use mavlink::common::MavMessage;
+use mavlink::{MavConnection, MavHeader, MavlinkVersion};
+use std::sync::Arc;
+
+/// A MAVLink connection owned by background threads, exchanging messages through channels
+/// so that Bevy systems never block on the socket.
+pub struct MavlinkLink {
+    pub outgoing: Sender<MavMessage>,
+    pub incoming: Receiver<(MavHeader, MavMessage)>,
+}
+
+impl MavlinkLink {
+    /// Opens `address` (e.g. `tcpin:0.0.0.0:4560`, `udpout:127.0.0.1:14550`) on a new thread.
+    /// Outgoing messages are sent with the ids from `header` and an increasing sequence number.
+    pub fn spawn(name: &str, address: String, header: MavHeader) -> Self {
+        let (outgoing_tx, outgoing_rx) = crossbeam_channel::unbounded::<MavMessage>();
+        let (incoming_tx, incoming_rx) = crossbeam_channel::unbounded();
+        let name = name.to_string();
+
+        std::thread::Builder::new()
+            .name(name.clone())
+            .spawn(move || {
+                info!("{name}: opening MAVLink connection on {address}");
+                // `tcpin` blocks until the peer connects
+                let mut connection = match mavlink::connect::<MavMessage>(&address) {
+                    Ok(connection) => connection,
+                    Err(e) => {
+                        error!("{name}: failed to open MAVLink connection on {address}: {e}");
+                        return;
+                    }
+                };
+                connection.set_protocol_version(MavlinkVersion::V2);
+                let connection: Arc<dyn MavConnection<MavMessage> + Sync + Send> =
+                    Arc::from(connection);
+                info!("{name}: MAVLink connection ready on {address}");
+
+                let writer = connection.clone();
+                let writer_name = name.clone();
+                std::thread::spawn(move || {
+                    let mut header = header;
+                    for message in outgoing_rx {
+                        if let Err(e) = writer.send(&header, &message) {
+                            warn!("{writer_name}: failed to send MAVLink message: {e}");
+                        }
+                        header.sequence = header.sequence.wrapping_add(1);
+                    }
+                });
+
+                loop {
+                    match connection.recv() {
+                        Ok(received) => {
+                            if incoming_tx.send(received).is_err() {
+                                return;
+                            }
+                        }
+                        Err(e) => {
+                            error!("{name}: MAVLink connection closed: {e}");
+                            return;
+                        }
+                    }
+                }
+            })
+            .expect("Failed to spawn a MAVLink thread");
+
+        Self {
+            outgoing: outgoing_tx,
+            incoming: incoming_rx,
+        }
+    }
+
+    /// Queues messages for sending, returns `false` once the connection thread is gone.
+    pub fn send(&self, messages: impl IntoIterator<Item = MavMessage>) -> bool {
+        messages
+            .into_iter()
+            .all(|message| self.outgoing.send(message).is_ok())
+    }
+}
@@ -0,0 +1,393 @@
+use crate::drone_plugin::{
+    DroneBattery, DroneKinematics, DronePosition, DroneSensors, ExternallyPositioned, GeoOrigin,
+    MotorOutputs, PlayerDrone, attitude_euler, local_to_frd, world_to_ned,
+};
+use crate::mavlink_link::MavlinkLink;
+use bevy::prelude::*;
+use mavlink::MavHeader;
+use mavlink::common::{
+    ATTITUDE_DATA, COMMAND_ACK_DATA, COMMAND_LONG_DATA, GLOBAL_POSITION_INT_DATA, HEARTBEAT_DATA,
+    MavAutopilot, MavCmd, MavFrame, MavMessage, MavModeFlag, MavResult, MavState,
+    MavSysStatusSensor, MavType, PositionTargetTypemask, RC_CHANNELS_DATA,
+    SET_POSITION_TARGET_LOCAL_NED_DATA, SYS_STATUS_DATA,
+};
+
+/// Streams the player drone's telemetry over MAVLink so ground control stations like
+/// QGroundControl can display it, and accepts arm, mode change and position target commands.
+pub struct MavlinkTelemetryPlugin;
+
+impl Plugin for MavlinkTelemetryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MavlinkTelemetrySettings>()
+            .add_systems(Startup, start_telemetry_link)
+            .add_systems(
+                Update,
+                (
+                    attach_vehicle_state,
+                    handle_gcs_messages,
+                    stream_telemetry,
+                    sync_guided_control,
+                    fly_to_position_target,
+                )
+                    .chain(),
+            )
+            .register_type::<MavlinkTelemetrySettings>()
+            .register_type::<MavlinkVehicle>()
+            .register_type::<VehicleMode>();
+    }
+}
+
+#[derive(Resource, Reflect, Clone)]
+#[reflect(Resource)]
+pub struct MavlinkTelemetrySettings {
+    /// MAVLink connection string, QGroundControl listens on UDP 14550.
+    pub address: String,
+    pub system_id: u8,
+    pub component_id: u8,
+    /// Rate of everything except the heartbeat, which is always sent at 1 Hz. Clamped to at
+    /// least 0.1 Hz.
+    pub stream_rate_hz: f32,
+    /// Speed used to reach position targets in guided mode, m/s.
+    pub guided_speed_mps: f32,
+}
+
+impl Default for MavlinkTelemetrySettings {
+    fn default() -> Self {
+        Self {
+            address: "udpout:127.0.0.1:14550".to_string(),
+            system_id: 1,
+            component_id: 1,
+            stream_rate_hz: 10.0,
+            guided_speed_mps: 3.0,
+        }
+    }
+}
+
+/// Vehicle state controlled by the ground control station.
+#[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component)]
+pub struct MavlinkVehicle {
+    pub armed: bool,
+    pub mode: VehicleMode,
+    /// Position target in world coordinates, followed in [`VehicleMode::Guided`].
+    pub position_target: Option<Vec3>,
+}
+
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Reflect)]
+pub enum VehicleMode {
+    /// Sticks fly the drone.
+    #[default]
+    Manual,
+    /// The drone flies to the position target set by the ground control station.
+    Guided,
+}
+
+impl VehicleMode {
+    fn from_base_mode(base_mode: MavModeFlag) -> Self {
+        if base_mode.contains(MavModeFlag::MAV_MODE_FLAG_GUIDED_ENABLED) {
+            VehicleMode::Guided
+        } else {
+            VehicleMode::Manual
+        }
+    }
+
+    fn custom_mode(self) -> u32 {
+        match self {
+            VehicleMode::Manual => 0,
+            VehicleMode::Guided => 1,
+        }
+    }
+}
+
+impl MavlinkVehicle {
+    fn base_mode(&self) -> MavModeFlag {
+        let mut flags = MavModeFlag::MAV_MODE_FLAG_CUSTOM_MODE_ENABLED
+            | MavModeFlag::MAV_MODE_FLAG_MANUAL_INPUT_ENABLED;
+        if self.armed {
+            flags |= MavModeFlag::MAV_MODE_FLAG_SAFETY_ARMED;
+        }
+        if self.mode == VehicleMode::Guided {
+            flags |= MavModeFlag::MAV_MODE_FLAG_GUIDED_ENABLED;
+        }
+        flags
+    }
+}
+
+#[derive(Resource)]
+struct TelemetryLink {
+    link: MavlinkLink,
+    heartbeat_timer: Timer,
+    stream_timer: Timer,
+}
+
+fn start_telemetry_link(mut commands: Commands, settings: Res<MavlinkTelemetrySettings>) {
+    let header = MavHeader {
+        system_id: settings.system_id,
+        component_id: settings.component_id,
+        sequence: 0,
+    };
+    commands.insert_resource(TelemetryLink {
+        link: MavlinkLink::spawn("mavlink-telemetry", settings.address.clone(), header),
+        heartbeat_timer: Timer::from_seconds(1.0, TimerMode::Repeating),
+        stream_timer: Timer::from_seconds(
+            1.0 / settings.stream_rate_hz.max(0.1),
+            TimerMode::Repeating,
+        ),
+    });
+}
+
+fn attach_vehicle_state(
+    mut commands: Commands,
+    drones: Query<Entity, (With<PlayerDrone>, Without<MavlinkVehicle>)>,
+) {
+    for entity in drones.iter() {
+        commands.entity(entity).insert(MavlinkVehicle::default());
+    }
+}
+
+fn handle_gcs_messages(
+    link: Option<Res<TelemetryLink>>,
+    settings: Res<MavlinkTelemetrySettings>,
+    mut vehicle: Query<&mut MavlinkVehicle, With<PlayerDrone>>,
+) {
+    let Some(link) = link else {
+        return;
+    };
+    let Ok(mut vehicle) = vehicle.single_mut() else {
+        debug!("No drone found to apply MAVLink commands to.");
+        return;
+    };
+
+    while let Ok((header, message)) = link.link.incoming.try_recv() {
+        match message {
+            MavMessage::COMMAND_LONG(command) if command.target_system == settings.system_id => {
+                let result = apply_command(&command, &mut vehicle);
+                let ack = MavMessage::COMMAND_ACK(COMMAND_ACK_DATA {
+                    command: command.command,
+                    result,
+                    target_system: header.system_id,
+                    target_component: header.component_id,
+                    ..Default::default()
+                });
+                link.link.send([ack]);
+            }
+            MavMessage::SET_MODE(set_mode) if set_mode.target_system == settings.system_id => {
+                let base_mode = MavModeFlag::from_bits_truncate(set_mode.base_mode as u8);
+                vehicle.mode = VehicleMode::from_base_mode(base_mode);
+                info!("GCS set mode to {:?}", vehicle.mode);
+            }
+            MavMessage::SET_POSITION_TARGET_LOCAL_NED(target)
+                if target.target_system == settings.system_id =>
+            {
+                match position_target_to_world(&target) {
+                    Ok(position) => {
+                        vehicle.position_target = Some(position);
+                        info!("GCS set position target to {position:?}");
+                    }
+                    Err(reason) => warn!("Ignoring GCS position target: {reason}"),
+                }
+            }
+            MavMessage::HEARTBEAT(_) => {}
+            message => trace!("Ignoring GCS message: {message:?}"),
+        }
+    }
+}
+
+fn apply_command(command: &COMMAND_LONG_DATA, vehicle: &mut MavlinkVehicle) -> MavResult {
+    match command.command {
+        MavCmd::MAV_CMD_COMPONENT_ARM_DISARM => {
+            vehicle.armed = command.param1 > 0.5;
+            info!("GCS {}", if vehicle.armed { "armed" } else { "disarmed" });
+            MavResult::MAV_RESULT_ACCEPTED
+        }
+        MavCmd::MAV_CMD_DO_SET_MODE => {
+            let base_mode = MavModeFlag::from_bits_truncate(command.param1 as u8);
+            vehicle.mode = VehicleMode::from_base_mode(base_mode);
+            info!("GCS set mode to {:?}", vehicle.mode);
+            MavResult::MAV_RESULT_ACCEPTED
+        }
+        _ => MavResult::MAV_RESULT_UNSUPPORTED,
+    }
+}
+
+/// Only absolute positions in the local NED frame are supported, velocity, acceleration and yaw
+/// setpoints are not.
+fn position_target_to_world(target: &SET_POSITION_TARGET_LOCAL_NED_DATA) -> Result<Vec3, String> {
+    if target.coordinate_frame != MavFrame::MAV_FRAME_LOCAL_NED {
+        return Err(format!(
+            "unsupported frame {:?}, only MAV_FRAME_LOCAL_NED is",
+            target.coordinate_frame
+        ));
+    }
+    let position_ignored = PositionTargetTypemask::POSITION_TARGET_TYPEMASK_X_IGNORE
+        | PositionTargetTypemask::POSITION_TARGET_TYPEMASK_Y_IGNORE
+        | PositionTargetTypemask::POSITION_TARGET_TYPEMASK_Z_IGNORE;
+    if target.type_mask.intersects(position_ignored) {
+        return Err(format!(
+            "type mask {:?} doesn't set a full position, velocity and acceleration setpoints \
+             are unsupported",
+            target.type_mask
+        ));
+    }
+    let others_ignored = PositionTargetTypemask::POSITION_TARGET_TYPEMASK_VX_IGNORE
+        | PositionTargetTypemask::POSITION_TARGET_TYPEMASK_VY_IGNORE
+        | PositionTargetTypemask::POSITION_TARGET_TYPEMASK_VZ_IGNORE
+        | PositionTargetTypemask::POSITION_TARGET_TYPEMASK_AX_IGNORE
+        | PositionTargetTypemask::POSITION_TARGET_TYPEMASK_AY_IGNORE
+        | PositionTargetTypemask::POSITION_TARGET_TYPEMASK_AZ_IGNORE
+        | PositionTargetTypemask::POSITION_TARGET_TYPEMASK_YAW_IGNORE
+        | PositionTargetTypemask::POSITION_TARGET_TYPEMASK_YAW_RATE_IGNORE;
+    if !target.type_mask.contains(others_ignored) {
+        warn!(
+            "Position target type mask {:?} also sets velocity, acceleration or yaw, only the \
+             position is used",
+            target.type_mask
+        );
+    }
+    // NED -> world (x east, y up, z south)
+    Ok(Vec3::new(target.y, -target.z, -target.x))
+}
+
+fn stream_telemetry(
+    link: Option<ResMut<TelemetryLink>>,
+    origin: Res<GeoOrigin>,
+    drone: Query<
+        (
+            &Transform,
+            &DroneKinematics,
+            &DroneSensors,
+            &DroneBattery,
+            &DronePosition,
+            &MavlinkVehicle,
+        ),
+        With<PlayerDrone>,
+    >,
+    time: Res<Time>,
+) {
+    let Some(mut link) = link else {
+        return;
+    };
+    let Ok((transform, kinematics, sensors, battery, sticks, vehicle)) = drone.single() else {
+        debug!("No drone found to stream MAVLink telemetry for.");
+        return;
+    };
+
+    let mut messages = Vec::new();
+    if link.heartbeat_timer.tick(time.delta()).just_finished() {
+        messages.push(MavMessage::HEARTBEAT(HEARTBEAT_DATA {
+            custom_mode: vehicle.mode.custom_mode(),
+            mavtype: MavType::MAV_TYPE_QUADROTOR,
+            autopilot: MavAutopilot::MAV_AUTOPILOT_GENERIC,
+            base_mode: vehicle.base_mode(),
+            system_status: if vehicle.armed {
+                MavState::MAV_STATE_ACTIVE
+            } else {
+                MavState::MAV_STATE_STANDBY
+            },
+            mavlink_version: 3,
+        }));
+    }
+
+    if link.stream_timer.tick(time.delta()).just_finished() {
+        let time_boot_ms = time.elapsed().as_millis() as u32;
+        let attitude = attitude_euler(transform.rotation);
+        let rates = local_to_frd(sensors.gyro);
+        let velocity_ned = world_to_ned(kinematics.linear_velocity);
+        let (lat, lon, alt) = origin.to_geodetic(transform.translation);
+        let sensors_present = MavSysStatusSensor::MAV_SYS_STATUS_SENSOR_3D_GYRO
+            | MavSysStatusSensor::MAV_SYS_STATUS_SENSOR_3D_ACCEL
+            | MavSysStatusSensor::MAV_SYS_STATUS_SENSOR_3D_MAG
+            | MavSysStatusSensor::MAV_SYS_STATUS_SENSOR_ABSOLUTE_PRESSURE
+            | MavSysStatusSensor::MAV_SYS_STATUS_SENSOR_GPS
+            | MavSysStatusSensor::MAV_SYS_STATUS_SENSOR_RC_RECEIVER
+            | MavSysStatusSensor::MAV_SYS_STATUS_SENSOR_BATTERY;
+
+        messages.push(MavMessage::ATTITUDE(ATTITUDE_DATA {
+            time_boot_ms,
+            roll: attitude.x,
+            pitch: attitude.y,
+            yaw: attitude.z,
+            rollspeed: rates.x,
+            pitchspeed: rates.y,
+            yawspeed: rates.z,
+        }));
+        messages.push(MavMessage::GLOBAL_POSITION_INT(GLOBAL_POSITION_INT_DATA {
+            time_boot_ms,
+            lat: (lat * 1e7) as i32,
+            lon: (lon * 1e7) as i32,
+            alt: (alt * 1000.0) as i32,
+            relative_alt: (transform.translation.y * 1000.0) as i32,
+            vx: (velocity_ned.x * 100.0) as i16,
+            vy: (velocity_ned.y * 100.0) as i16,
+            vz: (velocity_ned.z * 100.0) as i16,
+            hdg: (attitude.z.to_degrees().rem_euclid(360.0) * 100.0) as u16,
+        }));
+        messages.push(MavMessage::SYS_STATUS(SYS_STATUS_DATA {
+            onboard_control_sensors_present: sensors_present,
+            onboard_control_sensors_enabled: sensors_present,
+            onboard_control_sensors_health: sensors_present,
+            voltage_battery: (battery.voltage * 1000.0) as u16,
+            current_battery: (battery.current_a * 100.0) as i16,
+            battery_remaining: (battery.remaining() * 100.0) as i8,
+            ..Default::default()
+        }));
+        messages.push(MavMessage::RC_CHANNELS(rc_channels(time_boot_ms, sticks)));
+    }
+
+    link.link.send(messages);
+}
+
+/// Sticks as 1000..2000 us PWM channels in AETR order.
+fn rc_channels(time_boot_ms: u32, sticks: &DronePosition) -> RC_CHANNELS_DATA {
+    let pwm = |value: f32| (1500.0 + value.clamp(-1.0, 1.0) * 500.0) as u16;
+    RC_CHANNELS_DATA {
+        time_boot_ms,
+        chan1_raw: pwm(sticks.roll),
+        chan2_raw: pwm(sticks.pitch),
+        chan3_raw: pwm(sticks.throttle * 2.0 - 1.0),
+        chan4_raw: pwm(sticks.yaw),
+        chancount: 4,
+        rssi: u8::MAX,
+        ..Default::default()
+    }
+}
+
+/// Takes the drone away from the sticks while it follows GCS position targets.
+fn sync_guided_control(
+    mut commands: Commands,
+    drones: Query<(Entity, &MavlinkVehicle, Has<ExternallyPositioned>), Changed<MavlinkVehicle>>,
+) {
+    for (entity, vehicle, externally_positioned) in drones.iter() {
+        let guided = vehicle.armed && vehicle.mode == VehicleMode::Guided;
+        if guided && !externally_positioned {
+            commands.entity(entity).insert(ExternallyPositioned);
+        } else if !guided && externally_positioned {
+            commands.entity(entity).remove::<ExternallyPositioned>();
+        }
+    }
+}
+
+/// Moves stick-driven drones towards the GCS position target while armed in guided mode.
+fn fly_to_position_target(
+    settings: Res<MavlinkTelemetrySettings>,
+    mut drone: Query<
+        (&mut Transform, &MavlinkVehicle),
+        (
+            With<PlayerDrone>,
+            With<ExternallyPositioned>,
+            Without<MotorOutputs>,
+        ),
+    >,
+    time: Res<Time>,
+) {
+    let Ok((mut transform, vehicle)) = drone.single_mut() else {
+        return;
+    };
+    let Some(target) = vehicle.position_target else {
+        return;
+    };
+
+    let to_target = target - transform.translation;
+    let step = settings.guided_speed_mps * time.delta_secs();
+    transform.translation += to_target.clamp_length_max(step);
+}
@@ -0,0 +1,388 @@
+use crate::drone_plugin::{
+    DroneBattery, DronePosition, DroneSensors, GRAVITY_MPS2, MotorOutputs, PlayerDrone,
+    attitude_euler,
+};
+use crate::flight_controller_plugin::{AxisRates, FlightController, PidGains};
+use bevy::prelude::*;
+use crossbeam_channel::{Receiver, Sender};
+use std::io::{BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+
+/// MultiWii Serial Protocol (v1) server over TCP, backed by the in-sim [`FlightController`],
+/// so Betaflight Configurator-like tooling can inspect and tune the simulated quad.
+pub struct MspServerPlugin;
+
+impl Plugin for MspServerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MspServerSettings>()
+            .add_systems(Startup, start_msp_server)
+            .add_systems(Update, answer_msp_requests)
+            .register_type::<MspServerSettings>();
+    }
+}
+
+#[derive(Resource, Reflect, Clone)]
+#[reflect(Resource)]
+pub struct MspServerSettings {
+    /// Betaflight SITL exposes MSP on UART1 at this port as well.
+    pub address: String,
+}
+
+impl Default for MspServerSettings {
+    fn default() -> Self {
+        Self {
+            address: "0.0.0.0:5761".to_string(),
+        }
+    }
+}
+
+mod command {
+    pub const API_VERSION: u8 = 1;
+    pub const FC_VARIANT: u8 = 2;
+    pub const FC_VERSION: u8 = 3;
+    pub const BOARD_INFO: u8 = 4;
+    pub const STATUS: u8 = 101;
+    pub const RAW_IMU: u8 = 102;
+    pub const MOTOR: u8 = 104;
+    pub const RC: u8 = 105;
+    pub const ATTITUDE: u8 = 108;
+    pub const ANALOG: u8 = 110;
+    pub const RC_TUNING: u8 = 111;
+    pub const PID: u8 = 112;
+    pub const SET_PID: u8 = 202;
+    pub const SET_RC_TUNING: u8 = 204;
+    pub const EEPROM_WRITE: u8 = 250;
+}
+
+/// Sensor bits of `MSP_STATUS`.
+mod sensor {
+    pub const ACC: u16 = 1 << 0;
+    pub const BARO: u16 = 1 << 1;
+    pub const MAG: u16 = 1 << 2;
+    // Bit 3 is GPS and bit 4 the rangefinder, the simulated drone has neither
+    pub const GYRO: u16 = 1 << 5;
+}
+
+/// A decoded request from a client, answered by the Bevy side through `reply`.
+struct MspRequest {
+    command: u8,
+    payload: Vec<u8>,
+    reply: Sender<MspResponse>,
+}
+
+struct MspResponse {
+    command: u8,
+    /// `None` answers with an MSP error frame.
+    payload: Option<Vec<u8>>,
+}
+
+#[derive(Resource)]
+struct MspServer {
+    requests: Receiver<MspRequest>,
+}
+
+fn start_msp_server(mut commands: Commands, settings: Res<MspServerSettings>) {
+    let listener = match TcpListener::bind(&settings.address) {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!(
+                "Failed to start the MSP server on {}: {e}",
+                settings.address
+            );
+            return;
+        }
+    };
+    info!("MSP server listening on {}", settings.address);
+
+    let (requests_tx, requests_rx) = crossbeam_channel::unbounded();
+    std::thread::Builder::new()
+        .name("msp-server".to_string())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let requests = requests_tx.clone();
+                        std::thread::spawn(move || serve_client(stream, requests));
+                    }
+                    Err(e) => warn!("Failed to accept an MSP client: {e}"),
+                }
+            }
+        })
+        .expect("Failed to spawn the MSP server thread");
+
+    commands.insert_resource(MspServer {
+        requests: requests_rx,
+    });
+}
+
+fn serve_client(stream: TcpStream, requests: Sender<MspRequest>) {
+    let peer = stream.peer_addr().ok();
+    info!("MSP client connected: {peer:?}");
+    let mut writer = match stream.try_clone() {
+        Ok(writer) => writer,
+        Err(e) => {
+            warn!("Failed to set up the MSP client {peer:?}: {e}");
+            return;
+        }
+    };
+    let mut reader = BufReader::new(stream);
+    let (reply_tx, reply_rx) = crossbeam_channel::bounded(1);
+
+    loop {
+        let (command, payload) = match read_request(&mut reader) {
+            Ok(Some(request)) => request,
+            Ok(None) => continue,
+            Err(_) => break,
+        };
+        let request = MspRequest {
+            command,
+            payload,
+            reply: reply_tx.clone(),
+        };
+        if requests.send(request).is_err() {
+            break;
+        }
+        let Ok(response) = reply_rx.recv() else {
+            break;
+        };
+        if writer.write_all(&encode_response(&response)).is_err() {
+            break;
+        }
+    }
+    info!("MSP client disconnected: {peer:?}");
+}
+
+/// Reads one `$M<` frame. Returns `Ok(None)` for frames with a bad checksum.
+fn read_request(reader: &mut impl Read) -> std::io::Result<Option<(u8, Vec<u8>)>> {
+    let mut byte = [0u8; 1];
+    // Resynchronise on the preamble
+    let mut matched = 0;
+    while matched < 3 {
+        reader.read_exact(&mut byte)?;
+        matched = match (matched, byte[0]) {
+            (0, b'$') => 1,
+            (1, b'M') => 2,
+            (2, b'<') => 3,
+            (_, b'$') => 1,
+            _ => 0,
+        };
+    }
+
+    let mut header = [0u8; 2];
+    reader.read_exact(&mut header)?;
+    let [size, command] = header;
+    let mut payload = vec![0u8; size as usize];
+    reader.read_exact(&mut payload)?;
+    reader.read_exact(&mut byte)?;
+
+    if checksum(size, command, &payload) != byte[0] {
+        warn!("Dropping MSP request {command} with a bad checksum");
+        return Ok(None);
+    }
+    Ok(Some((command, payload)))
+}
+
+fn encode_response(response: &MspResponse) -> Vec<u8> {
+    let (direction, payload) = match &response.payload {
+        Some(payload) => (b'>', payload.as_slice()),
+        None => (b'!', [].as_slice()),
+    };
+    let size = payload.len() as u8;
+    let mut frame = vec![b'$', b'M', direction, size, response.command];
+    frame.extend_from_slice(payload);
+    frame.push(checksum(size, response.command, payload));
+    frame
+}
+
+fn checksum(size: u8, command: u8, payload: &[u8]) -> u8 {
+    payload.iter().fold(size ^ command, |acc, byte| acc ^ byte)
+}
+
+fn answer_msp_requests(
+    server: Option<Res<MspServer>>,
+    mut drone: Query<
+        (
+            &mut FlightController,
+            &MotorOutputs,
+            &DronePosition,
+            &DroneSensors,
+            &DroneBattery,
+            &Transform,
+        ),
+        With<PlayerDrone>,
+    >,
+    fixed_time: Res<Time<Fixed>>,
+) {
+    let Some(server) = server else {
+        return;
+    };
+
+    while let Ok(request) = server.requests.try_recv() {
+        let payload = match drone.single_mut() {
+            Ok((mut fc, motors, sticks, sensors, battery, transform)) => {
+                let state = DroneState {
+                    motors,
+                    sticks,
+                    sensors,
+                    battery,
+                    transform,
+                    cycle_time_us: fixed_time.timestep().as_micros() as u16,
+                };
+                handle_request(request.command, &request.payload, &mut fc, &state)
+            }
+            Err(_) => {
+                debug!("No flight controller found to answer MSP requests.");
+                None
+            }
+        };
+        if payload.is_none() {
+            debug!("Unsupported MSP command {}", request.command);
+        }
+        let _ = request.reply.send(MspResponse {
+            command: request.command,
+            payload,
+        });
+    }
+}
+
+/// Read-only drone state reported over MSP.
+struct DroneState<'a> {
+    motors: &'a MotorOutputs,
+    sticks: &'a DronePosition,
+    sensors: &'a DroneSensors,
+    battery: &'a DroneBattery,
+    transform: &'a Transform,
+    cycle_time_us: u16,
+}
+
+fn handle_request(
+    command: u8,
+    payload: &[u8],
+    fc: &mut FlightController,
+    state: &DroneState,
+) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    match command {
+        command::API_VERSION => out.extend([0, 1, 46]),
+        command::FC_VARIANT => out.extend(b"BTFL"),
+        command::FC_VERSION => out.extend([4, 5, 0]),
+        command::BOARD_INFO => {
+            out.extend(b"SIML");
+            out.extend(0u16.to_le_bytes());
+        }
+        command::STATUS => {
+            let sensors = sensor::ACC | sensor::BARO | sensor::MAG | sensor::GYRO;
+            let mode_flags = u32::from(fc.armed);
+            out.extend(state.cycle_time_us.to_le_bytes());
+            out.extend(0u16.to_le_bytes()); // i2c errors
+            out.extend(sensors.to_le_bytes());
+            out.extend(mode_flags.to_le_bytes());
+            out.push(0); // current profile
+        }
+        command::RAW_IMU => {
+            // 512 units per g, like MultiWii
+            let accel = state.sensors.accelerometer / GRAVITY_MPS2 * 512.0;
+            let gyro = fc.gyro;
+            let mag = state.sensors.magnetometer * 1000.0;
+            for value in [accel, gyro, mag] {
+                for axis in value.to_array() {
+                    out.extend((axis as i16).to_le_bytes());
+                }
+            }
+        }
+        command::MOTOR => {
+            for index in 0..8 {
+                let command = state.motors.0.get(index).copied().unwrap_or(0.0);
+                out.extend(((1000.0 + command * 1000.0) as u16).to_le_bytes());
+            }
+        }
+        command::RC => {
+            let pwm = |value: f32| (1500.0 + value.clamp(-1.0, 1.0) * 500.0) as u16;
+            let sticks = state.sticks;
+            let arm_switch = if fc.armed { 2000 } else { 1000 };
+            for channel in [
+                pwm(sticks.roll),
+                pwm(sticks.pitch),
+                pwm(sticks.throttle * 2.0 - 1.0),
+                pwm(sticks.yaw),
+                arm_switch,
+            ] {
+                out.extend(channel.to_le_bytes());
+            }
+        }
+        command::ATTITUDE => {
+            let attitude = attitude_euler(state.transform.rotation);
+            out.extend(((attitude.x.to_degrees() * 10.0) as i16).to_le_bytes());
+            out.extend(((attitude.y.to_degrees() * 10.0) as i16).to_le_bytes());
+            out.extend(((attitude.z.to_degrees().rem_euclid(360.0)) as i16).to_le_bytes());
+        }
+        command::ANALOG => {
+            let battery = state.battery;
+            out.push((battery.voltage * 10.0) as u8);
+            out.extend((battery.consumed_mah as u16).to_le_bytes());
+            out.extend(1023u16.to_le_bytes()); // rssi
+            out.extend(((battery.current_a * 100.0) as i16).to_le_bytes());
+            out.extend(((battery.voltage * 100.0) as u16).to_le_bytes());
+        }
+        command::RC_TUNING => {
+            let [roll, pitch, yaw] = fc.rates;
+            let percent = |value: f32| (value * 100.0).round() as u8;
+            out.extend([
+                percent(roll.rc_rate),
+                percent(roll.expo),
+                percent(roll.super_rate),
+                percent(pitch.super_rate),
+                percent(yaw.super_rate),
+                0,  // tpa rate
+                50, // throttle mid
+                0,  // throttle expo
+            ]);
+            out.extend(1650u16.to_le_bytes()); // tpa breakpoint
+            out.extend([
+                percent(yaw.expo),
+                percent(yaw.rc_rate),
+                percent(pitch.rc_rate),
+                percent(pitch.expo),
+            ]);
+        }
+        command::PID => {
+            for gains in fc.pids {
+                out.extend([gains.p, gains.i, gains.d]);
+            }
+            // Level and mag PIDs are not simulated
+            out.extend([0; 6]);
+        }
+        command::SET_PID => {
+            for (gains, values) in fc.pids.iter_mut().zip(payload.chunks_exact(3)) {
+                *gains = PidGains {
+                    p: values[0],
+                    i: values[1],
+                    d: values[2],
+                    ..*gains
+                };
+            }
+            info!("MSP updated PIDs: {:?}", fc.pids);
+        }
+        command::SET_RC_TUNING => {
+            if payload.len() < 14 {
+                return None;
+            }
+            let ratio = |value: u8| value as f32 / 100.0;
+            let rates = |rc_rate: u8, expo: u8, super_rate: u8| AxisRates {
+                rc_rate: ratio(rc_rate),
+                super_rate: ratio(super_rate),
+                expo: ratio(expo),
+            };
+            fc.rates = [
+                rates(payload[0], payload[1], payload[2]),
+                rates(payload[12], payload[13], payload[3]),
+                rates(payload[11], payload[10], payload[4]),
+            ];
+            info!("MSP updated rates: {:?}", fc.rates);
+        }
+        // Settings only live in memory
+        command::EEPROM_WRITE => {}
+        _ => return None,
+    }
+    Some(out)
+}
@@ -0,0 +1,396 @@
+use crate::drone_plugin::{DroneInputSet, DroneInputSource, DronePosition, PlayerDrone};
+use bevy::prelude::*;
+use crossbeam_channel::{Receiver, Sender};
+use serde::Deserialize;
+use std::net::{TcpListener, UdpSocket};
+use tungstenite::Message;
+
+/// Drives drones from control packets sent over UDP or WebSocket, so scripts, autonomy
+/// stacks or another machine can fly the simulator.
+///
+/// Packets are either JSON, e.g. `{"drone": "Drone", "throttle": 0.5, "yaw": 0.0,
+/// "pitch": 0.0, "roll": 0.0, "aux": [1.0]}`, or the compact binary layout described in
+/// [`ControlPacket::from_binary`]. Without a `drone` name the packet goes to
+/// [`NetworkInputSettings::target_drone`], or to the player drone if that is unset.
+///
+/// Packets are only applied while [`DroneInputSource`] is `Network`, which the app or the main
+/// menu selects.
+pub struct NetworkInputPlugin;
+
+impl Plugin for NetworkInputPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NetworkInputSettings>()
+            .init_resource::<DroneInputSource>()
+            .add_systems(Startup, start_network_input)
+            .add_systems(
+                Update,
+                (apply_control_packets, network_failsafe)
+                    .chain()
+                    .in_set(DroneInputSet),
+            )
+            .register_type::<NetworkInputSettings>()
+            .register_type::<RemoteControl>();
+    }
+}
+
+#[derive(Resource, Reflect, Clone)]
+#[reflect(Resource)]
+pub struct NetworkInputSettings {
+    /// UDP address to receive packets on, `None` disables UDP. Local only by default, use
+    /// `0.0.0.0` to accept packets from other machines.
+    pub udp_address: Option<String>,
+    /// TCP address to accept WebSocket clients on, `None` disables WebSocket.
+    pub websocket_address: Option<String>,
+    /// `Name` of the drone that receives packets without a `drone` field.
+    pub target_drone: Option<String>,
+    /// Sticks are centered and the throttle cut when no packet arrives for this long.
+    pub failsafe_timeout_secs: f32,
+}
+
+impl Default for NetworkInputSettings {
+    fn default() -> Self {
+        Self {
+            udp_address: Some("127.0.0.1:9760".to_string()),
+            websocket_address: Some("127.0.0.1:9761".to_string()),
+            target_drone: None,
+            failsafe_timeout_secs: 0.5,
+        }
+    }
+}
+
+/// Network control state of a drone.
+#[derive(Component, Reflect, Debug, Default, Clone)]
+#[reflect(Component)]
+pub struct RemoteControl {
+    /// Auxiliary channels from the last packet, `-1..=1`.
+    pub aux: Vec<f32>,
+    /// Seconds since the last packet.
+    pub packet_age_secs: f32,
+    pub failsafe: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct ControlPacket {
+    #[serde(default)]
+    drone: Option<String>,
+    throttle: f32,
+    yaw: f32,
+    pitch: f32,
+    roll: f32,
+    #[serde(default)]
+    aux: Vec<f32>,
+}
+
+impl ControlPacket {
+    const MAGIC: [u8; 2] = *b"RC";
+    const VERSION: u8 = 1;
+
+    /// Binary layout, little endian: `"RC"`, version `1`, throttle, yaw, pitch, roll as `f32`,
+    /// aux count as `u8` followed by that many `f32`s.
+    fn from_binary(bytes: &[u8]) -> Option<Self> {
+        let (header, body) = bytes.split_at_checked(3)?;
+        if header[..2] != Self::MAGIC || header[2] != Self::VERSION {
+            return None;
+        }
+        let mut values = body.get(..16)?.chunks_exact(4).map(read_f32);
+        let (throttle, yaw, pitch, roll) = (
+            values.next()?,
+            values.next()?,
+            values.next()?,
+            values.next()?,
+        );
+        let aux_count = *body.get(16)? as usize;
+        let aux = body.get(17..17 + aux_count * 4)?;
+        Some(Self {
+            drone: None,
+            throttle,
+            yaw,
+            pitch,
+            roll,
+            aux: aux.chunks_exact(4).map(read_f32).collect(),
+        })
+    }
+
+    fn from_json(text: &str) -> Option<Self> {
+        serde_json::from_str(text)
+            .inspect_err(|e| debug!("Invalid JSON control packet: {e}"))
+            .ok()
+    }
+
+    /// JSON packets start with `{`, which never starts a binary packet.
+    fn parse(bytes: &[u8]) -> Option<Self> {
+        if bytes.first() == Some(&b'{') {
+            Self::from_json(std::str::from_utf8(bytes).ok()?)
+        } else {
+            Self::from_binary(bytes)
+        }
+        .and_then(Self::validate)
+    }
+
+    /// Drops packets with NaN or infinite values, clamping would pass NaN on to the sticks.
+    /// Every transport runs its packets through this.
+    fn validate(self) -> Option<Self> {
+        let values = [self.throttle, self.yaw, self.pitch, self.roll];
+        if !values
+            .iter()
+            .chain(&self.aux)
+            .all(|value| value.is_finite())
+        {
+            debug!("Dropping a control packet with non-finite values.");
+            return None;
+        }
+        Some(self)
+    }
+}
+
+fn read_f32(bytes: &[u8]) -> f32 {
+    f32::from_le_bytes(bytes.try_into().unwrap_or_default())
+}
+
+#[derive(Resource)]
+struct NetworkInput {
+    packets: Receiver<ControlPacket>,
+}
+
+fn start_network_input(mut commands: Commands, settings: Res<NetworkInputSettings>) {
+    let (packets_tx, packets_rx) = crossbeam_channel::unbounded();
+
+    if let Some(address) = settings.udp_address.clone() {
+        let packets = packets_tx.clone();
+        std::thread::Builder::new()
+            .name("network-input-udp".to_string())
+            .spawn(move || receive_udp(&address, packets))
+            .expect("Failed to spawn the UDP input thread");
+    }
+    if let Some(address) = settings.websocket_address.clone() {
+        let packets = packets_tx.clone();
+        std::thread::Builder::new()
+            .name("network-input-websocket".to_string())
+            .spawn(move || accept_websockets(&address, packets))
+            .expect("Failed to spawn the WebSocket input thread");
+    }
+
+    commands.insert_resource(NetworkInput {
+        packets: packets_rx,
+    });
+}
+
+fn receive_udp(address: &str, packets: Sender<ControlPacket>) {
+    let socket = match UdpSocket::bind(address) {
+        Ok(socket) => socket,
+        Err(e) => {
+            error!("Failed to bind UDP control input on {address}: {e}");
+            return;
+        }
+    };
+    info!("Listening for UDP control packets on {address}");
+
+    let mut buffer = [0u8; 1500];
+    loop {
+        let size = match socket.recv(&mut buffer) {
+            Ok(size) => size,
+            Err(e) => {
+                warn!("UDP control input failed: {e}");
+                continue;
+            }
+        };
+        let Some(packet) = ControlPacket::parse(&buffer[..size]) else {
+            debug!("Dropping malformed UDP control packet");
+            continue;
+        };
+        if packets.send(packet).is_err() {
+            return;
+        }
+    }
+}
+
+fn accept_websockets(address: &str, packets: Sender<ControlPacket>) {
+    let listener = match TcpListener::bind(address) {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("Failed to bind WebSocket control input on {address}: {e}");
+            return;
+        }
+    };
+    info!("Listening for WebSocket control clients on {address}");
+
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else {
+            continue;
+        };
+        let packets = packets.clone();
+        std::thread::spawn(move || {
+            let mut socket = match tungstenite::accept(stream) {
+                Ok(socket) => socket,
+                Err(e) => {
+                    warn!("WebSocket handshake failed: {e}");
+                    return;
+                }
+            };
+            info!("WebSocket control client connected");
+            loop {
+                let packet = match socket.read() {
+                    Ok(Message::Text(text)) => ControlPacket::from_json(text.as_str()),
+                    Ok(Message::Binary(bytes)) => ControlPacket::from_binary(&bytes),
+                    Ok(Message::Close(_)) | Err(_) => break,
+                    Ok(_) => continue,
+                }
+                .and_then(ControlPacket::validate);
+                let Some(packet) = packet else {
+                    debug!("Dropping malformed WebSocket control packet");
+                    continue;
+                };
+                if packets.send(packet).is_err() {
+                    return;
+                }
+            }
+            info!("WebSocket control client disconnected");
+        });
+    }
+}
+
+fn apply_control_packets(
+    mut commands: Commands,
+    input: Option<Res<NetworkInput>>,
+    source: Res<DroneInputSource>,
+    settings: Res<NetworkInputSettings>,
+    mut drones: Query<(
+        Entity,
+        &Name,
+        &mut DronePosition,
+        Option<&mut RemoteControl>,
+        Has<PlayerDrone>,
+    )>,
+) {
+    let Some(input) = input else {
+        return;
+    };
+    if *source != DroneInputSource::Network {
+        // Drop what arrived meanwhile, so switching to network input doesn't replay it
+        input.packets.try_iter().for_each(drop);
+        return;
+    }
+
+    while let Ok(packet) = input.packets.try_recv() {
+        let target = packet.drone.as_ref().or(settings.target_drone.as_ref());
+        let drone = drones
+            .iter_mut()
+            .find(|(_, name, _, _, is_player)| match target {
+                Some(target) => name.as_str() == target,
+                None => *is_player,
+            });
+        let Some((entity, _, mut sticks, remote, _)) = drone else {
+            debug!("No drone {target:?} found for a network control packet.");
+            continue;
+        };
+
+        sticks.throttle = packet.throttle.clamp(0.0, 1.0);
+        sticks.yaw = packet.yaw.clamp(-1.0, 1.0);
+        sticks.pitch = packet.pitch.clamp(-1.0, 1.0);
+        sticks.roll = packet.roll.clamp(-1.0, 1.0);
+
+        let state = RemoteControl {
+            aux: packet.aux,
+            packet_age_secs: 0.0,
+            failsafe: false,
+        };
+        match remote {
+            Some(mut remote) => *remote = state,
+            None => {
+                commands.entity(entity).insert(state);
+            }
+        }
+    }
+}
+
+fn network_failsafe(
+    source: Res<DroneInputSource>,
+    settings: Res<NetworkInputSettings>,
+    mut drones: Query<(&Name, &mut RemoteControl, &mut DronePosition)>,
+    time: Res<Time>,
+) {
+    if *source != DroneInputSource::Network {
+        return;
+    }
+
+    for (name, mut remote, mut sticks) in drones.iter_mut() {
+        remote.packet_age_secs += time.delta_secs();
+        if remote.packet_age_secs > settings.failsafe_timeout_secs && !remote.failsafe {
+            warn!(
+                "No control packets for {name} in {:.2}s, entering failsafe",
+                remote.packet_age_secs
+            );
+            remote.failsafe = true;
+            *sticks = DronePosition::default();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn binary(values: [f32; 4], aux: &[f32]) -> Vec<u8> {
+        let mut bytes = ControlPacket::MAGIC.to_vec();
+        bytes.push(ControlPacket::VERSION);
+        for value in values {
+            bytes.extend(value.to_le_bytes());
+        }
+        bytes.push(aux.len() as u8);
+        for value in aux {
+            bytes.extend(value.to_le_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn parses_binary_packets() {
+        let packet = ControlPacket::parse(&binary([0.5, 0.1, -0.2, 0.3], &[1.0])).unwrap();
+        assert_eq!(
+            [packet.throttle, packet.yaw, packet.pitch, packet.roll],
+            [0.5, 0.1, -0.2, 0.3]
+        );
+        assert_eq!(packet.aux, [1.0]);
+    }
+
+    #[test]
+    fn parses_json_packets() {
+        let packet = ControlPacket::parse(
+            br#"{"drone": "Drone", "throttle": 0.5, "yaw": 0.0, "pitch": 0.0, "roll": 0.0}"#,
+        )
+        .unwrap();
+        assert_eq!(packet.drone.as_deref(), Some("Drone"));
+        assert_eq!(packet.throttle, 0.5);
+        assert!(packet.aux.is_empty());
+    }
+
+    #[test]
+    fn drops_non_finite_values() {
+        assert!(ControlPacket::parse(&binary([f32::NAN, 0.0, 0.0, 0.0], &[])).is_none());
+        assert!(ControlPacket::parse(&binary([0.5, 0.0, f32::INFINITY, 0.0], &[])).is_none());
+        assert!(ControlPacket::parse(&binary([0.5, 0.0, 0.0, 0.0], &[f32::NAN])).is_none());
+        // The WebSocket path validates what `from_binary` decoded
+        let packet = ControlPacket::from_binary(&binary([f32::NAN, 0.0, 0.0, 0.0], &[]));
+        assert!(packet.unwrap().validate().is_none());
+    }
+
+    #[test]
+    fn drops_truncated_packets() {
+        let bytes = binary([0.5, 0.0, 0.0, 0.0], &[1.0, -1.0]);
+        for len in 0..bytes.len() {
+            assert!(
+                ControlPacket::from_binary(&bytes[..len]).is_none(),
+                "{len} bytes"
+            );
+            assert!(ControlPacket::parse(&bytes[..len]).is_none(), "{len} bytes");
+        }
+        assert!(ControlPacket::parse(br#"{"throttle": 0.5, "yaw": 0.0"#).is_none());
+    }
+
+    #[test]
+    fn drops_unknown_versions() {
+        let mut bytes = binary([0.5, 0.0, 0.0, 0.0], &[]);
+        bytes[2] = ControlPacket::VERSION + 1;
+        assert!(ControlPacket::parse(&bytes).is_none());
+    }
+}
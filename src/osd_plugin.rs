@@ -0,0 +1,638 @@
+use crate::drone_plugin::{
+    DroneBattery, DroneInputSource, DroneKinematics, DronePosition, PlayerDrone, attitude_euler,
+    world_to_ned,
+};
+use crate::flight_controller_plugin::FlightController;
+use crate::fpv_camera_plugin::FpvCameraSettings;
+use crate::rc_input_plugin::RcChannels;
+use bevy::prelude::*;
+use bevy_inspector_egui::bevy_egui::{EguiContexts, EguiPrimaryContextPass};
+use bevy_inspector_egui::egui;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Columns of the OSD character grid, as on a PAL analog OSD.
+pub const OSD_COLUMNS: u8 = 30;
+/// Rows of the OSD character grid, as on a PAL analog OSD.
+pub const OSD_ROWS: u8 = 16;
+
+/// Betaflight-style on-screen display over the FPV feed: battery, timer, link quality,
+/// altitude, speed, home arrow, artificial horizon, crosshair, flight mode and warnings, each
+/// placed on a character grid.
+///
+/// `O` opens the layout editor, where elements are moved on the grid and layouts are saved as
+/// presets to `osd/<name>.ron`. The preset named by [`OsdSettings::preset`] is loaded on startup.
+pub struct OsdPlugin;
+
+impl Plugin for OsdPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<OsdSettings>()
+            .init_resource::<OsdLayout>()
+            .init_resource::<OsdEditor>()
+            .add_systems(Startup, load_startup_preset)
+            .add_systems(Update, (build_osd, update_osd).chain())
+            .add_systems(EguiPrimaryContextPass, osd_editor_window)
+            .register_type::<OsdSettings>()
+            .register_type::<OsdLayout>()
+            .register_type::<OsdElementPosition>()
+            .register_type::<OsdElement>();
+    }
+}
+
+#[derive(Resource, Reflect, Clone)]
+#[reflect(Resource)]
+pub struct OsdSettings {
+    pub enabled: bool,
+    /// Directory the layout presets are saved to.
+    pub directory: String,
+    /// Name of the active preset, loaded on startup.
+    pub preset: String,
+    pub font_size: f32,
+    pub color: Color,
+    /// How far the artificial horizon moves per degree of pitch, in percent of the screen height.
+    pub horizon_scale: f32,
+    /// Warn about a low battery below this cell voltage.
+    pub low_cell_voltage: f32,
+}
+
+impl Default for OsdSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            directory: "osd".to_string(),
+            preset: "default".to_string(),
+            font_size: 22.0,
+            color: Color::WHITE,
+            horizon_scale: 1.0,
+            low_cell_voltage: 3.5,
+        }
+    }
+}
+
+/// Placement of the OSD elements on the character grid.
+#[derive(Resource, Reflect, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[reflect(Resource)]
+pub struct OsdLayout {
+    pub elements: Vec<OsdElementPosition>,
+}
+
+impl Default for OsdLayout {
+    fn default() -> Self {
+        let place = |element, column, row| OsdElementPosition {
+            element,
+            column,
+            row,
+            visible: true,
+        };
+        Self {
+            elements: vec![
+                place(OsdElement::LinkQuality, 1, 1),
+                place(OsdElement::HomeArrow, 13, 1),
+                place(OsdElement::Altitude, 23, 1),
+                place(OsdElement::Speed, 1, 7),
+                place(OsdElement::Crosshair, 15, 8),
+                place(OsdElement::ArtificialHorizon, 15, 8),
+                place(OsdElement::Warnings, 10, 11),
+                place(OsdElement::ConsumedMah, 1, 13),
+                place(OsdElement::BatteryVoltage, 1, 14),
+                place(OsdElement::FlightMode, 13, 14),
+                place(OsdElement::FlightTimer, 24, 14),
+            ],
+        }
+    }
+}
+
+#[derive(Reflect, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct OsdElementPosition {
+    pub element: OsdElement,
+    /// Column of the element's top left corner, or of its centre for centred elements.
+    pub column: u8,
+    pub row: u8,
+    pub visible: bool,
+}
+
+#[derive(Reflect, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OsdElement {
+    BatteryVoltage,
+    ConsumedMah,
+    FlightTimer,
+    /// RC link quality, or RSSI if the receiver doesn't report link quality.
+    LinkQuality,
+    /// Height above the home point.
+    Altitude,
+    Speed,
+    /// Direction and distance to the home point, set when arming.
+    HomeArrow,
+    ArtificialHorizon,
+    Crosshair,
+    FlightMode,
+    Warnings,
+}
+
+impl OsdElement {
+    fn label(self) -> &'static str {
+        match self {
+            OsdElement::BatteryVoltage => "Battery voltage",
+            OsdElement::ConsumedMah => "Consumed mAh",
+            OsdElement::FlightTimer => "Flight timer",
+            OsdElement::LinkQuality => "Link quality",
+            OsdElement::Altitude => "Altitude",
+            OsdElement::Speed => "Speed",
+            OsdElement::HomeArrow => "Home arrow",
+            OsdElement::ArtificialHorizon => "Artificial horizon",
+            OsdElement::Crosshair => "Crosshair",
+            OsdElement::FlightMode => "Flight mode",
+            OsdElement::Warnings => "Warnings",
+        }
+    }
+
+    /// What the element looks like in the editor preview.
+    fn sample(self) -> &'static str {
+        match self {
+            OsdElement::BatteryVoltage => "16.8V",
+            OsdElement::ConsumedMah => "450MAH",
+            OsdElement::FlightTimer => "02:15",
+            OsdElement::LinkQuality => "LQ 100",
+            OsdElement::Altitude => "12.3M",
+            OsdElement::Speed => "42KM/H",
+            OsdElement::HomeArrow => "^ 35M",
+            OsdElement::ArtificialHorizon => "-----------",
+            OsdElement::Crosshair => "-+-",
+            OsdElement::FlightMode => "ACRO",
+            OsdElement::Warnings => "LOW BATTERY",
+        }
+    }
+
+    /// Centred elements are placed by their centre rather than their top left corner.
+    fn is_centered(self) -> bool {
+        matches!(self, OsdElement::ArtificialHorizon | OsdElement::Crosshair)
+    }
+}
+
+/// State of the layout editor window.
+#[derive(Resource, Default)]
+struct OsdEditor {
+    open: bool,
+    selected: usize,
+    preset_name: String,
+    presets: Vec<String>,
+}
+
+/// Root node of the OSD, rebuilt when the layout changes.
+#[derive(Component)]
+struct OsdRoot;
+
+/// Text showing the value of an element.
+#[derive(Component)]
+struct OsdText(OsdElement);
+
+/// Arrow of [`OsdElement::HomeArrow`], rotated towards home.
+#[derive(Component)]
+struct OsdHomeArrow;
+
+/// Line of [`OsdElement::ArtificialHorizon`], rotated with roll and moved with pitch.
+#[derive(Component)]
+struct OsdHorizon;
+
+fn load_startup_preset(settings: Res<OsdSettings>, mut layout: ResMut<OsdLayout>) {
+    let path = preset_path(&settings.directory, &settings.preset);
+    if !path.exists() {
+        debug!(
+            "No OSD preset at {}, using the default layout.",
+            path.display()
+        );
+        return;
+    }
+    if let Some(preset) = load_preset(&path) {
+        *layout = preset;
+        info!("Loaded OSD preset '{}'", settings.preset);
+    }
+}
+
+fn preset_path(directory: &str, name: &str) -> PathBuf {
+    PathBuf::from(directory).join(format!("{name}.ron"))
+}
+
+fn load_preset(path: &Path) -> Option<OsdLayout> {
+    let text = std::fs::read_to_string(path)
+        .inspect_err(|e| error!("Failed to read the OSD preset {}: {e}", path.display()))
+        .ok()?;
+    ron::de::from_str(&text)
+        .inspect_err(|e| error!("Invalid OSD preset {}: {e}", path.display()))
+        .ok()
+}
+
+fn save_preset(layout: &OsdLayout, directory: &str, name: &str) -> bool {
+    if let Err(e) = std::fs::create_dir_all(directory) {
+        error!("Failed to create the OSD preset directory {directory}: {e}");
+        return false;
+    }
+    let path = preset_path(directory, name);
+    let text = match ron::ser::to_string_pretty(layout, ron::ser::PrettyConfig::default()) {
+        Ok(text) => text,
+        Err(e) => {
+            error!("Failed to serialize the OSD layout: {e}");
+            return false;
+        }
+    };
+    match std::fs::write(&path, text) {
+        Ok(()) => {
+            info!("Saved OSD preset to {}", path.display());
+            true
+        }
+        Err(e) => {
+            error!("Failed to save the OSD preset to {}: {e}", path.display());
+            false
+        }
+    }
+}
+
+/// Names of the presets saved in `directory`, sorted.
+fn list_presets(directory: &str) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(directory) else {
+        return Vec::new();
+    };
+    let mut presets: Vec<String> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "ron"))
+        .filter_map(|path| Some(path.file_stem()?.to_string_lossy().into_owned()))
+        .collect();
+    presets.sort();
+    presets
+}
+
+fn build_osd(
+    mut commands: Commands,
+    settings: Res<OsdSettings>,
+    layout: Res<OsdLayout>,
+    roots: Query<Entity, With<OsdRoot>>,
+) {
+    if !settings.is_changed() && !layout.is_changed() {
+        return;
+    }
+    for root in roots.iter() {
+        commands.entity(root).despawn();
+    }
+
+    let font = TextFont {
+        font_size: settings.font_size,
+        ..default()
+    };
+    let color = TextColor(settings.color);
+    commands
+        .spawn((
+            Name::new("OSD"),
+            OsdRoot,
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                ..default()
+            },
+            Visibility::Hidden,
+        ))
+        .with_children(|osd| {
+            for placement in layout.elements.iter().filter(|placement| placement.visible) {
+                let element = placement.element;
+                let mut node = cell_node(placement);
+                if element.is_centered() {
+                    // Overflowing children of an empty flex node are centred on its position
+                    node.width = Val::Px(0.);
+                    node.height = Val::Px(0.);
+                    node.justify_content = JustifyContent::Center;
+                    node.align_items = AlignItems::Center;
+                } else if element == OsdElement::HomeArrow {
+                    node.column_gap = Val::Px(settings.font_size / 3.);
+                }
+                let mut cell = osd.spawn((Name::new(format!("OSD {}", element.label())), node));
+
+                match element {
+                    OsdElement::ArtificialHorizon => {
+                        cell.with_child((
+                            OsdHorizon,
+                            Node {
+                                width: Val::Vw(30.),
+                                height: Val::Px(2.),
+                                flex_shrink: 0.,
+                                ..default()
+                            },
+                            BackgroundColor(settings.color),
+                        ));
+                    }
+                    OsdElement::Crosshair => {
+                        cell.with_child((
+                            Text::new(element.sample()),
+                            font.clone(),
+                            color,
+                            TextShadow::default(),
+                        ));
+                    }
+                    OsdElement::HomeArrow => {
+                        cell.with_children(|home| {
+                            home.spawn((
+                                OsdHomeArrow,
+                                Text::new("^"),
+                                font.clone(),
+                                color,
+                                TextShadow::default(),
+                            ));
+                            home.spawn((
+                                OsdText(element),
+                                Text::default(),
+                                font.clone(),
+                                color,
+                                TextShadow::default(),
+                            ));
+                        });
+                    }
+                    _ => {
+                        cell.insert((
+                            OsdText(element),
+                            Text::default(),
+                            font.clone(),
+                            color,
+                            TextShadow::default(),
+                        ));
+                    }
+                }
+            }
+        });
+}
+
+fn cell_node(placement: &OsdElementPosition) -> Node {
+    Node {
+        position_type: PositionType::Absolute,
+        left: Val::Percent(placement.column as f32 * 100. / OSD_COLUMNS as f32),
+        top: Val::Percent(placement.row as f32 * 100. / OSD_ROWS as f32),
+        ..default()
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_osd(
+    settings: Res<OsdSettings>,
+    fpv: Option<Res<FpvCameraSettings>>,
+    input_source: Option<Res<DroneInputSource>>,
+    drones: Query<
+        (
+            &Transform,
+            &DroneKinematics,
+            &DroneBattery,
+            &DronePosition,
+            Option<&RcChannels>,
+            Option<&FlightController>,
+        ),
+        With<PlayerDrone>,
+    >,
+    mut roots: Query<&mut Visibility, With<OsdRoot>>,
+    mut texts: Query<(&OsdText, &mut Text)>,
+    mut arrows: Query<&mut Transform, (With<OsdHomeArrow>, Without<PlayerDrone>)>,
+    mut horizons: Query<
+        (&mut Node, &mut Transform),
+        (
+            With<OsdHorizon>,
+            Without<PlayerDrone>,
+            Without<OsdHomeArrow>,
+        ),
+    >,
+    time: Res<Time>,
+    mut home: Local<Option<Vec3>>,
+    mut flight_secs: Local<f32>,
+    mut was_armed: Local<bool>,
+) {
+    // The OSD is drawn on the FPV feed, so it follows the FPV view when there is one
+    let shown = settings.enabled && fpv.is_none_or(|fpv| fpv.enabled);
+    for mut visibility in roots.iter_mut() {
+        *visibility = if shown {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+
+    let Ok((transform, kinematics, battery, sticks, rc, flight_controller)) = drones.single()
+    else {
+        debug!("No player drone found for the OSD.");
+        return;
+    };
+
+    // Without a flight controller the drone counts as armed while the throttle is up
+    let armed = flight_controller.map_or(sticks.throttle > 0.05, |fc| fc.armed);
+    if armed && !*was_armed {
+        *home = Some(transform.translation);
+        *flight_secs = 0.0;
+    }
+    *was_armed = armed;
+    if armed {
+        *flight_secs += time.delta_secs();
+    }
+    let home = *home.get_or_insert(transform.translation);
+
+    let attitude = attitude_euler(transform.rotation);
+    let to_home = world_to_ned(home - transform.translation);
+    let home_distance = to_home.truncate().length();
+    let home_bearing = to_home.y.atan2(to_home.x);
+
+    for (element, mut text) in texts.iter_mut() {
+        text.0 = match element.0 {
+            OsdElement::BatteryVoltage => format!("{:.1}V", battery.voltage),
+            OsdElement::ConsumedMah => format!("{:.0}MAH", battery.consumed_mah),
+            OsdElement::FlightTimer => {
+                let secs = *flight_secs as u32;
+                format!("{:02}:{:02}", secs / 60, secs % 60)
+            }
+            OsdElement::LinkQuality => match rc {
+                Some(RcChannels {
+                    link_quality: Some(lq),
+                    ..
+                }) => format!("LQ {lq:>3}"),
+                Some(RcChannels {
+                    rssi_dbm: Some(rssi),
+                    ..
+                }) => format!("RSSI {rssi}DBM"),
+                _ => "LQ ---".to_string(),
+            },
+            OsdElement::Altitude => format!("{:.1}M", transform.translation.y - home.y),
+            OsdElement::Speed => {
+                format!("{:.0}KM/H", kinematics.linear_velocity.length() * 3.6)
+            }
+            OsdElement::HomeArrow => format!("{home_distance:.0}M"),
+            OsdElement::FlightMode => match (flight_controller, input_source.as_deref()) {
+                (_, Some(DroneInputSource::Replay)) => "REPLAY".to_string(),
+                (Some(_), _) => "ACRO".to_string(),
+                (None, _) => "DIRECT".to_string(),
+            },
+            OsdElement::Warnings => {
+                let mut warnings = Vec::new();
+                if flight_controller.is_some_and(|fc| !fc.armed) {
+                    warnings.push("DISARMED");
+                }
+                if rc.is_some_and(|rc| rc.failsafe) {
+                    warnings.push("RXLOSS");
+                }
+                if battery.cell_voltage() < settings.low_cell_voltage {
+                    warnings.push("LOW BATTERY");
+                }
+                warnings.join(" ")
+            }
+            OsdElement::ArtificialHorizon | OsdElement::Crosshair => continue,
+        };
+    }
+
+    // Screen space rotations are clockwise, the y axis points down
+    for mut arrow in arrows.iter_mut() {
+        arrow.rotation = Quat::from_rotation_z(home_bearing - attitude.z);
+    }
+    for (mut node, mut horizon) in horizons.iter_mut() {
+        horizon.rotation = Quat::from_rotation_z(-attitude.x);
+        node.top = Val::Vh(attitude.y.to_degrees() * settings.horizon_scale);
+    }
+}
+
+fn osd_editor_window(
+    mut contexts: EguiContexts,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut editor: ResMut<OsdEditor>,
+    mut settings: ResMut<OsdSettings>,
+    mut layout: ResMut<OsdLayout>,
+) {
+    let Ok(ctx) = contexts.ctx_mut() else {
+        debug!("No Egui context found for the OSD editor.");
+        return;
+    };
+    if keyboard.just_pressed(KeyCode::KeyO) && !ctx.wants_keyboard_input() {
+        editor.open = !editor.open;
+        if editor.open {
+            editor.presets = list_presets(&settings.directory);
+            editor.preset_name = settings.preset.clone();
+        }
+    }
+    if !editor.open {
+        return;
+    }
+
+    // Edit copies so the OSD is only rebuilt when something actually changed
+    let mut edited = layout.clone();
+    let mut enabled = settings.enabled;
+    let mut load = None;
+    let mut save = false;
+    let mut open = true;
+    egui::Window::new("OSD Editor")
+        .open(&mut open)
+        .show(ctx, |ui| {
+            ui.checkbox(&mut enabled, "Show OSD");
+            ui.label("Click or drag on the grid to move the selected element.");
+            draw_layout_grid(ui, &mut edited, editor.selected);
+
+            ui.separator();
+            for (index, placement) in edited.elements.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut placement.visible, "");
+                    if ui
+                        .selectable_label(editor.selected == index, placement.element.label())
+                        .clicked()
+                    {
+                        editor.selected = index;
+                    }
+                    ui.add(egui::DragValue::new(&mut placement.column).range(0..=OSD_COLUMNS - 1));
+                    ui.add(egui::DragValue::new(&mut placement.row).range(0..=OSD_ROWS - 1));
+                });
+            }
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut editor.preset_name);
+                save = ui.button("Save").clicked();
+                if ui.button("Reset").clicked() {
+                    edited = OsdLayout::default();
+                }
+            });
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Presets:");
+                for preset in &editor.presets {
+                    if ui.button(preset).clicked() {
+                        load = Some(preset.clone());
+                    }
+                }
+            });
+        });
+    editor.open &= open;
+
+    if enabled != settings.enabled {
+        settings.enabled = enabled;
+    }
+    if edited != *layout {
+        *layout = edited;
+    }
+    if save && !editor.preset_name.trim().is_empty() {
+        let name = editor.preset_name.trim().to_string();
+        if save_preset(&layout, &settings.directory, &name) {
+            settings.preset = name;
+            editor.presets = list_presets(&settings.directory);
+        }
+    }
+    if let Some(name) = load {
+        if let Some(preset) = load_preset(&preset_path(&settings.directory, &name)) {
+            *layout = preset;
+            info!("Loaded OSD preset '{name}'");
+            editor.preset_name = name.clone();
+            settings.preset = name;
+        }
+    }
+}
+
+/// Preview of the layout on the character grid, clicking a cell moves the selected element.
+fn draw_layout_grid(ui: &mut egui::Ui, layout: &mut OsdLayout, selected: usize) {
+    let cell = egui::vec2(10.0, 14.0);
+    let size = egui::vec2(OSD_COLUMNS as f32 * cell.x, OSD_ROWS as f32 * cell.y);
+    let (response, painter) = ui.allocate_painter(size, egui::Sense::click_and_drag());
+    let rect = response.rect;
+
+    painter.rect_filled(rect, 0.0, egui::Color32::from_gray(30));
+    let grid = egui::Stroke::new(1.0, egui::Color32::from_gray(45));
+    for column in 1..OSD_COLUMNS {
+        let x = rect.left() + column as f32 * cell.x;
+        painter.vline(x, rect.y_range(), grid);
+    }
+    for row in 1..OSD_ROWS {
+        let y = rect.top() + row as f32 * cell.y;
+        painter.hline(rect.x_range(), y, grid);
+    }
+
+    for (index, placement) in layout.elements.iter().enumerate() {
+        if !placement.visible {
+            continue;
+        }
+        let position = rect.min
+            + egui::vec2(
+                placement.column as f32 * cell.x,
+                placement.row as f32 * cell.y,
+            );
+        let anchor = if placement.element.is_centered() {
+            egui::Align2::CENTER_CENTER
+        } else {
+            egui::Align2::LEFT_TOP
+        };
+        let color = if index == selected {
+            egui::Color32::YELLOW
+        } else {
+            egui::Color32::WHITE
+        };
+        painter.text(
+            position,
+            anchor,
+            placement.element.sample(),
+            egui::FontId::monospace(cell.y * 0.85),
+            color,
+        );
+    }
+
+    if let Some(pointer) = response.interact_pointer_pos() {
+        let offset = pointer - rect.min;
+        if let Some(placement) = layout.elements.get_mut(selected) {
+            placement.column = ((offset.x / cell.x) as u8).min(OSD_COLUMNS - 1);
+            placement.row = ((offset.y / cell.y) as u8).min(OSD_ROWS - 1);
+        }
+    }
+}
@@ -0,0 +1,111 @@
+//! Meshes and materials shared by every spawned copy of a prefab, so spawning or loading many
+//! objects reuses the same handles instead of adding new assets each time.
+
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+
+/// Shared assets by key. Keys are up to the caller, e.g. `"falling_cube"` or a colour for
+/// materials that differ per object. The library keeps the assets alive, so every key is
+/// created once for the lifetime of the app.
+///
+/// The assets are shared, so they must not be mutated: editing one, e.g. in the inspector,
+/// changes every object using it and every later object spawned with its key. Give an object
+/// its own copy before editing it, and use [`Self::material_matching`] for materials keyed by
+/// their contents.
+#[derive(Resource, Default)]
+pub struct PrefabAssetLibrary {
+    meshes: HashMap<String, Handle<Mesh>>,
+    materials: HashMap<String, Handle<StandardMaterial>>,
+}
+
+impl PrefabAssetLibrary {
+    /// The mesh for `key`, created with `create` the first time.
+    pub fn mesh(
+        &mut self,
+        key: &str,
+        meshes: &mut Assets<Mesh>,
+        create: impl FnOnce() -> Mesh,
+    ) -> Handle<Mesh> {
+        if let Some(handle) = self.meshes.get(key) {
+            return handle.clone();
+        }
+        let handle = meshes.add(create());
+        self.meshes.insert(key.to_string(), handle.clone());
+        handle
+    }
+
+    /// The material for `key`, created with `create` the first time.
+    pub fn material(
+        &mut self,
+        key: &str,
+        materials: &mut Assets<StandardMaterial>,
+        create: impl FnOnce() -> StandardMaterial,
+    ) -> Handle<StandardMaterial> {
+        if let Some(handle) = self.materials.get(key) {
+            return handle.clone();
+        }
+        let handle = materials.add(create());
+        self.materials.insert(key.to_string(), handle.clone());
+        handle
+    }
+
+    /// [`Self::material`] for materials keyed by their contents, recreated when the cached one
+    /// was removed or edited so it no longer passes `matches`.
+    pub fn material_matching(
+        &mut self,
+        key: &str,
+        materials: &mut Assets<StandardMaterial>,
+        matches: impl FnOnce(&StandardMaterial) -> bool,
+        create: impl FnOnce() -> StandardMaterial,
+    ) -> Handle<StandardMaterial> {
+        let cached = self.materials.get(key);
+        if let Some(handle) = cached.filter(|handle| materials.get(*handle).is_some_and(matches)) {
+            return handle.clone();
+        }
+        let handle = materials.add(create());
+        self.materials.insert(key.to_string(), handle.clone());
+        handle
+    }
+
+    /// [`Self::mesh`] with exclusive world access, for `Prefab::spawn`.
+    pub fn world_mesh(world: &mut World, key: &str, create: impl FnOnce() -> Mesh) -> Handle<Mesh> {
+        world.init_resource::<PrefabAssetLibrary>();
+        world.resource_scope(|world, mut library: Mut<PrefabAssetLibrary>| {
+            library.mesh(key, &mut world.resource_mut::<Assets<Mesh>>(), create)
+        })
+    }
+
+    /// [`Self::material`] with exclusive world access, for `Prefab::spawn`.
+    pub fn world_material(
+        world: &mut World,
+        key: &str,
+        create: impl FnOnce() -> StandardMaterial,
+    ) -> Handle<StandardMaterial> {
+        world.init_resource::<PrefabAssetLibrary>();
+        world.resource_scope(|world, mut library: Mut<PrefabAssetLibrary>| {
+            library.material(
+                key,
+                &mut world.resource_mut::<Assets<StandardMaterial>>(),
+                create,
+            )
+        })
+    }
+
+    /// [`Self::material_matching`] with exclusive world access, for `Prefab::spawn`.
+    pub fn world_material_matching(
+        world: &mut World,
+        key: &str,
+        matches: impl FnOnce(&StandardMaterial) -> bool,
+        create: impl FnOnce() -> StandardMaterial,
+    ) -> Handle<StandardMaterial> {
+        world.init_resource::<PrefabAssetLibrary>();
+        world.resource_scope(|world, mut library: Mut<PrefabAssetLibrary>| {
+            library.material_matching(
+                key,
+                &mut world.resource_mut::<Assets<StandardMaterial>>(),
+                matches,
+                create,
+            )
+        })
+    }
+}
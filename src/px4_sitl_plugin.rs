@@ -0,0 +1,251 @@
+use crate::drone_plugin::{
+    DroneKinematics, DroneSensors, DroneSet, GRAVITY_MPS2, GeoOrigin, MotorOutputs, PlayerDrone,
+    local_to_frd, rotation_to_ned, world_to_ned,
+};
+use crate::mavlink_link::MavlinkLink;
+use bevy::prelude::*;
+use crossbeam_channel::RecvTimeoutError;
+use mavlink::MavHeader;
+use mavlink::common::{
+    HIL_GPS_DATA, HIL_SENSOR_DATA, HIL_STATE_QUATERNION_DATA, HilSensorUpdatedFlags, MavMessage,
+    MavModeFlag,
+};
+use std::time::{Duration, Instant};
+
+/// Bridges the player drone to a PX4 SITL instance over MAVLink HIL messages.
+///
+/// Works like PX4's `none_iris` simulator interface: PX4 connects to the simulator port,
+/// receives `HIL_SENSOR`, `HIL_GPS` and `HIL_STATE_QUATERNION` and answers with
+/// `HIL_ACTUATOR_CONTROLS`, which drive the drone's [`MotorOutputs`].
+pub struct Px4SitlPlugin;
+
+impl Plugin for Px4SitlPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Px4SitlSettings>()
+            // PX4 expects IMU samples at 250 Hz
+            .insert_resource(Time::<Fixed>::from_hz(250.0))
+            .add_systems(Startup, start_sitl_link)
+            .add_systems(Update, attach_motor_outputs)
+            .add_systems(
+                FixedUpdate,
+                (
+                    receive_actuator_controls.before(DroneSet::Dynamics),
+                    send_hil_messages.after(DroneSet::Sensors),
+                ),
+            )
+            .register_type::<Px4SitlSettings>();
+    }
+}
+
+#[derive(Resource, Reflect, Clone)]
+#[reflect(Resource)]
+pub struct Px4SitlSettings {
+    /// MAVLink connection string, PX4 connects to `tcpin:0.0.0.0:4560` by default.
+    pub address: String,
+    /// Wait for PX4's actuator controls before every physics step, so both run deterministically.
+    pub lockstep: bool,
+    /// How long a lockstep step waits for PX4 before moving on without new controls.
+    pub lockstep_timeout_ms: u64,
+    pub gps_rate_hz: f32,
+}
+
+impl Default for Px4SitlSettings {
+    fn default() -> Self {
+        Self {
+            address: "tcpin:0.0.0.0:4560".to_string(),
+            lockstep: true,
+            lockstep_timeout_ms: 500,
+            gps_rate_hz: 10.0,
+        }
+    }
+}
+
+#[derive(Resource)]
+struct Px4SitlLink {
+    link: MavlinkLink,
+    /// PX4 answered at least once, so lockstep waits are meaningful.
+    connected: bool,
+    /// Sensors were sent since the last actuator controls were received.
+    awaiting_controls: bool,
+    last_gps_usec: Option<u64>,
+}
+
+fn start_sitl_link(mut commands: Commands, settings: Res<Px4SitlSettings>) {
+    let header = MavHeader {
+        system_id: 1,
+        component_id: 51,
+        sequence: 0,
+    };
+    commands.insert_resource(Px4SitlLink {
+        link: MavlinkLink::spawn("px4-sitl-link", settings.address.clone(), header),
+        connected: false,
+        awaiting_controls: false,
+        last_gps_usec: None,
+    });
+}
+
+/// PX4 drives the motors directly, so the player drone has to switch to motor dynamics.
+fn attach_motor_outputs(
+    mut commands: Commands,
+    drones: Query<Entity, (With<PlayerDrone>, Without<MotorOutputs>)>,
+) {
+    for entity in drones.iter() {
+        info!("Drone {entity} is now driven by PX4 actuator controls");
+        commands.entity(entity).insert(MotorOutputs::default());
+    }
+}
+
+fn receive_actuator_controls(
+    link: Option<ResMut<Px4SitlLink>>,
+    settings: Res<Px4SitlSettings>,
+    mut motors: Query<&mut MotorOutputs, With<PlayerDrone>>,
+) {
+    let Some(mut link) = link else {
+        return;
+    };
+
+    let mut latest = None;
+    if settings.lockstep && link.connected && link.awaiting_controls {
+        // Block the physics step until PX4 has processed the last sensor sample
+        let deadline = Instant::now() + Duration::from_millis(settings.lockstep_timeout_ms);
+        while latest.is_none() {
+            match link.link.incoming.recv_deadline(deadline) {
+                Ok((_, MavMessage::HIL_ACTUATOR_CONTROLS(controls))) => latest = Some(controls),
+                Ok(_) => {}
+                Err(RecvTimeoutError::Timeout) => {
+                    warn!("PX4 lockstep timed out, stepping anyway");
+                    break;
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    link.connected = false;
+                    break;
+                }
+            }
+        }
+    }
+    while let Ok((_, message)) = link.link.incoming.try_recv() {
+        if let MavMessage::HIL_ACTUATOR_CONTROLS(controls) = message {
+            latest = Some(controls);
+        }
+    }
+
+    let Some(controls) = latest else {
+        return;
+    };
+    link.connected = true;
+    link.awaiting_controls = false;
+
+    let Ok(mut motors) = motors.single_mut() else {
+        debug!("No motor driven drone found for PX4 actuator controls.");
+        return;
+    };
+    let armed = controls
+        .mode
+        .contains(MavModeFlag::MAV_MODE_FLAG_SAFETY_ARMED);
+    for (motor, control) in motors.0.iter_mut().zip(controls.controls) {
+        *motor = if armed { control.clamp(0.0, 1.0) } else { 0.0 };
+    }
+}
+
+fn send_hil_messages(
+    link: Option<ResMut<Px4SitlLink>>,
+    settings: Res<Px4SitlSettings>,
+    origin: Res<GeoOrigin>,
+    drone: Query<(&Transform, &DroneKinematics, &DroneSensors), With<PlayerDrone>>,
+    time: Res<Time>,
+) {
+    let Some(mut link) = link else {
+        return;
+    };
+    let Ok((transform, kinematics, sensors)) = drone.single() else {
+        debug!("No drone found to send HIL messages for.");
+        return;
+    };
+
+    let time_usec = time.elapsed().as_micros() as u64;
+    let accel = local_to_frd(sensors.accelerometer);
+    let gyro = local_to_frd(sensors.gyro);
+    let mag = local_to_frd(sensors.magnetometer);
+    let velocity_ned = world_to_ned(kinematics.linear_velocity);
+    let (lat, lon, alt) = origin.to_geodetic(transform.translation);
+    let lat_e7 = (lat * 1e7) as i32;
+    let lon_e7 = (lon * 1e7) as i32;
+    let alt_mm = (alt * 1000.0) as i32;
+
+    let mut messages = vec![MavMessage::HIL_SENSOR(HIL_SENSOR_DATA {
+        time_usec,
+        xacc: accel.x,
+        yacc: accel.y,
+        zacc: accel.z,
+        xgyro: gyro.x,
+        ygyro: gyro.y,
+        zgyro: gyro.z,
+        xmag: mag.x,
+        ymag: mag.y,
+        zmag: mag.z,
+        abs_pressure: sensors.pressure_hpa,
+        diff_pressure: 0.0,
+        pressure_alt: sensors.altitude_amsl_m,
+        temperature: sensors.temperature_c,
+        fields_updated: HilSensorUpdatedFlags::all(),
+        ..Default::default()
+    })];
+
+    let gps_period_usec = (1_000_000.0 / settings.gps_rate_hz) as u64;
+    if link
+        .last_gps_usec
+        .is_none_or(|last| time_usec >= last + gps_period_usec)
+    {
+        let ground_speed = velocity_ned.truncate().length();
+        let course = velocity_ned
+            .y
+            .atan2(velocity_ned.x)
+            .to_degrees()
+            .rem_euclid(360.0);
+        messages.push(MavMessage::HIL_GPS(HIL_GPS_DATA {
+            time_usec,
+            lat: lat_e7,
+            lon: lon_e7,
+            alt: alt_mm,
+            eph: 30,
+            epv: 40,
+            vel: (ground_speed * 100.0) as u16,
+            vn: (velocity_ned.x * 100.0) as i16,
+            ve: (velocity_ned.y * 100.0) as i16,
+            vd: (velocity_ned.z * 100.0) as i16,
+            cog: (course * 100.0) as u16,
+            fix_type: 3,
+            satellites_visible: 10,
+            ..Default::default()
+        }));
+        link.last_gps_usec = Some(time_usec);
+    }
+
+    // Ground truth, used by PX4 for logging and by `none_iris`-style setups for visualisation
+    let attitude = rotation_to_ned(transform.rotation);
+    let accel_mg = accel * 1000.0 / GRAVITY_MPS2;
+    messages.push(MavMessage::HIL_STATE_QUATERNION(
+        HIL_STATE_QUATERNION_DATA {
+            time_usec,
+            attitude_quaternion: [attitude.w, attitude.x, attitude.y, attitude.z],
+            rollspeed: gyro.x,
+            pitchspeed: gyro.y,
+            yawspeed: gyro.z,
+            lat: lat_e7,
+            lon: lon_e7,
+            alt: alt_mm,
+            vx: (velocity_ned.x * 100.0) as i16,
+            vy: (velocity_ned.y * 100.0) as i16,
+            vz: (velocity_ned.z * 100.0) as i16,
+            ind_airspeed: (kinematics.linear_velocity.length() * 100.0) as u16,
+            true_airspeed: (kinematics.linear_velocity.length() * 100.0) as u16,
+            xacc: accel_mg.x as i16,
+            yacc: accel_mg.y as i16,
+            zacc: accel_mg.z as i16,
+        },
+    ));
+
+    if link.link.send(messages) {
+        link.awaiting_controls = true;
+    }
+}
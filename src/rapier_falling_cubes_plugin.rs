@@ -1,16 +1,57 @@
+use crate::level_colliders_plugin::RapierLevelCollidersPlugin;
+use crate::prefab_asset_library::PrefabAssetLibrary;
+use crate::save_system_plugin::{ApplyFlow, CaptureFlow, SaveSystemPlugin};
 use bevy::prelude::*;
 use bevy_rapier3d::prelude::*;
+use bevy_save::prelude::*;
 
+const CUBE_SIZE: Vec3 = Vec3::ONE;
+
+/// Every cube shares one mesh and material from the [`PrefabAssetLibrary`].
+const CUBE_ASSET_KEY: &str = "rapier_falling_cube";
+
+/// Cubes falling onto the ground of a level, which needs `LevelPlugin` and
+/// [`RapierLevelCollidersPlugin`] to give the level geometry colliders. Without
+/// [`RapierLevelCollidersPlugin`] the cubes fall onto a plain ground plane instead.
 pub struct FallingCubesPlugin;
 
 impl Plugin for FallingCubesPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, (setup_scene, spawn_cubes, setup_gravity))
             // .add_systems(Update, log_collisions)
-            .add_systems(Update, (handle_gravity_type, apply_gravity));
+            .add_systems(Update, (handle_gravity_type, apply_gravity))
+            .init_resource::<PrefabAssetLibrary>()
+            .register_type::<WorldGravity>()
+            .register_type::<FallingCube>();
+    }
+
+    // Every plugin is added by now, whatever order the app added them in
+    fn finish(&self, app: &mut App) {
+        if !app.is_plugin_added::<RapierLevelCollidersPlugin>() {
+            app.add_systems(Startup, spawn_fallback_ground);
+        }
+    }
+}
+
+/// Saves the cubes mid-motion, with their velocities, sleeping state and external forces, and
+/// the gravity settings. Loading despawns the current cubes and respawns the saved ones.
+pub struct FallingCubesSavePlugin;
+
+impl Plugin for FallingCubesSavePlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<SaveSystemPlugin>() {
+            app.add_plugins(SaveSystemPlugin);
+        }
+        app.add_flows(CaptureFlow, capture_cubes)
+            .add_flows(ApplyFlow, apply_cubes)
+            .register_type::<FallingCubePrefab>();
     }
 }
 
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+pub struct FallingCube;
+
 #[derive(Resource, Reflect)]
 #[reflect(Resource)]
 struct WorldGravity {
@@ -55,7 +96,7 @@ impl GravityType {
     }
 }
 
-fn setup_scene(
+fn spawn_fallback_ground(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
@@ -63,7 +104,6 @@ fn setup_scene(
     let ground_size = Vec3::new(20.0, 0.2, 20.0);
     let ground_collider_size = ground_size * 0.5;
 
-    // Ground
     commands.spawn((
         Name::new("Ground"),
         RigidBody::Fixed,
@@ -82,7 +122,9 @@ fn setup_scene(
         // Needed for Rapier collision events:
         ActiveEvents::COLLISION_EVENTS,
     ));
+}
 
+fn setup_scene(mut commands: Commands) {
     // Add a light source so we can see clearly.
     commands.spawn((
         DirectionalLight {
@@ -104,40 +146,117 @@ fn setup_scene(
 
 fn spawn_cubes(
     mut commands: Commands,
+    mut library: ResMut<PrefabAssetLibrary>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
 ) {
-    let cube_size = Vec3::new(1.0, 1.0, 1.0);
-    let cube_collider_size = cube_size * 0.5;
-
     for y in 0..5 {
         for x in 0..5 {
             let pos = Vec3::new(x as f32 - 3.0, 3.0 + y as f32 * 1.2, 0.0);
             commands.spawn((
-                Name::new(format!("Cube_{x}_{y}")),
-                RigidBody::Dynamic,
-                Collider::cuboid(
-                    cube_collider_size.x,
-                    cube_collider_size.y,
-                    cube_collider_size.z,
+                cube_bundle(
+                    format!("Cube_{x}_{y}"),
+                    Transform::from_translation(pos),
+                    library.mesh(CUBE_ASSET_KEY, &mut meshes, cube_mesh),
+                    library.material(CUBE_ASSET_KEY, &mut materials, cube_material),
                 ),
-                Friction::coefficient(0.8),
-                Restitution::coefficient(0.4),
                 Sleeping::disabled(),
-                Transform::from_translation(pos),
-                Mesh3d(meshes.add(Mesh::from(Cuboid::new(
-                    cube_size.x,
-                    cube_size.y,
-                    cube_size.z,
-                )))),
-                MeshMaterial3d(materials.add(Color::srgb(0.6, 0.7, 1.0))),
-                // enable events for these entities:
-                ActiveEvents::COLLISION_EVENTS,
             ));
         }
     }
 }
 
+fn cube_mesh() -> Mesh {
+    Mesh::from(Cuboid::from_size(CUBE_SIZE))
+}
+
+fn cube_material() -> StandardMaterial {
+    Color::srgb(0.6, 0.7, 1.0).into()
+}
+
+fn cube_bundle(
+    name: String,
+    transform: Transform,
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+) -> impl Bundle {
+    let cube_collider_size = CUBE_SIZE * 0.5;
+    (
+        Name::new(name),
+        FallingCube,
+        RigidBody::Dynamic,
+        Collider::cuboid(
+            cube_collider_size.x,
+            cube_collider_size.y,
+            cube_collider_size.z,
+        ),
+        Friction::coefficient(0.8),
+        Restitution::coefficient(0.4),
+        transform,
+        Mesh3d(mesh),
+        MeshMaterial3d(material),
+        // enable events for these entities:
+        ActiveEvents::COLLISION_EVENTS,
+    )
+}
+
+/// Saved state of a cube, enough to resume its motion exactly.
+#[derive(Reflect, Default)]
+struct FallingCubePrefab {
+    name: String,
+    transform: Transform,
+    velocity: Velocity,
+    sleeping: Sleeping,
+    external_force: ExternalForce,
+    external_impulse: ExternalImpulse,
+}
+
+impl Prefab for FallingCubePrefab {
+    type Marker = FallingCube;
+
+    fn spawn(self, target: Entity, world: &mut World) {
+        let mesh = PrefabAssetLibrary::world_mesh(world, CUBE_ASSET_KEY, cube_mesh);
+        let material = PrefabAssetLibrary::world_material(world, CUBE_ASSET_KEY, cube_material);
+
+        world.entity_mut(target).insert((
+            cube_bundle(self.name, self.transform, mesh, material),
+            self.velocity,
+            self.sleeping,
+            self.external_force,
+            self.external_impulse,
+        ));
+    }
+
+    fn extract(builder: BuilderRef) -> BuilderRef {
+        builder.extract_prefab(|entity| {
+            Some(FallingCubePrefab {
+                name: entity.get::<Name>()?.to_string(),
+                transform: entity.get::<Transform>().copied().unwrap_or_default(),
+                velocity: entity.get::<Velocity>().copied().unwrap_or_default(),
+                sleeping: entity.get::<Sleeping>().copied().unwrap_or_default(),
+                external_force: entity.get::<ExternalForce>().copied().unwrap_or_default(),
+                external_impulse: entity.get::<ExternalImpulse>().copied().unwrap_or_default(),
+            })
+        })
+    }
+}
+
+fn capture_cubes(In(cap): In<Builder>, world: &World) -> Builder {
+    cap.scope(world, |b| {
+        b.extract_all_prefabs::<FallingCubePrefab>()
+            // `RapierConfiguration::gravity` follows it
+            .extract_resource::<WorldGravity>()
+            .clear_empty()
+    })
+}
+
+fn apply_cubes(In(apply): In<Applier<'static>>, world: &mut World) -> Applier<'static> {
+    apply.scope(world, |a| {
+        a.prefab::<FallingCubePrefab>()
+            .despawn::<With<FallingCube>>()
+    })
+}
+
 fn setup_gravity(mut commands: Commands, mut q: Query<&mut RapierConfiguration>) {
     commands.insert_resource(WorldGravity::default());
 
@@ -0,0 +1,291 @@
+use crate::drone_plugin::{DroneInputSet, DroneInputSource, DronePosition, PlayerDrone};
+use crate::rc_protocol::{CrsfDecoder, RC_CHANNEL_COUNT, RcFrame, RcFrameDecoder, SbusDecoder};
+use bevy::prelude::*;
+use crossbeam_channel::{Receiver, Sender};
+use std::fs::File;
+use std::io::Read;
+use std::net::TcpStream;
+use std::time::Duration;
+
+/// Flies the player drone from CRSF or SBUS frames read from a byte stream: a pty or
+/// USB-serial passthrough of a radio, a TCP socket or a recorded capture file.
+///
+/// The sticks are only set while [`DroneInputSource`] is `RcReceiver`, which the app or the
+/// main menu selects.
+pub struct RcInputPlugin;
+
+impl Plugin for RcInputPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RcInputSettings>()
+            .init_resource::<DroneInputSource>()
+            .add_systems(Startup, start_rc_input)
+            .add_systems(
+                Update,
+                (attach_rc_channels, receive_rc_frames, apply_rc_channels)
+                    .chain()
+                    .in_set(DroneInputSet),
+            )
+            .register_type::<RcInputSettings>()
+            .register_type::<RcChannels>()
+            .register_type::<ChannelMap>();
+    }
+}
+
+#[derive(Resource, Reflect, Clone)]
+#[reflect(Resource)]
+pub struct RcInputSettings {
+    pub stream: RcByteStream,
+    pub protocol: RcProtocol,
+    /// Sticks are centered and the throttle cut when no frame arrives for this long.
+    pub failsafe_timeout_secs: f32,
+}
+
+impl Default for RcInputSettings {
+    fn default() -> Self {
+        Self {
+            stream: RcByteStream::Device {
+                path: "/dev/ttyACM0".to_string(),
+            },
+            protocol: RcProtocol::Crsf,
+            failsafe_timeout_secs: 0.5,
+        }
+    }
+}
+
+#[derive(Reflect, Clone, Debug)]
+pub enum RcByteStream {
+    /// A pty or serial device already configured for the protocol's baud rate.
+    Device {
+        path: String,
+    },
+    /// A captured byte stream, replayed at `frame_rate_hz` channel frames per second. Rates that
+    /// aren't positive replay as fast as the stream reads.
+    Recording {
+        path: String,
+        frame_rate_hz: f32,
+        looped: bool,
+    },
+    Tcp {
+        address: String,
+    },
+}
+
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RcProtocol {
+    Crsf,
+    Sbus,
+}
+
+impl RcProtocol {
+    fn decoder(self) -> Box<dyn RcFrameDecoder> {
+        match self {
+            RcProtocol::Crsf => Box::new(CrsfDecoder::default()),
+            RcProtocol::Sbus => Box::new(SbusDecoder::default()),
+        }
+    }
+}
+
+/// Latest RC channels received for a drone, normalized to `-1..=1`.
+#[derive(Component, Reflect, Debug, Clone)]
+#[reflect(Component)]
+pub struct RcChannels {
+    pub channels: [f32; RC_CHANNEL_COUNT],
+    pub failsafe: bool,
+    pub rssi_dbm: Option<i16>,
+    pub link_quality: Option<u8>,
+    /// Seconds since the last channel frame.
+    pub frame_age_secs: f32,
+}
+
+impl Default for RcChannels {
+    fn default() -> Self {
+        Self {
+            channels: [0.0; RC_CHANNEL_COUNT],
+            failsafe: true,
+            rssi_dbm: None,
+            link_quality: None,
+            frame_age_secs: f32::INFINITY,
+        }
+    }
+}
+
+/// Which channel drives which stick, zero based. Defaults to AETR.
+#[derive(Component, Reflect, Debug, Clone)]
+#[reflect(Component)]
+pub struct ChannelMap {
+    pub roll: usize,
+    pub pitch: usize,
+    pub throttle: usize,
+    pub yaw: usize,
+}
+
+impl Default for ChannelMap {
+    fn default() -> Self {
+        Self {
+            roll: 0,
+            pitch: 1,
+            throttle: 2,
+            yaw: 3,
+        }
+    }
+}
+
+#[derive(Resource)]
+struct RcInputStream {
+    frames: Receiver<RcFrame>,
+}
+
+fn start_rc_input(mut commands: Commands, settings: Res<RcInputSettings>) {
+    let (frames_tx, frames_rx) = crossbeam_channel::unbounded();
+    let stream = settings.stream.clone();
+    let protocol = settings.protocol;
+
+    std::thread::Builder::new()
+        .name("rc-input".to_string())
+        .spawn(move || read_rc_stream(stream, protocol, frames_tx))
+        .expect("Failed to spawn the RC input thread");
+
+    commands.insert_resource(RcInputStream { frames: frames_rx });
+}
+
+fn open_stream(stream: &RcByteStream) -> std::io::Result<Box<dyn Read + Send>> {
+    Ok(match stream {
+        RcByteStream::Device { path } | RcByteStream::Recording { path, .. } => {
+            Box::new(File::open(path)?)
+        }
+        RcByteStream::Tcp { address } => Box::new(TcpStream::connect(address)?),
+    })
+}
+
+fn read_rc_stream(stream: RcByteStream, protocol: RcProtocol, frames: Sender<RcFrame>) {
+    let mut decoder = protocol.decoder();
+    let frame_interval = match &stream {
+        RcByteStream::Recording { frame_rate_hz, .. } => {
+            let interval = (*frame_rate_hz > 0.0)
+                .then(|| Duration::try_from_secs_f32(1.0 / frame_rate_hz).ok())
+                .flatten();
+            if interval.is_none() {
+                warn!("Invalid RC recording frame rate {frame_rate_hz}, reading without pacing");
+            }
+            interval
+        }
+        _ => None,
+    };
+    let looped = matches!(stream, RcByteStream::Recording { looped: true, .. });
+
+    loop {
+        let mut reader = match open_stream(&stream) {
+            Ok(reader) => reader,
+            Err(e) => {
+                error!("Failed to open RC input {stream:?}: {e}");
+                return;
+            }
+        };
+        info!("Reading {protocol:?} frames from {stream:?}");
+
+        let mut buffer = [0u8; 256];
+        loop {
+            let read = match reader.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(e) => {
+                    error!("RC input {stream:?} failed: {e}");
+                    return;
+                }
+            };
+            for &byte in &buffer[..read] {
+                let Some(frame) = decoder.feed(byte) else {
+                    continue;
+                };
+                let is_channels = matches!(frame, RcFrame::Channels { .. });
+                if frames.send(frame).is_err() {
+                    return;
+                }
+                // Recordings have no timing of their own
+                if let (true, Some(interval)) = (is_channels, frame_interval) {
+                    std::thread::sleep(interval);
+                }
+            }
+        }
+
+        if !looped {
+            info!("RC input {stream:?} ended");
+            return;
+        }
+    }
+}
+
+fn attach_rc_channels(
+    mut commands: Commands,
+    drones: Query<Entity, (With<PlayerDrone>, Without<RcChannels>)>,
+) {
+    for entity in drones.iter() {
+        commands
+            .entity(entity)
+            .insert((RcChannels::default(), ChannelMap::default()));
+    }
+}
+
+fn receive_rc_frames(
+    stream: Option<Res<RcInputStream>>,
+    settings: Res<RcInputSettings>,
+    mut channels: Query<&mut RcChannels, With<PlayerDrone>>,
+    time: Res<Time>,
+) {
+    let Some(stream) = stream else {
+        return;
+    };
+    let Ok(mut rc) = channels.single_mut() else {
+        debug!("No drone found to receive RC frames.");
+        return;
+    };
+
+    rc.frame_age_secs += time.delta_secs();
+    while let Ok(frame) = stream.frames.try_recv() {
+        match frame {
+            RcFrame::Channels { channels, failsafe } => {
+                rc.channels = channels;
+                rc.failsafe = failsafe;
+                rc.frame_age_secs = 0.0;
+            }
+            RcFrame::LinkStatistics {
+                rssi_dbm,
+                link_quality,
+            } => {
+                rc.rssi_dbm = Some(rssi_dbm);
+                rc.link_quality = Some(link_quality);
+            }
+        }
+    }
+
+    if rc.frame_age_secs > settings.failsafe_timeout_secs && !rc.failsafe {
+        warn!(
+            "No RC frames for {:.2}s, entering failsafe",
+            rc.frame_age_secs
+        );
+        rc.failsafe = true;
+    }
+}
+
+/// Maps RC channels to sticks, the same way for every byte stream and protocol.
+fn apply_rc_channels(
+    source: Res<DroneInputSource>,
+    mut drones: Query<(&RcChannels, &ChannelMap, &mut DronePosition)>,
+) {
+    if *source != DroneInputSource::RcReceiver {
+        return;
+    }
+
+    for (rc, map, mut sticks) in drones.iter_mut() {
+        if rc.failsafe {
+            *sticks = DronePosition::default();
+            continue;
+        }
+        let channel = |index: usize| rc.channels.get(index).copied().unwrap_or(0.0);
+        sticks.roll = channel(map.roll);
+        // Pushing the stick forward pitches the nose down
+        sticks.pitch = -channel(map.pitch);
+        sticks.throttle = (channel(map.throttle) + 1.0) / 2.0;
+        sticks.yaw = channel(map.yaw);
+    }
+}
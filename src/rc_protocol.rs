@@ -0,0 +1,326 @@
+//! Decoders for RC receiver protocols carried over serial-like byte streams.
+
+/// Number of channels carried by CRSF and SBUS RC frames.
+pub const RC_CHANNEL_COUNT: usize = 16;
+
+/// A decoded frame from an RC receiver.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RcFrame {
+    /// Channel values normalized to `-1..=1`.
+    Channels {
+        channels: [f32; RC_CHANNEL_COUNT],
+        /// The receiver lost the link and is sending failsafe values.
+        failsafe: bool,
+    },
+    LinkStatistics {
+        rssi_dbm: i16,
+        /// Percentage of received packets.
+        link_quality: u8,
+    },
+}
+
+/// Turns a byte stream into RC frames, resynchronising on corrupt data.
+pub trait RcFrameDecoder: Send {
+    fn feed(&mut self, byte: u8) -> Option<RcFrame>;
+}
+
+/// Both CRSF and SBUS use 11 bit channels where 172..=1811 is the full stick range.
+fn normalize_channel(raw: u16) -> f32 {
+    ((raw as f32 - 992.0) / 819.5).clamp(-1.0, 1.0)
+}
+
+/// Unpacks 16 little-endian 11 bit channels from 22 bytes.
+fn unpack_channels(data: &[u8]) -> [f32; RC_CHANNEL_COUNT] {
+    let mut channels = [0.0; RC_CHANNEL_COUNT];
+    let mut bits: u32 = 0;
+    let mut bit_count = 0;
+    let mut bytes = data.iter();
+    for channel in channels.iter_mut() {
+        while bit_count < 11 {
+            bits |= (*bytes.next().unwrap_or(&0) as u32) << bit_count;
+            bit_count += 8;
+        }
+        *channel = normalize_channel((bits & 0x7FF) as u16);
+        bits >>= 11;
+        bit_count -= 11;
+    }
+    channels
+}
+
+/// Crossfire / ExpressLRS receiver protocol.
+#[derive(Default)]
+pub struct CrsfDecoder {
+    buffer: Vec<u8>,
+}
+
+impl CrsfDecoder {
+    const SYNC_BYTES: [u8; 3] = [0xC8, 0xEA, 0xEE];
+    const MAX_FRAME_LENGTH: usize = 64;
+    const TYPE_LINK_STATISTICS: u8 = 0x14;
+    const TYPE_RC_CHANNELS_PACKED: u8 = 0x16;
+
+    fn decode(frame_type: u8, payload: &[u8]) -> Option<RcFrame> {
+        match frame_type {
+            Self::TYPE_RC_CHANNELS_PACKED if payload.len() == 22 => Some(RcFrame::Channels {
+                channels: unpack_channels(payload),
+                failsafe: false,
+            }),
+            Self::TYPE_LINK_STATISTICS if payload.len() >= 3 => {
+                // Uplink RSSI of both antennas is sent as positive dBm
+                let rssi = payload[0].min(payload[1]);
+                Some(RcFrame::LinkStatistics {
+                    rssi_dbm: -(rssi as i16),
+                    link_quality: payload[2],
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// The sync byte was a false start, look for the next one from the following byte on.
+    fn resync(&mut self) -> Option<RcFrame> {
+        let bytes = std::mem::take(&mut self.buffer);
+        let mut frame = None;
+        for &byte in &bytes[1..] {
+            frame = self.feed(byte).or(frame);
+        }
+        frame
+    }
+}
+
+/// CRC-8/DVB-S2 used by CRSF over the frame type and payload.
+fn crc8_dvb_s2(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |mut crc, byte| {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0xD5
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+impl RcFrameDecoder for CrsfDecoder {
+    fn feed(&mut self, byte: u8) -> Option<RcFrame> {
+        // [sync] [length] [type] [payload..] [crc], length counts type, payload and crc
+        if self.buffer.is_empty() && !Self::SYNC_BYTES.contains(&byte) {
+            return None;
+        }
+        self.buffer.push(byte);
+
+        let &length = self.buffer.get(1)?;
+        let length = length as usize;
+        if !(2..=Self::MAX_FRAME_LENGTH - 2).contains(&length) {
+            return self.resync();
+        }
+        if self.buffer.len() < length + 2 {
+            return None;
+        }
+
+        let (body, crc) = self.buffer[2..].split_at(length - 1);
+        if crc8_dvb_s2(body) != crc[0] {
+            return self.resync();
+        }
+        let frame = Self::decode(body[0], &body[1..]);
+        self.buffer.clear();
+        frame
+    }
+}
+
+/// Futaba SBUS, 25 byte frames at 100 kbaud 8E2 (inverted on the wire).
+#[derive(Default)]
+pub struct SbusDecoder {
+    buffer: Vec<u8>,
+}
+
+impl SbusDecoder {
+    const HEADER: u8 = 0x0F;
+    const FRAME_LENGTH: usize = 25;
+    const FLAG_FAILSAFE: u8 = 1 << 3;
+
+    /// SBUS2 cycles the footer through telemetry slot ids.
+    fn is_footer(byte: u8) -> bool {
+        byte == 0x00 || byte & 0x0F == 0x04
+    }
+}
+
+impl RcFrameDecoder for SbusDecoder {
+    fn feed(&mut self, byte: u8) -> Option<RcFrame> {
+        if self.buffer.is_empty() && byte != Self::HEADER {
+            return None;
+        }
+        self.buffer.push(byte);
+        if self.buffer.len() < Self::FRAME_LENGTH {
+            return None;
+        }
+
+        let frame = std::mem::take(&mut self.buffer);
+        if !Self::is_footer(frame[24]) {
+            // Misaligned, retry from the next header byte inside the frame
+            if let Some(start) = frame[1..].iter().position(|&b| b == Self::HEADER) {
+                self.buffer.extend_from_slice(&frame[start + 1..]);
+            }
+            return None;
+        }
+
+        let flags = frame[23];
+        Some(RcFrame::Channels {
+            channels: unpack_channels(&frame[1..23]),
+            failsafe: flags & Self::FLAG_FAILSAFE != 0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Packs 16 11 bit channels like a receiver does.
+    fn pack_channels(raw: [u16; RC_CHANNEL_COUNT]) -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut bits: u32 = 0;
+        let mut bit_count = 0;
+        for channel in raw {
+            bits |= (channel as u32 & 0x7FF) << bit_count;
+            bit_count += 11;
+            while bit_count >= 8 {
+                data.push(bits as u8);
+                bits >>= 8;
+                bit_count -= 8;
+            }
+        }
+        data
+    }
+
+    fn crsf_frame(frame_type: u8, payload: &[u8]) -> Vec<u8> {
+        let mut body = vec![frame_type];
+        body.extend_from_slice(payload);
+        let mut frame = vec![0xC8, body.len() as u8 + 1];
+        frame.extend_from_slice(&body);
+        frame.push(crc8_dvb_s2(&body));
+        frame
+    }
+
+    fn sbus_frame(raw: [u16; RC_CHANNEL_COUNT], flags: u8, footer: u8) -> Vec<u8> {
+        let mut frame = vec![SbusDecoder::HEADER];
+        frame.extend(pack_channels(raw));
+        frame.extend([flags, footer]);
+        frame
+    }
+
+    fn feed_all(decoder: &mut impl RcFrameDecoder, bytes: &[u8]) -> Vec<RcFrame> {
+        bytes
+            .iter()
+            .filter_map(|&byte| decoder.feed(byte))
+            .collect()
+    }
+
+    /// Roll full left, pitch centered, throttle full, the rest centered.
+    fn test_channels() -> [u16; RC_CHANNEL_COUNT] {
+        let mut raw = [992; RC_CHANNEL_COUNT];
+        raw[0] = 172;
+        raw[2] = 1811;
+        raw
+    }
+
+    fn assert_test_channels(frame: &RcFrame, expected_failsafe: bool) {
+        let RcFrame::Channels { channels, failsafe } = frame else {
+            panic!("Expected channels, got {frame:?}");
+        };
+        assert_eq!(*failsafe, expected_failsafe);
+        assert!((channels[0] + 1.0).abs() < 1e-3);
+        assert!(channels[1].abs() < 1e-3);
+        assert!((channels[2] - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn crsf_decodes_channels() {
+        let frame = crsf_frame(0x16, &pack_channels(test_channels()));
+        let frames = feed_all(&mut CrsfDecoder::default(), &frame);
+        assert_eq!(frames.len(), 1);
+        assert_test_channels(&frames[0], false);
+    }
+
+    #[test]
+    fn crsf_decodes_link_statistics() {
+        let frame = crsf_frame(0x14, &[70, 60, 95, 0, 0, 0, 0, 0, 0, 0]);
+        let frames = feed_all(&mut CrsfDecoder::default(), &frame);
+        assert_eq!(
+            frames,
+            vec![RcFrame::LinkStatistics {
+                rssi_dbm: -60,
+                link_quality: 95
+            }]
+        );
+    }
+
+    #[test]
+    fn crsf_drops_bad_crc() {
+        let mut frame = crsf_frame(0x16, &pack_channels(test_channels()));
+        *frame.last_mut().unwrap() ^= 0xFF;
+        assert!(feed_all(&mut CrsfDecoder::default(), &frame).is_empty());
+    }
+
+    #[test]
+    fn crsf_skips_garbage_prefix() {
+        let mut bytes = vec![0x00, 0x42, 0xFF];
+        bytes.extend(crsf_frame(0x16, &pack_channels(test_channels())));
+        let frames = feed_all(&mut CrsfDecoder::default(), &bytes);
+        assert_eq!(frames.len(), 1);
+        assert_test_channels(&frames[0], false);
+    }
+
+    #[test]
+    fn crsf_resyncs_after_false_sync() {
+        // A stray sync byte and a plausible length swallow the start of the real frame
+        let mut bytes = vec![0xEE, 24];
+        bytes.extend(crsf_frame(0x16, &pack_channels(test_channels())));
+        let frames = feed_all(&mut CrsfDecoder::default(), &bytes);
+        assert_eq!(frames.len(), 1);
+        assert_test_channels(&frames[0], false);
+    }
+
+    #[test]
+    fn crsf_recovers_from_truncated_frame() {
+        let frame = crsf_frame(0x16, &pack_channels(test_channels()));
+        let mut bytes = frame[..10].to_vec();
+        bytes.extend(&frame);
+        let frames = feed_all(&mut CrsfDecoder::default(), &bytes);
+        assert_eq!(frames.len(), 1);
+        assert_test_channels(&frames[0], false);
+    }
+
+    #[test]
+    fn sbus_decodes_channels() {
+        let frame = sbus_frame(test_channels(), 0, 0x00);
+        let frames = feed_all(&mut SbusDecoder::default(), &frame);
+        assert_eq!(frames.len(), 1);
+        assert_test_channels(&frames[0], false);
+    }
+
+    #[test]
+    fn sbus_decodes_failsafe() {
+        let frame = sbus_frame(test_channels(), SbusDecoder::FLAG_FAILSAFE, 0x00);
+        let frames = feed_all(&mut SbusDecoder::default(), &frame);
+        assert_eq!(frames.len(), 1);
+        assert_test_channels(&frames[0], true);
+    }
+
+    #[test]
+    fn sbus_drops_bad_footer() {
+        let frame = sbus_frame(test_channels(), 0, 0xFF);
+        assert!(feed_all(&mut SbusDecoder::default(), &frame).is_empty());
+    }
+
+    #[test]
+    fn sbus_skips_garbage_prefix() {
+        let mut bytes = vec![0x00, 0x42, 0xFF];
+        bytes.extend(sbus_frame(test_channels(), 0, 0x00));
+        let frames = feed_all(&mut SbusDecoder::default(), &bytes);
+        assert_eq!(frames.len(), 1);
+        assert_test_channels(&frames[0], false);
+    }
+}
@@ -0,0 +1,713 @@
+use crate::drone_plugin::{
+    DroneBattery, DroneInputSet, DroneInputSource, DroneKinematics, DronePosition, PlayerDrone,
+};
+use crate::flight_controller_plugin::FlightController;
+use crate::free_camera_plugin::CameraRotation;
+use avian3d::prelude::{AngularVelocity, LinearVelocity};
+use bevy::ecs::system::SystemParam;
+use bevy::input::gamepad::GamepadInput;
+use bevy::input::keyboard::KeyboardInput;
+use bevy::input::mouse::{
+    AccumulatedMouseMotion, AccumulatedMouseScroll, MouseMotion, MouseScrollUnit,
+};
+use bevy::input::{ButtonState, InputSystem};
+use bevy::platform::collections::{HashMap, HashSet};
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+use bevy_rapier3d::prelude::Velocity;
+use serde::{Deserialize, Serialize};
+use std::hash::Hash;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Records every input frame of a session (keyboard, mouse, gamepad buttons and axes, the
+/// player drone's sticks) together with the initial level state, and plays it back so the
+/// flight reproduces exactly. Recordings are RON files that can be attached to bug reports.
+///
+/// Playback also replays the recorded frame times, so every frame runs the same number of
+/// fixed steps with the same inputs as the original session. Only state advanced from inputs
+/// and the fixed timestep reproduces exactly; external links such as PX4 SITL don't. The
+/// initial state covers named transforms, Avian and Rapier body velocities and the player
+/// drone, not physics engine internals such as contacts and sleeping, so physics can drift
+/// when recording starts mid-motion.
+///
+/// `F5` starts and stops recording, `F6` plays back the latest recording or stops the playback.
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ReplaySettings>()
+            .init_resource::<Replay>()
+            .init_resource::<DroneInputSource>()
+            .add_systems(PostStartup, apply_startup_settings)
+            .add_systems(PreUpdate, inject_replayed_input.after(InputSystem))
+            .add_systems(
+                Update,
+                (
+                    play_back_frame.in_set(DroneInputSet),
+                    record_frame.after(DroneInputSet),
+                    handle_replay_keys.after(record_frame),
+                ),
+            )
+            .add_systems(Last, save_recording_on_exit)
+            .register_type::<ReplaySettings>();
+    }
+}
+
+#[derive(Resource, Reflect, Clone)]
+#[reflect(Resource)]
+pub struct ReplaySettings {
+    pub directory: String,
+    /// Record from the first frame and save when the app exits.
+    pub record_on_startup: bool,
+    /// Play this recording back from the first frame instead.
+    pub replay_file: Option<String>,
+}
+
+impl Default for ReplaySettings {
+    fn default() -> Self {
+        Self {
+            directory: "replays".to_string(),
+            record_on_startup: false,
+            replay_file: None,
+        }
+    }
+}
+
+/// Whether a session is being recorded or played back.
+#[derive(Resource, Default)]
+pub struct Replay {
+    state: ReplayState,
+    last_saved: Option<PathBuf>,
+}
+
+impl Replay {
+    pub fn is_recording(&self) -> bool {
+        matches!(self.state, ReplayState::Recording(_))
+    }
+
+    pub fn is_playing(&self) -> bool {
+        matches!(self.state, ReplayState::Playing(_))
+    }
+}
+
+#[derive(Default)]
+enum ReplayState {
+    #[default]
+    Idle,
+    Recording(Recorder),
+    Playing(Playback),
+}
+
+struct Recorder {
+    recording: InputRecording,
+    /// Last recorded gamepad axis values, only changes are stored per frame.
+    axes: HashMap<GamepadInput, f32>,
+}
+
+struct Playback {
+    recording: InputRecording,
+    /// Index of the frame being played.
+    cursor: usize,
+    keys_held: HashSet<KeyCode>,
+    mouse_buttons_held: HashSet<MouseButton>,
+    buttons_held: HashSet<GamepadButton>,
+    axes: HashMap<GamepadInput, f32>,
+    previous_source: DroneInputSource,
+}
+
+/// Keys controlling the recorder itself, never recorded.
+const REPLAY_KEYS: [KeyCode; 2] = [KeyCode::F5, KeyCode::F6];
+
+const RECORDING_VERSION: u32 = 2;
+
+#[derive(Serialize, Deserialize)]
+struct InputRecording {
+    version: u32,
+    fixed_timestep: Duration,
+    initial_state: InitialState,
+    frames: Vec<InputFrame>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct InitialState {
+    /// Transforms of all named entities.
+    transforms: Vec<(String, Transform)>,
+    /// Velocities of the named physics bodies.
+    velocities: Vec<(String, BodyVelocity)>,
+    player_drone: Option<DroneSnapshot>,
+    keys_held: Vec<KeyCode>,
+    mouse_buttons_held: Vec<MouseButton>,
+    buttons_held: Vec<GamepadButton>,
+    axes: Vec<(GamepadInput, f32)>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+struct BodyVelocity {
+    linear: Vec3,
+    angular: Vec3,
+}
+
+#[derive(Serialize, Deserialize)]
+struct DroneSnapshot {
+    sticks: DronePosition,
+    kinematics: DroneKinematics,
+    battery: DroneBattery,
+    flight_controller: Option<FlightController>,
+}
+
+/// Inputs of one `Update` frame.
+#[derive(Serialize, Deserialize, Default)]
+struct InputFrame {
+    /// Real time since the previous frame.
+    delta: Duration,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    keys_pressed: Vec<KeyCode>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    keys_released: Vec<KeyCode>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    mouse_buttons_pressed: Vec<MouseButton>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    mouse_buttons_released: Vec<MouseButton>,
+    /// Mouse movement this frame, in pixels.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mouse_motion: Option<Vec2>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mouse_scroll: Option<(MouseScrollUnit, Vec2)>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    buttons_pressed: Vec<GamepadButton>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    buttons_released: Vec<GamepadButton>,
+    /// Gamepad axes that changed since the previous frame.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    axes: Vec<(GamepadInput, f32)>,
+    /// Player drone sticks after this frame's input systems, used by the next fixed steps.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sticks: Option<DronePosition>,
+}
+
+/// Marks the gamepad spawned to receive replayed input when none is connected.
+#[derive(Component)]
+struct ReplayGamepad;
+
+/// Everything needed to start and stop recording or playback.
+#[derive(SystemParam)]
+struct Session<'w, 's> {
+    commands: Commands<'w, 's>,
+    source: ResMut<'w, DroneInputSource>,
+    fixed_time: ResMut<'w, Time<Fixed>>,
+    time_strategy: ResMut<'w, TimeUpdateStrategy>,
+    keys: ResMut<'w, ButtonInput<KeyCode>>,
+    mouse_buttons: ResMut<'w, ButtonInput<MouseButton>>,
+    gamepads: Query<'w, 's, &'static mut Gamepad>,
+    replay_gamepads: Query<'w, 's, Entity, With<ReplayGamepad>>,
+    transforms: Query<
+        'w,
+        's,
+        (
+            &'static Name,
+            &'static mut Transform,
+            Option<&'static mut CameraRotation>,
+        ),
+        Without<Node>,
+    >,
+    bodies: Query<
+        'w,
+        's,
+        (
+            &'static Name,
+            Option<&'static mut LinearVelocity>,
+            Option<&'static mut AngularVelocity>,
+            Option<&'static mut Velocity>,
+        ),
+    >,
+    drone: Query<
+        'w,
+        's,
+        (
+            &'static mut DronePosition,
+            &'static mut DroneKinematics,
+            &'static mut DroneBattery,
+            Option<&'static mut FlightController>,
+        ),
+        With<PlayerDrone>,
+    >,
+}
+
+impl Session<'_, '_> {
+    fn snapshot(&self) -> InitialState {
+        let gamepad = self.gamepads.iter().next();
+        InitialState {
+            transforms: self
+                .transforms
+                .iter()
+                .map(|(name, transform, _)| (name.to_string(), *transform))
+                .collect(),
+            velocities: self
+                .bodies
+                .iter()
+                .filter_map(|(name, linear, angular, rapier)| {
+                    let velocity = match (linear, angular, rapier) {
+                        (_, _, Some(rapier)) => BodyVelocity {
+                            linear: rapier.linvel,
+                            angular: rapier.angvel,
+                        },
+                        (None, None, None) => return None,
+                        (linear, angular, None) => BodyVelocity {
+                            linear: linear.map_or(Vec3::ZERO, |v| v.0),
+                            angular: angular.map_or(Vec3::ZERO, |v| v.0),
+                        },
+                    };
+                    Some((name.to_string(), velocity))
+                })
+                .collect(),
+            player_drone: self
+                .drone
+                .single()
+                .ok()
+                .map(|(sticks, kinematics, battery, fc)| DroneSnapshot {
+                    sticks: *sticks,
+                    kinematics: kinematics.clone(),
+                    battery: battery.clone(),
+                    flight_controller: fc.cloned(),
+                }),
+            keys_held: recorded_keys(self.keys.get_pressed()),
+            mouse_buttons_held: self.mouse_buttons.get_pressed().copied().collect(),
+            buttons_held: gamepad
+                .map(|gamepad| gamepad.get_pressed().copied().collect())
+                .unwrap_or_default(),
+            axes: gamepad
+                .map(|gamepad| {
+                    gamepad
+                        .analog()
+                        .all_axes_and_values()
+                        .map(|(input, value)| (*input, value))
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+
+    fn restore(&mut self, state: &InitialState) {
+        let saved: HashMap<&str, &Transform> = state
+            .transforms
+            .iter()
+            .map(|(name, transform)| (name.as_str(), transform))
+            .collect();
+        let mut restored = 0;
+        for (name, mut transform, rotation) in self.transforms.iter_mut() {
+            if let Some(saved) = saved.get(name.as_str()) {
+                *transform = **saved;
+                if let Some(mut rotation) = rotation {
+                    rotation.look_along(transform.rotation);
+                }
+                restored += 1;
+            }
+        }
+
+        let velocities: HashMap<&str, &BodyVelocity> = state
+            .velocities
+            .iter()
+            .map(|(name, velocity)| (name.as_str(), velocity))
+            .collect();
+        for (name, linear, angular, rapier) in self.bodies.iter_mut() {
+            let Some(saved) = velocities.get(name.as_str()) else {
+                continue;
+            };
+            if let Some(mut linear) = linear {
+                linear.0 = saved.linear;
+            }
+            if let Some(mut angular) = angular {
+                angular.0 = saved.angular;
+            }
+            if let Some(mut rapier) = rapier {
+                rapier.linvel = saved.linear;
+                rapier.angvel = saved.angular;
+            }
+        }
+        if restored < saved.len() {
+            warn!(
+                "Only {restored} of {} recorded entities exist, the replay may diverge",
+                saved.len()
+            );
+        }
+
+        let Some(snapshot) = &state.player_drone else {
+            return;
+        };
+        let Ok((mut sticks, mut kinematics, mut battery, fc)) = self.drone.single_mut() else {
+            warn!("No drone found to restore the recorded drone state to.");
+            return;
+        };
+        *sticks = snapshot.sticks;
+        *kinematics = snapshot.kinematics.clone();
+        *battery = snapshot.battery.clone();
+        if let (Some(mut fc), Some(saved)) = (fc, &snapshot.flight_controller) {
+            *fc = saved.clone();
+        }
+    }
+
+    /// Makes the next frame start on a fixed step boundary, both when recording and replaying.
+    fn align_fixed_steps(&mut self) {
+        let overstep = self.fixed_time.overstep();
+        self.fixed_time.discard_overstep(overstep);
+    }
+
+    fn start_recording(&mut self, replay: &mut Replay) {
+        self.align_fixed_steps();
+
+        let initial_state = self.snapshot();
+        let axes = initial_state.axes.iter().copied().collect();
+        replay.state = ReplayState::Recording(Recorder {
+            recording: InputRecording {
+                version: RECORDING_VERSION,
+                fixed_timestep: self.fixed_time.timestep(),
+                initial_state,
+                frames: Vec::new(),
+            },
+            axes,
+        });
+        info!("Recording inputs");
+    }
+
+    fn start_playback(&mut self, replay: &mut Replay, recording: InputRecording) {
+        if recording.version != RECORDING_VERSION {
+            error!(
+                "Unsupported recording version {}, expected {RECORDING_VERSION}",
+                recording.version
+            );
+            return;
+        }
+        let Some(first_frame) = recording.frames.first() else {
+            warn!("The recording has no frames to play back.");
+            return;
+        };
+
+        if self.fixed_time.timestep() != recording.fixed_timestep {
+            warn!(
+                "Switching the fixed timestep to the recorded {:?}",
+                recording.fixed_timestep
+            );
+            self.fixed_time.set_timestep(recording.fixed_timestep);
+        }
+        self.align_fixed_steps();
+        self.restore(&recording.initial_state);
+        if self.gamepads.is_empty() {
+            self.commands.spawn((
+                Name::new("Replay Gamepad"),
+                Gamepad::default(),
+                ReplayGamepad,
+            ));
+        }
+        *self.time_strategy = TimeUpdateStrategy::ManualDuration(first_frame.delta);
+        let previous_source = std::mem::replace(&mut *self.source, DroneInputSource::Replay);
+
+        let initial_state = &recording.initial_state;
+        info!("Playing back {} frames", recording.frames.len());
+        replay.state = ReplayState::Playing(Playback {
+            keys_held: initial_state.keys_held.iter().copied().collect(),
+            mouse_buttons_held: initial_state.mouse_buttons_held.iter().copied().collect(),
+            buttons_held: initial_state.buttons_held.iter().copied().collect(),
+            axes: initial_state.axes.iter().copied().collect(),
+            cursor: 0,
+            previous_source,
+            recording,
+        });
+    }
+
+    fn stop_playback(&mut self, replay: &mut Replay) {
+        let ReplayState::Playing(playback) = std::mem::take(&mut replay.state) else {
+            return;
+        };
+        *self.source = playback.previous_source;
+        *self.time_strategy = TimeUpdateStrategy::Automatic;
+        self.keys.reset_all();
+        self.mouse_buttons.reset_all();
+        for mut gamepad in self.gamepads.iter_mut() {
+            gamepad.digital_mut().reset_all();
+        }
+        for entity in self.replay_gamepads.iter() {
+            self.commands.entity(entity).despawn();
+        }
+        info!(
+            "Playback stopped after {} of {} frames",
+            playback.cursor,
+            playback.recording.frames.len()
+        );
+    }
+}
+
+fn stop_recording(replay: &mut Replay, settings: &ReplaySettings) {
+    let ReplayState::Recording(recorder) = std::mem::take(&mut replay.state) else {
+        return;
+    };
+    if let Some(path) = save_recording(&recorder.recording, &settings.directory) {
+        replay.last_saved = Some(path);
+    }
+}
+
+fn save_recording(recording: &InputRecording, directory: &str) -> Option<PathBuf> {
+    if let Err(e) = std::fs::create_dir_all(directory) {
+        error!("Failed to create the replay directory {directory}: {e}");
+        return None;
+    }
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let path = PathBuf::from(directory).join(format!("replay_{timestamp}.ron"));
+
+    let text = match ron::ser::to_string_pretty(recording, ron::ser::PrettyConfig::default()) {
+        Ok(text) => text,
+        Err(e) => {
+            error!("Failed to serialize the recording: {e}");
+            return None;
+        }
+    };
+    match std::fs::write(&path, text) {
+        Ok(()) => {
+            info!(
+                "Saved {} recorded frames to {}",
+                recording.frames.len(),
+                path.display()
+            );
+            Some(path)
+        }
+        Err(e) => {
+            error!("Failed to save the recording to {}: {e}", path.display());
+            None
+        }
+    }
+}
+
+fn load_recording(path: &Path) -> Option<InputRecording> {
+    let text = std::fs::read_to_string(path)
+        .inspect_err(|e| error!("Failed to read the recording {}: {e}", path.display()))
+        .ok()?;
+    ron::de::from_str(&text)
+        .inspect_err(|e| error!("Invalid recording {}: {e}", path.display()))
+        .ok()
+}
+
+/// The most recently modified recording in `directory`.
+fn latest_recording(directory: &str) -> Option<PathBuf> {
+    std::fs::read_dir(directory)
+        .ok()?
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "ron"))
+        .max_by_key(|entry| entry.metadata().and_then(|m| m.modified()).ok())
+        .map(|entry| entry.path())
+}
+
+fn apply_startup_settings(
+    settings: Res<ReplaySettings>,
+    mut replay: ResMut<Replay>,
+    mut session: Session,
+) {
+    if let Some(path) = &settings.replay_file {
+        if let Some(recording) = load_recording(Path::new(path)) {
+            session.start_playback(&mut replay, recording);
+        }
+    } else if settings.record_on_startup {
+        session.start_recording(&mut replay);
+    }
+}
+
+/// Read from keyboard events since playback overrides the keyboard state.
+fn handle_replay_keys(
+    mut key_events: EventReader<KeyboardInput>,
+    settings: Res<ReplaySettings>,
+    mut replay: ResMut<Replay>,
+    mut session: Session,
+) {
+    for event in key_events.read() {
+        if event.state != ButtonState::Pressed || event.repeat {
+            continue;
+        }
+        match event.key_code {
+            KeyCode::F5 if replay.is_recording() => stop_recording(&mut replay, &settings),
+            KeyCode::F5 if !replay.is_playing() => session.start_recording(&mut replay),
+            KeyCode::F6 if replay.is_playing() => session.stop_playback(&mut replay),
+            KeyCode::F6 if !replay.is_recording() => {
+                let path = replay
+                    .last_saved
+                    .clone()
+                    .or_else(|| latest_recording(&settings.directory));
+                let Some(path) = path else {
+                    warn!("No recording found in {} to play back.", settings.directory);
+                    continue;
+                };
+                if let Some(recording) = load_recording(&path) {
+                    info!("Playing back {}", path.display());
+                    session.start_playback(&mut replay, recording);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn record_frame(
+    mut replay: ResMut<Replay>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    mouse_motion: Res<AccumulatedMouseMotion>,
+    mouse_scroll: Res<AccumulatedMouseScroll>,
+    gamepads: Query<&Gamepad>,
+    drone: Query<&DronePosition, With<PlayerDrone>>,
+    time: Res<Time<Real>>,
+) {
+    let ReplayState::Recording(recorder) = &mut replay.state else {
+        return;
+    };
+
+    let mut frame = InputFrame {
+        delta: time.delta(),
+        keys_pressed: recorded_keys(keys.get_just_pressed()),
+        keys_released: recorded_keys(keys.get_just_released()),
+        mouse_buttons_pressed: mouse_buttons.get_just_pressed().copied().collect(),
+        mouse_buttons_released: mouse_buttons.get_just_released().copied().collect(),
+        mouse_motion: (mouse_motion.delta != Vec2::ZERO).then_some(mouse_motion.delta),
+        mouse_scroll: (mouse_scroll.delta != Vec2::ZERO)
+            .then_some((mouse_scroll.unit, mouse_scroll.delta)),
+        sticks: drone.single().ok().copied(),
+        ..default()
+    };
+    if let Some(gamepad) = gamepads.iter().next() {
+        frame.buttons_pressed = gamepad.get_just_pressed().copied().collect();
+        frame.buttons_released = gamepad.get_just_released().copied().collect();
+        for (input, value) in gamepad.analog().all_axes_and_values() {
+            if recorder.axes.insert(*input, value) != Some(value) {
+                frame.axes.push((*input, value));
+            }
+        }
+    }
+    recorder.recording.frames.push(frame);
+}
+
+fn recorded_keys<'a>(keys: impl Iterator<Item = &'a KeyCode>) -> Vec<KeyCode> {
+    keys.filter(|key| !REPLAY_KEYS.contains(key))
+        .copied()
+        .collect()
+}
+
+/// Replaces the live keyboard, mouse and gamepad state with the recorded one.
+fn inject_replayed_input(
+    mut replay: ResMut<Replay>,
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    mut mouse: ReplayedMouse,
+    mut gamepads: Query<&mut Gamepad>,
+) {
+    let ReplayState::Playing(playback) = &mut replay.state else {
+        return;
+    };
+    let Some(frame) = playback.recording.frames.get(playback.cursor) else {
+        return;
+    };
+
+    replay_buttons(
+        &mut keys,
+        &mut playback.keys_held,
+        &frame.keys_pressed,
+        &frame.keys_released,
+    );
+    replay_buttons(
+        &mut mouse.buttons,
+        &mut playback.mouse_buttons_held,
+        &frame.mouse_buttons_pressed,
+        &frame.mouse_buttons_released,
+    );
+    // Live motion is dropped, systems reading the events only see the recorded motion
+    mouse.motion_events.clear();
+    let motion = frame.mouse_motion.unwrap_or_default();
+    if motion != Vec2::ZERO {
+        mouse.motion_events.send(MouseMotion { delta: motion });
+    }
+    mouse.motion.delta = motion;
+    let (unit, scroll) = frame
+        .mouse_scroll
+        .unwrap_or((MouseScrollUnit::Line, Vec2::ZERO));
+    mouse.scroll.unit = unit;
+    mouse.scroll.delta = scroll;
+    playback.axes.extend(frame.axes.iter().copied());
+
+    let Some(mut gamepad) = gamepads.iter_mut().next() else {
+        debug!("No gamepad found to replay gamepad input to.");
+        return;
+    };
+    replay_buttons(
+        gamepad.digital_mut(),
+        &mut playback.buttons_held,
+        &frame.buttons_pressed,
+        &frame.buttons_released,
+    );
+    for (input, value) in &playback.axes {
+        gamepad.analog_mut().set(*input, *value);
+    }
+}
+
+#[derive(SystemParam)]
+struct ReplayedMouse<'w> {
+    buttons: ResMut<'w, ButtonInput<MouseButton>>,
+    motion_events: ResMut<'w, Events<MouseMotion>>,
+    motion: ResMut<'w, AccumulatedMouseMotion>,
+    scroll: ResMut<'w, AccumulatedMouseScroll>,
+}
+
+fn replay_buttons<T: Copy + Eq + Hash + Send + Sync + 'static>(
+    input: &mut ButtonInput<T>,
+    held: &mut HashSet<T>,
+    pressed: &[T],
+    released: &[T],
+) {
+    // Drop live input, restore what the recording held, then apply this frame's changes
+    input.reset_all();
+    for button in held.iter() {
+        input.press(*button);
+    }
+    input.clear();
+    for button in pressed {
+        input.press(*button);
+        held.insert(*button);
+    }
+    for button in released {
+        input.release(*button);
+        held.remove(button);
+    }
+}
+
+/// Applies the recorded sticks and schedules the next frame's time step.
+fn play_back_frame(mut replay: ResMut<Replay>, mut session: Session) {
+    let ReplayState::Playing(playback) = &mut replay.state else {
+        return;
+    };
+    let Some(frame) = playback.recording.frames.get(playback.cursor) else {
+        return;
+    };
+
+    if let (Some(sticks), Ok((mut current, ..))) = (frame.sticks, session.drone.single_mut()) {
+        *current = sticks;
+    }
+    playback.cursor += 1;
+    let next_delta = playback
+        .recording
+        .frames
+        .get(playback.cursor)
+        .map(|next| next.delta);
+    match next_delta {
+        Some(delta) => *session.time_strategy = TimeUpdateStrategy::ManualDuration(delta),
+        None => session.stop_playback(&mut replay),
+    }
+}
+
+fn save_recording_on_exit(
+    mut exit: EventReader<AppExit>,
+    settings: Res<ReplaySettings>,
+    mut replay: ResMut<Replay>,
+) {
+    if exit.is_empty() {
+        return;
+    }
+    exit.clear();
+    stop_recording(&mut replay, &settings);
+}
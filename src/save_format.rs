@@ -0,0 +1,634 @@
+//! File formats for save files: readable JSON and RON, compact MessagePack, optionally
+//! gzip-compressed, as bevy_save [`Format`]s. The format of an existing file is picked by its
+//! extension.
+//!
+//! Every file has a version header around the snapshot, and loading upgrades older snapshots
+//! with the [`SaveMigrations`] passed to [`with_save_migrations`]. Snapshots are written and
+//! read by each format's own serializer, only older snapshots are read into the JSON value
+//! tree that migrations edit.
+
+use crate::save_migration::{SaveMigrations, SnapshotData};
+use crate::snapshot_tree;
+use bevy::prelude::*;
+use bevy_save::error::Error;
+use bevy_save::format::Format;
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::de::{
+    self, DeserializeOwned, DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess,
+    Visitor,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::fmt;
+use std::io::{BufWriter, Read, Write};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
+pub enum SaveFormat {
+    /// Pretty printed JSON, for debugging. JSON has no NaN, NaN values are saved as `null`
+    /// and the save doesn't load.
+    #[default]
+    Json,
+    /// Pretty printed RON, for editing by hand.
+    Ron,
+    /// Compact binary, for large worlds.
+    MessagePack,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
+pub enum SaveCompression {
+    #[default]
+    None,
+    Gzip,
+}
+
+/// Layout of the save files: a version header around the snapshot.
+#[derive(Serialize)]
+struct SaveFile<'a, T> {
+    version: u32,
+    snapshot: &'a T,
+}
+
+/// Just the header of a [`SaveFile`]. Saves from before the header are a bare JSON snapshot,
+/// without a version or a `snapshot` field.
+#[derive(Deserialize)]
+struct SaveHeader {
+    #[serde(default)]
+    version: u32,
+    #[serde(default, rename = "snapshot", deserialize_with = "skip_present")]
+    has_snapshot: bool,
+}
+
+/// Skips a field, noting it was there.
+fn skip_present<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    IgnoredAny::deserialize(deserializer).map(|_| true)
+}
+
+#[derive(Deserialize)]
+#[serde(field_identifier, rename_all = "snake_case")]
+enum SaveFileField {
+    Snapshot,
+    #[serde(other)]
+    Other,
+}
+
+/// Reads the snapshot of a [`SaveFile`] with the wrapped seed, skipping the header.
+struct SaveFileSeed<S>(S);
+
+impl<'de, S: DeserializeSeed<'de>> DeserializeSeed<'de> for SaveFileSeed<S> {
+    type Value = S::Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<S::Value, D::Error> {
+        deserializer.deserialize_struct("SaveFile", &["version", "snapshot"], self)
+    }
+}
+
+impl<'de, S: DeserializeSeed<'de>> Visitor<'de> for SaveFileSeed<S> {
+    type Value = S::Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a save file")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<S::Value, A::Error> {
+        let mut seed = Some(self.0);
+        let mut snapshot = None;
+        while let Some(field) = map.next_key::<SaveFileField>()? {
+            if let SaveFileField::Other = field {
+                map.next_value::<IgnoredAny>()?;
+                continue;
+            }
+            let seed = seed
+                .take()
+                .ok_or_else(|| de::Error::duplicate_field("snapshot"))?;
+            snapshot = Some(map.next_value_seed(seed)?);
+        }
+        snapshot.ok_or_else(|| de::Error::missing_field("snapshot"))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<S::Value, A::Error> {
+        seq.next_element::<IgnoredAny>()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        seq.next_element_seed(self.0)?
+            .ok_or_else(|| de::Error::invalid_length(1, &"a save file"))
+    }
+}
+
+thread_local! {
+    /// Migrations of the save or load running on this thread, see [`with_save_migrations`].
+    static MIGRATIONS: RefCell<SaveMigrations> = RefCell::default();
+}
+
+/// Runs `f` with `migrations` versioning the saves written and upgrading the saves read by the
+/// save formats. bevy_save formats can't reach the world, so the save system hands them over
+/// around every save and load.
+pub fn with_save_migrations<R>(migrations: &SaveMigrations, f: impl FnOnce() -> R) -> R {
+    let previous = MIGRATIONS.replace(migrations.clone());
+    let result = f();
+    MIGRATIONS.set(previous);
+    result
+}
+
+impl SaveFormat {
+    pub const ALL: [SaveFormat; 3] = [SaveFormat::Json, SaveFormat::Ron, SaveFormat::MessagePack];
+
+    /// Extension bevy_save appends to the slot key, `.json`, `.msgpack.gz`, ...
+    fn key_extension(self, compression: SaveCompression) -> &'static str {
+        match (self, compression) {
+            (SaveFormat::Json, SaveCompression::None) => ".json",
+            (SaveFormat::Json, SaveCompression::Gzip) => ".json.gz",
+            (SaveFormat::Ron, SaveCompression::None) => ".ron",
+            (SaveFormat::Ron, SaveCompression::Gzip) => ".ron.gz",
+            (SaveFormat::MessagePack, SaveCompression::None) => ".msgpack",
+            (SaveFormat::MessagePack, SaveCompression::Gzip) => ".msgpack.gz",
+        }
+    }
+
+    /// File extension for this format, `json`, `msgpack.gz`, ...
+    pub fn file_extension(self, compression: SaveCompression) -> &'static str {
+        &self.key_extension(compression)[1..]
+    }
+
+    /// Every file extension a save can have.
+    pub fn file_extensions() -> impl Iterator<Item = (&'static str, SaveFormat, SaveCompression)> {
+        Self::ALL.into_iter().flat_map(|format| {
+            [SaveCompression::None, SaveCompression::Gzip]
+                .map(|compression| (format.file_extension(compression), format, compression))
+        })
+    }
+
+    /// Format and compression of a file by its extension.
+    pub fn from_path(path: &Path) -> Option<(SaveFormat, SaveCompression)> {
+        let name = path.file_name()?.to_str()?;
+        Self::file_extensions()
+            .find(|(extension, _, _)| name.ends_with(&format!(".{extension}")))
+            .map(|(_, format, compression)| (format, compression))
+    }
+
+    /// Writes `value` uncompressed.
+    fn write_plain<W: Write, T: Serialize>(self, writer: W, value: &T) -> Result<(), String> {
+        let mut writer = BufWriter::new(writer);
+        match self {
+            SaveFormat::Json => {
+                serde_json::to_writer_pretty(&mut writer, value).map_err(|e| e.to_string())?
+            }
+            SaveFormat::Ron => {
+                ron::ser::to_writer_pretty(&mut writer, value, ron::ser::PrettyConfig::default())
+                    .map_err(|e| e.to_string())?
+            }
+            // Named fields keep the files readable by other MessagePack tools
+            SaveFormat::MessagePack => {
+                rmp_serde::encode::write_named(&mut writer, value).map_err(|e| e.to_string())?
+            }
+        }
+        writer.flush().map_err(|e| e.to_string())
+    }
+
+    pub fn decode<T: DeserializeOwned>(
+        self,
+        bytes: &[u8],
+        compression: SaveCompression,
+    ) -> Result<T, String> {
+        self.decode_plain(&decompress(bytes, compression)?)
+    }
+
+    fn decode_plain<T: DeserializeOwned>(self, bytes: &[u8]) -> Result<T, String> {
+        self.decode_seed(bytes, std::marker::PhantomData)
+    }
+
+    /// Reads uncompressed `bytes` with `seed`.
+    fn decode_seed<'de, S: DeserializeSeed<'de>>(
+        self,
+        bytes: &'de [u8],
+        seed: S,
+    ) -> Result<S::Value, String> {
+        match self {
+            SaveFormat::Json => {
+                let mut deserializer = serde_json::Deserializer::from_slice(bytes);
+                let value = seed
+                    .deserialize(&mut deserializer)
+                    .map_err(|e| e.to_string())?;
+                deserializer.end().map_err(|e| e.to_string())?;
+                Ok(value)
+            }
+            SaveFormat::Ron => {
+                let mut deserializer =
+                    ron::Deserializer::from_bytes(bytes).map_err(|e| e.to_string())?;
+                let value = seed
+                    .deserialize(&mut deserializer)
+                    .map_err(|e| e.to_string())?;
+                deserializer.end().map_err(|e| e.to_string())?;
+                Ok(value)
+            }
+            SaveFormat::MessagePack => seed
+                .deserialize(&mut rmp_serde::Deserializer::new(bytes))
+                .map_err(|e| e.to_string()),
+        }
+    }
+
+    /// Reads uncompressed `bytes` into the JSON value tree migrations edit.
+    fn decode_tree(self, bytes: &[u8]) -> Result<Value, String> {
+        match self {
+            SaveFormat::Json => serde_json::from_slice(bytes).map_err(|e| e.to_string()),
+            SaveFormat::Ron => {
+                snapshot_tree::from_ron(std::str::from_utf8(bytes).map_err(|e| e.to_string())?)
+            }
+            SaveFormat::MessagePack => {
+                snapshot_tree::from_self_describing(&mut rmp_serde::Deserializer::new(bytes))
+                    .map_err(|e| e.to_string())
+            }
+        }
+    }
+
+    /// Save version of a save file, `0` for saves from before the version header.
+    pub fn save_version(self, bytes: &[u8], compression: SaveCompression) -> Result<u32, String> {
+        self.decode::<SaveHeader>(bytes, compression)
+            .map(|header| header.version)
+    }
+
+    /// Decodes a save file into the JSON value tree and upgrades its snapshot to the current
+    /// version of `migrations`.
+    pub fn read_snapshot(
+        self,
+        bytes: &[u8],
+        compression: SaveCompression,
+        migrations: &SaveMigrations,
+    ) -> Result<SnapshotData, String> {
+        let bytes = decompress(bytes, compression)?;
+        let (version, snapshot) = match self.decode_tree(&bytes)? {
+            Value::Object(mut file) if file.contains_key("snapshot") => {
+                let version = file.get("version").and_then(Value::as_u64).unwrap_or(0);
+                let snapshot = file.remove("snapshot").unwrap_or_default();
+                (u32::try_from(version).unwrap_or(u32::MAX), snapshot)
+            }
+            // Saves from before the version header are a bare JSON snapshot
+            snapshot if (self, compression) == (SaveFormat::Json, SaveCompression::None) => {
+                (0, snapshot)
+            }
+            _ => return Err("not a save file".to_string()),
+        };
+        if !snapshot.is_object() {
+            return Err("not a snapshot".to_string());
+        }
+        let supported = migrations.current_version();
+        if version > supported {
+            return Err(unsupported(version, supported));
+        }
+        let mut snapshot = SnapshotData(snapshot);
+        migrations.migrate(version, &mut snapshot)?;
+        Ok(snapshot)
+    }
+
+    /// [`Format::serialize`] of the save formats.
+    fn write_save<W: Write, T: Serialize>(
+        self,
+        compression: SaveCompression,
+        writer: W,
+        value: &T,
+    ) -> Result<(), Error> {
+        let file = SaveFile {
+            version: MIGRATIONS.with_borrow(SaveMigrations::current_version),
+            snapshot: value,
+        };
+        match compression {
+            SaveCompression::None => self.write_plain(writer, &file),
+            SaveCompression::Gzip => {
+                let mut encoder = GzEncoder::new(writer, Compression::default());
+                self.write_plain(&mut encoder, &file)
+                    .and_then(|()| encoder.finish().map(drop).map_err(|e| e.to_string()))
+            }
+        }
+        .map_err(|reason| Error::saving(std::io::Error::other(reason)))
+    }
+
+    /// [`Format::deserialize`] of the save formats. Snapshots of the current version are read
+    /// straight into `seed`, older ones go through the migrations first.
+    fn read_save<R: Read, S: for<'de> DeserializeSeed<'de, Value = T>, T>(
+        self,
+        compression: SaveCompression,
+        mut reader: R,
+        seed: S,
+    ) -> Result<T, Error> {
+        let loading = |reason| Error::loading(std::io::Error::other(reason));
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).map_err(Error::loading)?;
+        let plain = decompress(&bytes, compression).map_err(loading)?;
+        let header: SaveHeader = self.decode_plain(&plain).map_err(loading)?;
+        let supported = MIGRATIONS.with_borrow(SaveMigrations::current_version);
+        if header.version > supported {
+            return Err(loading(unsupported(header.version, supported)));
+        }
+        if header.version == supported && header.has_snapshot {
+            return self
+                .decode_seed(&plain, SaveFileSeed(seed))
+                .map_err(loading);
+        }
+        let snapshot = MIGRATIONS
+            .with_borrow(|migrations| self.read_snapshot(&bytes, compression, migrations))
+            .map_err(loading)?;
+        seed.deserialize(snapshot.0).map_err(Error::loading)
+    }
+}
+
+fn unsupported(version: u32, supported: u32) -> String {
+    format!("save version {version}, this build supports up to {supported}")
+}
+
+fn decompress(bytes: &[u8], compression: SaveCompression) -> Result<Cow<'_, [u8]>, String> {
+    match compression {
+        SaveCompression::None => Ok(Cow::Borrowed(bytes)),
+        SaveCompression::Gzip => {
+            let mut buffer = Vec::new();
+            GzDecoder::new(bytes)
+                .read_to_end(&mut buffer)
+                .map_err(|e| e.to_string())?;
+            Ok(Cow::Owned(buffer))
+        }
+    }
+}
+
+fn compression(gzip: bool) -> SaveCompression {
+    if gzip {
+        SaveCompression::Gzip
+    } else {
+        SaveCompression::None
+    }
+}
+
+/// [`SaveFormat::Json`] as a bevy_save [`Format`], gzip-compressed if `GZIP`.
+pub struct JsonSaveFormat<const GZIP: bool>;
+
+impl<const GZIP: bool> Format for JsonSaveFormat<GZIP> {
+    fn extension() -> &'static str {
+        SaveFormat::Json.key_extension(compression(GZIP))
+    }
+
+    fn serialize<W: Write, T: Serialize>(writer: W, value: &T) -> Result<(), Error> {
+        SaveFormat::Json.write_save(compression(GZIP), writer, value)
+    }
+
+    fn deserialize<R: Read, S: for<'de> DeserializeSeed<'de, Value = T>, T>(
+        reader: R,
+        seed: S,
+    ) -> Result<T, Error> {
+        SaveFormat::Json.read_save(compression(GZIP), reader, seed)
+    }
+}
+
+/// [`SaveFormat::Ron`] as a bevy_save [`Format`], gzip-compressed if `GZIP`.
+pub struct RonSaveFormat<const GZIP: bool>;
+
+impl<const GZIP: bool> Format for RonSaveFormat<GZIP> {
+    fn extension() -> &'static str {
+        SaveFormat::Ron.key_extension(compression(GZIP))
+    }
+
+    fn serialize<W: Write, T: Serialize>(writer: W, value: &T) -> Result<(), Error> {
+        SaveFormat::Ron.write_save(compression(GZIP), writer, value)
+    }
+
+    fn deserialize<R: Read, S: for<'de> DeserializeSeed<'de, Value = T>, T>(
+        reader: R,
+        seed: S,
+    ) -> Result<T, Error> {
+        SaveFormat::Ron.read_save(compression(GZIP), reader, seed)
+    }
+}
+
+/// [`SaveFormat::MessagePack`] as a bevy_save [`Format`], gzip-compressed if `GZIP`.
+pub struct MessagePackSaveFormat<const GZIP: bool>;
+
+impl<const GZIP: bool> Format for MessagePackSaveFormat<GZIP> {
+    fn extension() -> &'static str {
+        SaveFormat::MessagePack.key_extension(compression(GZIP))
+    }
+
+    fn serialize<W: Write, T: Serialize>(writer: W, value: &T) -> Result<(), Error> {
+        SaveFormat::MessagePack.write_save(compression(GZIP), writer, value)
+    }
+
+    fn deserialize<R: Read, S: for<'de> DeserializeSeed<'de, Value = T>, T>(
+        reader: R,
+        seed: S,
+    ) -> Result<T, Error> {
+        SaveFormat::MessagePack.read_save(compression(GZIP), reader, seed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::save_migration::SaveMigration;
+    use serde_json::json;
+    use std::collections::BTreeMap;
+    use std::marker::PhantomData;
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    enum Shape {
+        Cube { size: f32 },
+        Sphere(f32),
+    }
+
+    /// Shaped like a snapshot: entity ids as map keys, enums and options in the components.
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    struct Snapshot {
+        entities: BTreeMap<u64, BTreeMap<String, Shape>>,
+        gravity: Option<f32>,
+    }
+
+    fn snapshot() -> Snapshot {
+        let components = |shape| BTreeMap::from([("Shape".to_string(), shape)]);
+        Snapshot {
+            entities: BTreeMap::from([
+                (4294967296, components(Shape::Cube { size: 1.0 })),
+                (4294967297, components(Shape::Sphere(0.5))),
+            ]),
+            gravity: Some(-9.81),
+        }
+    }
+
+    fn migrations(count: u32) -> SaveMigrations {
+        let mut migrations = SaveMigrations::default();
+        for from in 0..count {
+            migrations.register(
+                from,
+                SaveMigration {
+                    description: "test",
+                    migrate: |_| {},
+                },
+            );
+        }
+        migrations
+    }
+
+    fn round_trip<F: Format>() {
+        let migrations = migrations(2);
+        let mut bytes = Vec::new();
+        with_save_migrations(&migrations, || F::serialize(&mut bytes, &snapshot())).unwrap();
+        let loaded = with_save_migrations(&migrations, || {
+            F::deserialize(bytes.as_slice(), PhantomData::<Snapshot>)
+        })
+        .unwrap();
+        assert_eq!(loaded, snapshot(), "{}", F::extension());
+
+        let path = format!("slot{}", F::extension());
+        let (format, compression) = SaveFormat::from_path(Path::new(&path)).unwrap();
+        assert_eq!(format.save_version(&bytes, compression), Ok(2));
+        // An older build can't read it
+        assert!(
+            with_save_migrations(&SaveMigrations::default(), || {
+                F::deserialize(bytes.as_slice(), PhantomData::<Snapshot>)
+            })
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn json_round_trip() {
+        round_trip::<JsonSaveFormat<false>>();
+        round_trip::<JsonSaveFormat<true>>();
+    }
+
+    #[test]
+    fn ron_round_trip() {
+        round_trip::<RonSaveFormat<false>>();
+        round_trip::<RonSaveFormat<true>>();
+    }
+
+    #[test]
+    fn message_pack_round_trip() {
+        round_trip::<MessagePackSaveFormat<false>>();
+        round_trip::<MessagePackSaveFormat<true>>();
+    }
+
+    /// Older saves are read into the value tree and migrated, in every format.
+    fn migrate<F: Format>() {
+        let mut bytes = Vec::new();
+        with_save_migrations(&migrations(1), || F::serialize(&mut bytes, &snapshot())).unwrap();
+
+        let mut newer = migrations(1);
+        newer.register(
+            1,
+            SaveMigration {
+                description: "moon gravity",
+                migrate: |snapshot| {
+                    snapshot.0["gravity"] = json!(-1.62);
+                },
+            },
+        );
+        let loaded = with_save_migrations(&newer, || {
+            F::deserialize(bytes.as_slice(), PhantomData::<Snapshot>)
+        })
+        .unwrap();
+        let expected = Snapshot {
+            gravity: Some(-1.62),
+            ..snapshot()
+        };
+        assert_eq!(loaded, expected, "{}", F::extension());
+    }
+
+    #[test]
+    fn older_saves_are_migrated() {
+        migrate::<JsonSaveFormat<false>>();
+        migrate::<JsonSaveFormat<true>>();
+        migrate::<RonSaveFormat<false>>();
+        migrate::<RonSaveFormat<true>>();
+        migrate::<MessagePackSaveFormat<false>>();
+        migrate::<MessagePackSaveFormat<true>>();
+    }
+
+    fn saved_gravity<F: Format>(gravity: f32) -> Option<f32> {
+        let snapshot = Snapshot {
+            gravity: Some(gravity),
+            ..snapshot()
+        };
+        let mut bytes = Vec::new();
+        with_save_migrations(&migrations(0), || F::serialize(&mut bytes, &snapshot)).unwrap();
+        with_save_migrations(&migrations(0), || {
+            F::deserialize(bytes.as_slice(), PhantomData::<Snapshot>)
+        })
+        .unwrap()
+        .gravity
+    }
+
+    #[test]
+    fn ron_and_message_pack_keep_nan() {
+        assert!(saved_gravity::<RonSaveFormat<false>>(f32::NAN).is_some_and(f32::is_nan));
+        assert!(saved_gravity::<MessagePackSaveFormat<true>>(f32::NAN).is_some_and(f32::is_nan));
+        assert_eq!(
+            saved_gravity::<MessagePackSaveFormat<false>>(f32::INFINITY),
+            Some(f32::INFINITY)
+        );
+    }
+
+    #[test]
+    fn message_pack_is_written_directly() {
+        let bytes = rmp_serde::to_vec_named(&SaveFile {
+            version: 0,
+            snapshot: &snapshot(),
+        })
+        .unwrap();
+        let mut saved = Vec::new();
+        with_save_migrations(&migrations(0), || {
+            MessagePackSaveFormat::<false>::serialize(&mut saved, &snapshot())
+        })
+        .unwrap();
+        assert_eq!(saved, bytes);
+    }
+
+    #[test]
+    fn extensions_pick_the_format() {
+        for (extension, format, compression) in SaveFormat::file_extensions() {
+            let path = format!("saves/slot_1.{extension}");
+            assert_eq!(
+                SaveFormat::from_path(Path::new(&path)),
+                Some((format, compression))
+            );
+        }
+        assert_eq!(SaveFormat::from_path(Path::new("slot_1.meta")), None);
+    }
+
+    #[test]
+    fn headerless_json_is_version_0() {
+        let bytes = serde_json::to_vec(&json!({ "entities": { "4294967296": {} } })).unwrap();
+        assert_eq!(
+            SaveFormat::Json.save_version(&bytes, SaveCompression::None),
+            Ok(0)
+        );
+
+        let mut migrations = SaveMigrations::default();
+        migrations.register(
+            0,
+            SaveMigration {
+                description: "gravity",
+                migrate: |snapshot| {
+                    snapshot.0["gravity"] = json!(-1.62);
+                },
+            },
+        );
+        let snapshot = SaveFormat::Json
+            .read_snapshot(&bytes, SaveCompression::None, &migrations)
+            .unwrap();
+        assert_eq!(snapshot.0["gravity"], json!(-1.62));
+    }
+
+    #[test]
+    fn corrupt_files_are_errors() {
+        for format in SaveFormat::ALL {
+            assert!(
+                format
+                    .save_version(b"\x00garbage", SaveCompression::None)
+                    .is_err()
+            );
+            assert!(
+                format
+                    .read_snapshot(b"\x00garbage", SaveCompression::Gzip, &migrations(0))
+                    .is_err()
+            );
+        }
+    }
+}
@@ -0,0 +1,367 @@
+//! Upgrades snapshots written by older versions of the app, so saves keep loading as
+//! components are renamed and change shape.
+
+use bevy::prelude::*;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+/// Rewrites a snapshot from one version to the next.
+pub type SaveMigrationFn = fn(&mut SnapshotData);
+
+#[derive(Clone)]
+pub struct SaveMigration {
+    /// Logged when the migration runs.
+    pub description: &'static str,
+    pub migrate: SaveMigrationFn,
+}
+
+/// Migrations by the version they upgrade from. The current save version is one past the
+/// newest migration, saves without a version header are version `0`.
+#[derive(Resource, Default, Clone)]
+pub struct SaveMigrations {
+    migrations: BTreeMap<u32, SaveMigration>,
+}
+
+impl SaveMigrations {
+    /// Version written into new saves.
+    pub fn current_version(&self) -> u32 {
+        self.migrations
+            .last_key_value()
+            .map_or(0, |(version, _)| version + 1)
+    }
+
+    /// Registers the migration from `from_version` to `from_version + 1`.
+    pub fn register(&mut self, from_version: u32, migration: SaveMigration) {
+        if self.migrations.insert(from_version, migration).is_some() {
+            warn!("Replaced the save migration from version {from_version}");
+        }
+    }
+
+    /// Upgrades `snapshot` from `version` to [`Self::current_version`].
+    pub fn migrate(&self, version: u32, snapshot: &mut SnapshotData) -> Result<(), String> {
+        for from in version..self.current_version() {
+            let Some(migration) = self.migrations.get(&from) else {
+                return Err(format!("no migration from version {from}"));
+            };
+            info!(
+                "Migrating save from version {from} to {}: {}",
+                from + 1,
+                migration.description
+            );
+            (migration.migrate)(snapshot);
+        }
+        Ok(())
+    }
+}
+
+pub trait SaveMigrationAppExt {
+    /// Registers a migration upgrading saves from `from_version` to `from_version + 1`.
+    ///
+    /// Versions are one counter for the whole app, so apps register the migrations, not the
+    /// plugins they use.
+    fn add_save_migration(
+        &mut self,
+        from_version: u32,
+        description: &'static str,
+        migrate: SaveMigrationFn,
+    ) -> &mut Self;
+}
+
+impl SaveMigrationAppExt for App {
+    fn add_save_migration(
+        &mut self,
+        from_version: u32,
+        description: &'static str,
+        migrate: SaveMigrationFn,
+    ) -> &mut Self {
+        self.world_mut()
+            .get_resource_or_init::<SaveMigrations>()
+            .register(
+                from_version,
+                SaveMigration {
+                    description,
+                    migrate,
+                },
+            );
+        self
+    }
+}
+
+/// A serialized snapshot, with helpers for the usual migrations. Types are identified by their
+/// full type path, e.g. `bevy_drone_sim::drone_plugin::DroneBattery`.
+pub struct SnapshotData(pub Value);
+
+impl SnapshotData {
+    /// Renames a component or resource type.
+    pub fn rename_type(&mut self, from: &str, to: &str) {
+        for types in self.type_maps() {
+            if let Some(value) = types.remove(from) {
+                types.insert(to.to_string(), value);
+            }
+        }
+    }
+
+    /// Renames a field of a component or resource.
+    pub fn rename_field(&mut self, type_path: &str, from: &str, to: &str) {
+        self.map_type(type_path, |value| {
+            let Some(fields) = value.as_object_mut() else {
+                return;
+            };
+            if let Some(field) = fields.remove(from) {
+                fields.insert(to.to_string(), field);
+            }
+        });
+    }
+
+    /// Adds a field to a component or resource where it's missing.
+    pub fn add_field(&mut self, type_path: &str, field: &str, default: Value) {
+        self.map_type(type_path, |value| {
+            if let Some(fields) = value.as_object_mut() {
+                fields
+                    .entry(field.to_string())
+                    .or_insert_with(|| default.clone());
+            }
+        });
+    }
+
+    /// Adds a component to the entities having `with` but not `component` yet, for components
+    /// that became required.
+    pub fn add_component(&mut self, with: &str, component: &str, default: Value) {
+        for components in self.entity_components() {
+            if components.contains_key(with) && !components.contains_key(component) {
+                components.insert(component.to_string(), default.clone());
+            }
+        }
+    }
+
+    /// Removes a component or resource type.
+    pub fn remove_type(&mut self, type_path: &str) {
+        for types in self.type_maps() {
+            types.remove(type_path);
+        }
+    }
+
+    /// Runs `f` on the serialized value of every instance of a component or resource.
+    pub fn map_type(&mut self, type_path: &str, mut f: impl FnMut(&mut Value)) {
+        for types in self.type_maps() {
+            if let Some(value) = types.get_mut(type_path) {
+                f(value);
+            }
+        }
+    }
+
+    /// The components of every entity.
+    fn entity_components(&mut self) -> Vec<&mut Map<String, Value>> {
+        let entities: Vec<&mut Value> = match self.0.get_mut("entities") {
+            Some(Value::Object(entities)) => entities.values_mut().collect(),
+            Some(Value::Array(entities)) => entities.iter_mut().collect(),
+            _ => Vec::new(),
+        };
+        entities
+            .into_iter()
+            .filter_map(|entity| entity.get_mut("components")?.as_object_mut())
+            .collect()
+    }
+
+    /// Maps from type path to value: the resources and the components of every entity.
+    fn type_maps(&mut self) -> Vec<&mut Map<String, Value>> {
+        let Some(root) = self.0.as_object_mut() else {
+            return Vec::new();
+        };
+        let mut maps = Vec::new();
+        let mut entities = Vec::new();
+        for (key, value) in root.iter_mut() {
+            match (key.as_str(), value) {
+                ("resources", Value::Object(resources)) => maps.push(resources),
+                ("entities", Value::Object(values)) => entities.extend(values.values_mut()),
+                ("entities", Value::Array(values)) => entities.extend(values.iter_mut()),
+                _ => {}
+            }
+        }
+        maps.extend(
+            entities
+                .into_iter()
+                .filter_map(|entity| entity.get_mut("components")?.as_object_mut()),
+        );
+        maps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const BATTERY: &str = "bevy_drone_sim::drone_plugin::DroneBattery";
+    const FRAME: &str = "bevy_drone_sim::drone_plugin::DroneFrame";
+
+    /// A snapshot shaped like the ones bevy_save writes.
+    fn snapshot() -> SnapshotData {
+        SnapshotData(json!({
+            "resources": {
+                "bevy_drone_sim::Settings": { "rate": 1.0 },
+            },
+            "entities": {
+                "4294967296": {
+                    "components": {
+                        "Battery": { "voltage": 16.8 },
+                        FRAME: { "mass": 0.5 },
+                    },
+                },
+                "4294967297": {
+                    "components": {
+                        "Battery": { "voltage": 12.6 },
+                    },
+                },
+            },
+        }))
+    }
+
+    fn components<'a>(snapshot: &'a SnapshotData, entity: &str) -> &'a Value {
+        &snapshot.0["entities"][entity]["components"]
+    }
+
+    #[test]
+    fn rename_type_renames_every_instance() {
+        let mut snapshot = snapshot();
+        snapshot.rename_type("Battery", BATTERY);
+        for entity in ["4294967296", "4294967297"] {
+            assert!(components(&snapshot, entity).get("Battery").is_none());
+            assert!(components(&snapshot, entity).get(BATTERY).is_some());
+        }
+        assert_eq!(
+            components(&snapshot, "4294967297")[BATTERY]["voltage"],
+            12.6
+        );
+    }
+
+    #[test]
+    fn rename_type_renames_resources() {
+        let mut snapshot = snapshot();
+        snapshot.rename_type("bevy_drone_sim::Settings", "bevy_drone_sim::SimSettings");
+        assert_eq!(
+            snapshot.0["resources"],
+            json!({ "bevy_drone_sim::SimSettings": { "rate": 1.0 } })
+        );
+    }
+
+    #[test]
+    fn rename_field_keeps_the_value() {
+        let mut snapshot = snapshot();
+        snapshot.rename_field("Battery", "voltage", "voltage_v");
+        assert_eq!(
+            components(&snapshot, "4294967296")["Battery"],
+            json!({ "voltage_v": 16.8 })
+        );
+        assert_eq!(
+            components(&snapshot, "4294967297")["Battery"],
+            json!({ "voltage_v": 12.6 })
+        );
+    }
+
+    #[test]
+    fn rename_field_leaves_other_types_alone() {
+        let mut snapshot = snapshot();
+        snapshot.rename_field("Battery", "mass", "mass_kg");
+        assert_eq!(
+            components(&snapshot, "4294967296")[FRAME],
+            json!({ "mass": 0.5 })
+        );
+    }
+
+    #[test]
+    fn add_field_only_where_missing() {
+        let mut snapshot = snapshot();
+        snapshot.rename_field("Battery", "voltage", "cells");
+        snapshot.add_field("Battery", "voltage", json!(0.0));
+        snapshot.add_field("Battery", "cells", json!(4));
+        assert_eq!(
+            components(&snapshot, "4294967296")["Battery"],
+            json!({ "cells": 16.8, "voltage": 0.0 })
+        );
+    }
+
+    #[test]
+    fn add_component_to_entities_with_the_other() {
+        let mut snapshot = snapshot();
+        snapshot.add_component("Battery", FRAME, json!({ "mass": 1.0 }));
+        // The existing component is kept
+        assert_eq!(
+            components(&snapshot, "4294967296")[FRAME],
+            json!({ "mass": 0.5 })
+        );
+        assert_eq!(
+            components(&snapshot, "4294967297")[FRAME],
+            json!({ "mass": 1.0 })
+        );
+    }
+
+    #[test]
+    fn add_component_skips_entities_without_the_other() {
+        let mut snapshot = snapshot();
+        snapshot.add_component(FRAME, BATTERY, json!({}));
+        assert!(components(&snapshot, "4294967296").get(BATTERY).is_some());
+        assert!(components(&snapshot, "4294967297").get(BATTERY).is_none());
+    }
+
+    #[test]
+    fn entity_lists_are_migrated_too() {
+        let mut snapshot = SnapshotData(json!({
+            "entities": [
+                { "entity": 4294967296u64, "components": { "Battery": { "voltage": 16.8 } } },
+            ],
+        }));
+        snapshot.rename_type("Battery", BATTERY);
+        snapshot.add_field(BATTERY, "cell_count", json!(4));
+        assert_eq!(
+            snapshot.0["entities"][0]["components"],
+            json!({ BATTERY: { "voltage": 16.8, "cell_count": 4 } })
+        );
+    }
+
+    #[test]
+    fn migrate_runs_every_migration_from_the_version() {
+        let mut migrations = SaveMigrations::default();
+        migrations.register(
+            0,
+            SaveMigration {
+                description: "battery moved",
+                migrate: |snapshot| snapshot.rename_type("Battery", BATTERY),
+            },
+        );
+        migrations.register(
+            1,
+            SaveMigration {
+                description: "battery cells",
+                migrate: |snapshot| snapshot.add_field(BATTERY, "cell_count", json!(4)),
+            },
+        );
+        assert_eq!(migrations.current_version(), 2);
+
+        let mut old = snapshot();
+        migrations.migrate(0, &mut old).unwrap();
+        assert_eq!(
+            components(&old, "4294967297")[BATTERY],
+            json!({ "voltage": 12.6, "cell_count": 4 })
+        );
+
+        // Only the newer migration runs on version 1 saves
+        let mut newer = snapshot();
+        migrations.migrate(1, &mut newer).unwrap();
+        assert!(components(&newer, "4294967297").get(BATTERY).is_none());
+        assert!(migrations.migrate(2, &mut snapshot()).is_ok());
+    }
+
+    #[test]
+    fn migrate_fails_on_a_gap() {
+        let mut migrations = SaveMigrations::default();
+        migrations.register(
+            1,
+            SaveMigration {
+                description: "battery cells",
+                migrate: |_| {},
+            },
+        );
+        assert!(migrations.migrate(0, &mut snapshot()).is_err());
+    }
+}
@@ -1,22 +1,75 @@
+use crate::level_plugin::CurrentLevel;
+use crate::save_format::{
+    JsonSaveFormat, MessagePackSaveFormat, RonSaveFormat, SaveCompression, SaveFormat,
+    with_save_migrations,
+};
+use crate::save_migration::SaveMigrations;
+use crate::simulation_plugin::SimulationCommand;
+use crate::toast_plugin::{Toast, ToastPlugin};
+use bevy::asset::RenderAssetUsages;
+use bevy::image::{CompressedImageFormats, ImageSampler, ImageType};
+use bevy::platform::collections::HashMap;
 use bevy::prelude::*;
-use bevy_save::format::JSONFormat;
+use bevy::render::view::screenshot::{Screenshot, ScreenshotCaptured};
+use bevy_inspector_egui::bevy_egui::{EguiContexts, EguiPrimaryContextPass};
+use bevy_inspector_egui::egui;
+use bevy_save::format::Format;
 use bevy_save::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::Write;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
+/// Saves and loads the world to named slots through bevy_save.
+///
+/// Every slot keeps the save itself, metadata (name, time, level) and a screenshot thumbnail
+/// in [`SaveSlotSettings::directory`]. `F2` opens the save browser, other UI saves and loads
+/// with [`SaveSlotCommand`] events. What gets saved is up to the app's [`CaptureFlow`] and
+/// [`ApplyFlow`].
+///
+/// Save files start with a version header. Older saves are upgraded by the migrations registered
+/// with [`SaveMigrationAppExt::add_save_migration`](crate::save_migration::SaveMigrationAppExt)
+/// when they're loaded.
+///
+/// Saves are written in [`SaveSlotSettings::format`], loading picks the format by the file
+/// extension, so slots in different formats can live side by side. Every format is a bevy_save
+/// [`Format`] from [`crate::save_format`].
+///
+/// Files are written to a temporary file first and then renamed over the slot, and save files
+/// are checked before loading. Failures are logged and shown as toasts, they never panic.
 pub struct SaveSystemPlugin;
 
 impl bevy::prelude::Plugin for SaveSystemPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
+        if !app.is_plugin_added::<ToastPlugin>() {
+            app.add_plugins(ToastPlugin);
+        }
         app
             // bevy_save plugins
             .add_plugins(SavePlugins)
             // Flows
             // .add_flows(CaptureFlow, save_cube)
             // .add_flows(ApplyFlow, load_cube)
+            .init_resource::<SaveSlotSettings>()
+            .init_resource::<SaveSlots>()
+            .init_resource::<SaveBrowser>()
+            .init_resource::<SaveMigrations>()
+            .add_event::<SaveSlotCommand>()
             .register_type::<CanSaveLoad>()
+            .register_type::<SaveSlotSettings>()
+            .register_type::<SaveFormat>()
+            .register_type::<SaveCompression>()
             .add_systems(
                 bevy::prelude::Update,
-                handle_save_input, // .run_if(bevy::prelude::in_state(bevy::prelude::AppState::Running)),
-            );
+                (
+                    apply_save_slot_commands,
+                    refresh_save_slots.run_if(save_slots_need_refresh),
+                )
+                    .chain(),
+            )
+            .add_systems(EguiPrimaryContextPass, save_browser_window);
     }
 }
 
@@ -26,20 +79,105 @@ impl bevy::prelude::Plugin for SaveSystemPlugin {
 #[reflect(Component)]
 pub struct CanSaveLoad;
 
-// Pathways look very similar to pipelines, but there are a few key differences
-pub struct BasicSaveLoadPathway;
+#[derive(Resource, Reflect, Clone)]
+#[reflect(Resource)]
+pub struct SaveSlotSettings {
+    /// Directory of the save files, metadata and thumbnails.
+    pub directory: String,
+    /// Thumbnails are scaled down to fit this size, in pixels.
+    pub thumbnail_size: UVec2,
+    /// Format of new saves.
+    pub format: SaveFormat,
+    pub compression: SaveCompression,
+}
+
+impl Default for SaveSlotSettings {
+    fn default() -> Self {
+        Self {
+            directory: "saves".to_string(),
+            thumbnail_size: UVec2::new(256, 144),
+            format: SaveFormat::Json,
+            compression: SaveCompression::None,
+        }
+    }
+}
+
+/// Description of a save slot, kept next to the save as `<id>.meta.ron`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaveSlotMeta {
+    /// File name of the slot, unique in the save directory.
+    pub id: String,
+    pub name: String,
+    /// Seconds since the Unix epoch.
+    pub saved_at_secs: u64,
+    /// Asset path of the level that was loaded when saving.
+    pub level: Option<String>,
+}
 
-impl Pathway for BasicSaveLoadPathway {
+#[derive(Debug, Clone)]
+pub struct SaveSlot {
+    pub meta: SaveSlotMeta,
+    pub thumbnail: Option<Handle<Image>>,
+}
+
+/// Save slots found in the save directory, newest first.
+#[derive(Resource)]
+pub struct SaveSlots {
+    pub slots: Vec<SaveSlot>,
+    /// Re-read the save directory on the next update.
+    pub needs_refresh: bool,
+}
+
+impl Default for SaveSlots {
+    fn default() -> Self {
+        Self {
+            slots: Vec::new(),
+            needs_refresh: true,
+        }
+    }
+}
+
+#[derive(Event, Debug, Clone)]
+pub enum SaveSlotCommand {
+    /// Save to a new slot with this name.
+    Save(String),
+    /// Save over the slot with this id, keeping its name.
+    Overwrite(String),
+    Load(String),
+    Delete(String),
+}
+
+#[derive(Resource, Default)]
+struct SaveBrowser {
+    open: bool,
+    new_slot_name: String,
+}
+
+/// Saves to and loads from a single slot, in the file format `F`.
+pub struct SaveSlotPathway<F> {
+    key: String,
+    format: PhantomData<F>,
+}
+
+impl<F> SaveSlotPathway<F> {
+    pub fn new(directory: &str, slot_id: &str) -> Self {
+        Self {
+            key: format!("{directory}/{slot_id}"),
+            format: PhantomData,
+        }
+    }
+}
+
+impl<F: Format + Send + Sync + 'static> Pathway for SaveSlotPathway<F> {
     // The capture type allows you to save anything you want to disk, even without using reflection
     type Capture = Snapshot;
 
     type Backend = DefaultDebugBackend;
-    type Format = JSONFormat;
+    type Format = F;
     type Key<'a> = String;
 
     fn key(&self) -> Self::Key<'_> {
-        // TODO: Parametrize this key generation
-        "saves/save_load_demo_with_bevy_save".to_string()
+        self.key.clone()
     }
 
     // Instead of capturing and applying directly, now these methods just return labels to user-defined flows
@@ -60,14 +198,590 @@ pub struct CaptureFlow;
 #[derive(Hash, Debug, PartialEq, Eq, Clone, Copy, FlowLabel)]
 pub struct ApplyFlow;
 
-fn handle_save_input(world: &mut World) {
-    let keys = world.resource::<ButtonInput<KeyCode>>();
+/// Saves the world to the slot `id` through the pathway of `format`.
+fn save_pathway(
+    world: &mut World,
+    directory: &str,
+    id: &str,
+    format: SaveFormat,
+    compression: SaveCompression,
+) -> Result<(), bevy_save::error::Error> {
+    match (format, compression) {
+        (SaveFormat::Json, SaveCompression::None) => world.save(&SaveSlotPathway::<
+            JsonSaveFormat<false>,
+        >::new(directory, id)),
+        (SaveFormat::Json, SaveCompression::Gzip) => {
+            world.save(&SaveSlotPathway::<JsonSaveFormat<true>>::new(directory, id))
+        }
+        (SaveFormat::Ron, SaveCompression::None) => {
+            world.save(&SaveSlotPathway::<RonSaveFormat<false>>::new(directory, id))
+        }
+        (SaveFormat::Ron, SaveCompression::Gzip) => {
+            world.save(&SaveSlotPathway::<RonSaveFormat<true>>::new(directory, id))
+        }
+        (SaveFormat::MessagePack, SaveCompression::None) => {
+            world.save(&SaveSlotPathway::<MessagePackSaveFormat<false>>::new(
+                directory, id,
+            ))
+        }
+        (SaveFormat::MessagePack, SaveCompression::Gzip) => {
+            world.save(&SaveSlotPathway::<MessagePackSaveFormat<true>>::new(
+                directory, id,
+            ))
+        }
+    }
+}
+
+/// Loads the slot `id` through the pathway of `format`.
+fn load_pathway(
+    world: &mut World,
+    directory: &str,
+    id: &str,
+    format: SaveFormat,
+    compression: SaveCompression,
+) -> Result<(), bevy_save::error::Error> {
+    match (format, compression) {
+        (SaveFormat::Json, SaveCompression::None) => world.load(&SaveSlotPathway::<
+            JsonSaveFormat<false>,
+        >::new(directory, id)),
+        (SaveFormat::Json, SaveCompression::Gzip) => {
+            world.load(&SaveSlotPathway::<JsonSaveFormat<true>>::new(directory, id))
+        }
+        (SaveFormat::Ron, SaveCompression::None) => {
+            world.load(&SaveSlotPathway::<RonSaveFormat<false>>::new(directory, id))
+        }
+        (SaveFormat::Ron, SaveCompression::Gzip) => {
+            world.load(&SaveSlotPathway::<RonSaveFormat<true>>::new(directory, id))
+        }
+        (SaveFormat::MessagePack, SaveCompression::None) => {
+            world.load(&SaveSlotPathway::<MessagePackSaveFormat<false>>::new(
+                directory, id,
+            ))
+        }
+        (SaveFormat::MessagePack, SaveCompression::Gzip) => {
+            world.load(&SaveSlotPathway::<MessagePackSaveFormat<true>>::new(
+                directory, id,
+            ))
+        }
+    }
+}
+
+fn slot_file(directory: &str, id: &str, extension: &str) -> PathBuf {
+    PathBuf::from(directory).join(format!("{id}.{extension}"))
+}
+
+/// The save files of a slot, normally just one.
+fn save_files(directory: &str, id: &str) -> Vec<(PathBuf, SaveFormat, SaveCompression)> {
+    SaveFormat::file_extensions()
+        .map(|(extension, format, compression)| {
+            (slot_file(directory, id, extension), format, compression)
+        })
+        .filter(|(path, _, _)| path.exists())
+        .collect()
+}
+
+/// Why saving, loading or deleting a slot failed.
+#[derive(Debug)]
+pub enum SaveError {
+    /// There's no save in the slot.
+    NotFound { slot: String },
+    /// The save file isn't a valid save, nothing was applied.
+    Corrupt { slot: String, reason: String },
+    /// The save was written by a newer version of the app.
+    UnsupportedVersion {
+        slot: String,
+        version: u32,
+        supported: u32,
+    },
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// Capturing, serializing or applying the world failed.
+    Backend { slot: String, reason: String },
+}
+
+impl std::fmt::Display for SaveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SaveError::NotFound { slot } => write!(f, "no save in slot {slot}"),
+            SaveError::Corrupt { slot, reason } => write!(f, "slot {slot} is corrupt: {reason}"),
+            SaveError::UnsupportedVersion {
+                slot,
+                version,
+                supported,
+            } => write!(
+                f,
+                "slot {slot} has save version {version}, this build supports up to {supported}"
+            ),
+            SaveError::Io { path, source } => write!(f, "{}: {source}", path.display()),
+            SaveError::Backend { slot, reason } => write!(f, "slot {slot}: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SaveError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
 
-    if keys.just_released(KeyCode::Enter) {
-        info!("Saving data");
-        world.save(&BasicSaveLoadPathway).expect("Failed to save");
-    } else if keys.just_released(KeyCode::Backspace) {
-        info!("Loading data");
-        world.load(&BasicSaveLoadPathway).expect("Failed to load");
+fn io_error(path: &Path) -> impl FnOnce(std::io::Error) -> SaveError + '_ {
+    move |source| SaveError::Io {
+        path: path.to_path_buf(),
+        source,
     }
 }
+
+/// Replaces `path` with `temp` in one step, so readers see either the old or the new file.
+fn replace_file(temp: &Path, path: &Path) -> Result<(), SaveError> {
+    std::fs::rename(temp, path).map_err(io_error(path))
+}
+
+/// Writes through a temporary file, so a crash mid-write leaves the previous file intact.
+fn write_atomic(path: &Path, contents: &[u8]) -> Result<(), SaveError> {
+    let temp = path.with_extension("tmp");
+    let mut file = File::create(&temp).map_err(io_error(&temp))?;
+    file.write_all(contents).map_err(io_error(&temp))?;
+    file.sync_all().map_err(io_error(&temp))?;
+    replace_file(&temp, path)
+}
+
+fn write_meta(directory: &str, meta: &SaveSlotMeta) -> Result<(), SaveError> {
+    let path = slot_file(directory, &meta.id, "meta.ron");
+    let text =
+        ron::ser::to_string_pretty(meta, ron::ser::PrettyConfig::default()).map_err(|e| {
+            SaveError::Backend {
+                slot: meta.id.clone(),
+                reason: e.to_string(),
+            }
+        })?;
+    write_atomic(&path, text.as_bytes())
+}
+
+fn read_meta(path: &Path) -> Option<SaveSlotMeta> {
+    let text = std::fs::read_to_string(path)
+        .inspect_err(|e| error!("Failed to read {}: {e}", path.display()))
+        .ok()?;
+    ron::de::from_str(&text)
+        .inspect_err(|e| error!("Invalid save slot metadata {}: {e}", path.display()))
+        .ok()
+}
+
+fn apply_save_slot_commands(world: &mut World) {
+    let commands: Vec<SaveSlotCommand> = world
+        .resource_mut::<Events<SaveSlotCommand>>()
+        .drain()
+        .collect();
+    if commands.is_empty() {
+        return;
+    }
+    let settings = world.resource::<SaveSlotSettings>().clone();
+    let directory = settings.directory.as_str();
+
+    for command in commands {
+        let (result, done) = match command {
+            SaveSlotCommand::Save(name) => {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default();
+                let id = format!("slot_{}", now.as_millis());
+                let done = format!("Saved {name}");
+                (save_slot(world, &settings, id, name), done)
+            }
+            SaveSlotCommand::Overwrite(id) => {
+                let name = slot_name(world, &id);
+                let done = format!("Saved {name}");
+                (save_slot(world, &settings, id, name), done)
+            }
+            SaveSlotCommand::Load(id) => {
+                let done = format!("Loaded {}", slot_name(world, &id));
+                let result = load_slot(world, directory, &id);
+                // Loading respawns the saved entities, so the restart snapshot would point at
+                // despawned ones
+                if result.is_ok() && world.contains_resource::<Events<SimulationCommand>>() {
+                    world.send_event(SimulationCommand::CaptureSnapshot);
+                }
+                (result, done)
+            }
+            SaveSlotCommand::Delete(id) => {
+                let done = format!("Deleted {}", slot_name(world, &id));
+                (delete_slot(directory, &id), done)
+            }
+        };
+        match result {
+            Ok(()) => {
+                info!("{done}");
+                world.send_event(Toast::info(done));
+            }
+            Err(e) => {
+                error!("Save system: {e}");
+                world.send_event(Toast::error(format!("Save system: {e}")));
+            }
+        }
+    }
+    world.resource_mut::<SaveSlots>().needs_refresh = true;
+}
+
+/// Display name of a slot, its id if it isn't listed.
+fn slot_name(world: &World, id: &str) -> String {
+    world
+        .resource::<SaveSlots>()
+        .slots
+        .iter()
+        .find(|slot| slot.meta.id == id)
+        .map_or_else(|| id.to_string(), |slot| slot.meta.name.clone())
+}
+
+fn save_slot(
+    world: &mut World,
+    settings: &SaveSlotSettings,
+    id: String,
+    name: String,
+) -> Result<(), SaveError> {
+    let directory = settings.directory.as_str();
+    std::fs::create_dir_all(directory).map_err(io_error(Path::new(directory)))?;
+
+    // bevy_save writes a temporary slot, renamed over the real one once it's complete
+    let temp_id = format!("{id}.tmp");
+    let migrations = world.resource::<SaveMigrations>().clone();
+    with_save_migrations(&migrations, || {
+        save_pathway(
+            world,
+            directory,
+            &temp_id,
+            settings.format,
+            settings.compression,
+        )
+    })
+    .map_err(|e| SaveError::Backend {
+        slot: id.clone(),
+        reason: e.to_string(),
+    })?;
+    let extension = settings.format.file_extension(settings.compression);
+    let path = slot_file(directory, &id, extension);
+    replace_file(&slot_file(directory, &temp_id, extension), &path)?;
+    // Overwriting in another format leaves the old file behind
+    for (old, _, _) in save_files(directory, &id) {
+        if old != path {
+            std::fs::remove_file(&old).map_err(io_error(&old))?;
+        }
+    }
+
+    let meta = SaveSlotMeta {
+        id: id.clone(),
+        name,
+        saved_at_secs: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs(),
+        level: world
+            .get_resource::<CurrentLevel>()
+            .map(|level| level.path.clone())
+            .filter(|path| !path.is_empty()),
+    };
+    write_meta(directory, &meta)?;
+
+    // Captured at the end of this frame, after the browser closed
+    world.resource_mut::<SaveBrowser>().open = false;
+    let temp_path = slot_file(directory, &temp_id, "png");
+    let thumbnail_path = slot_file(directory, &id, "png");
+    let size = settings.thumbnail_size;
+    world.spawn(Screenshot::primary_window()).observe(
+        move |trigger: Trigger<ScreenshotCaptured>, mut slots: ResMut<SaveSlots>| {
+            let image = match trigger.event().0.clone().try_into_dynamic() {
+                Ok(image) => image,
+                Err(e) => {
+                    error!("Failed to convert the save thumbnail: {e}");
+                    return;
+                }
+            };
+            // Drop the alpha channel, it holds brightness with HDR cameras
+            let thumbnail = image.thumbnail(size.x, size.y).to_rgb8();
+            if let Err(e) = thumbnail.save(&temp_path) {
+                error!("Failed to save the thumbnail {}: {e}", temp_path.display());
+                return;
+            }
+            match replace_file(&temp_path, &thumbnail_path) {
+                Ok(()) => slots.needs_refresh = true,
+                Err(e) => error!("Failed to save the thumbnail: {e}"),
+            }
+        },
+    );
+    Ok(())
+}
+
+/// Loads a slot in whatever format it was saved in. The save file's version is checked first,
+/// and bevy_save only applies the snapshot once it's read and upgraded, so a missing, damaged
+/// or too new file is reported without touching the world.
+fn load_slot(world: &mut World, directory: &str, id: &str) -> Result<(), SaveError> {
+    let Some((path, format, compression)) = save_files(directory, id).into_iter().next() else {
+        return Err(SaveError::NotFound {
+            slot: id.to_string(),
+        });
+    };
+    let bytes = std::fs::read(&path).map_err(io_error(&path))?;
+    let version =
+        format
+            .save_version(&bytes, compression)
+            .map_err(|reason| SaveError::Corrupt {
+                slot: id.to_string(),
+                reason,
+            })?;
+    let migrations = world.resource::<SaveMigrations>().clone();
+    let supported = migrations.current_version();
+    if version > supported {
+        return Err(SaveError::UnsupportedVersion {
+            slot: id.to_string(),
+            version,
+            supported,
+        });
+    }
+
+    with_save_migrations(&migrations, || {
+        load_pathway(world, directory, id, format, compression)
+    })
+    .map_err(|e| SaveError::Backend {
+        slot: id.to_string(),
+        reason: e.to_string(),
+    })
+}
+
+fn delete_slot(directory: &str, id: &str) -> Result<(), SaveError> {
+    let saves = save_files(directory, id)
+        .into_iter()
+        .map(|(path, _, _)| path);
+    let others = ["meta.ron", "png"]
+        .map(|extension| slot_file(directory, id, extension))
+        .into_iter()
+        .filter(|path| path.exists());
+    for path in saves.chain(others) {
+        std::fs::remove_file(&path).map_err(io_error(&path))?;
+    }
+    Ok(())
+}
+
+/// Metadata for a save without any, written before saves had slots. The file name is the slot
+/// id and name, e.g. `save_load_demo_with_bevy_save.json`.
+fn legacy_meta(path: &Path) -> Option<SaveSlotMeta> {
+    let (format, compression) = SaveFormat::from_path(path)?;
+    let file_name = path.file_name()?.to_str()?;
+    let id = file_name.strip_suffix(&format!(".{}", format.file_extension(compression)))?;
+    // Slot metadata and temporary files
+    if id.contains('.') {
+        return None;
+    }
+    let saved_at_secs = std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .unwrap_or_default()
+        .as_secs();
+    Some(SaveSlotMeta {
+        id: id.to_string(),
+        name: id.to_string(),
+        saved_at_secs,
+        level: None,
+    })
+}
+
+fn load_thumbnail(path: &Path) -> Option<Image> {
+    let bytes = std::fs::read(path).ok()?;
+    Image::from_buffer(
+        &bytes,
+        ImageType::Extension("png"),
+        CompressedImageFormats::NONE,
+        true,
+        ImageSampler::Default,
+        RenderAssetUsages::RENDER_WORLD,
+    )
+    .inspect_err(|e| error!("Invalid save thumbnail {}: {e}", path.display()))
+    .ok()
+}
+
+fn save_slots_need_refresh(slots: Res<SaveSlots>) -> bool {
+    slots.needs_refresh
+}
+
+fn refresh_save_slots(
+    settings: Res<SaveSlotSettings>,
+    mut slots: ResMut<SaveSlots>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    slots.needs_refresh = false;
+    let Ok(entries) = std::fs::read_dir(&settings.directory) else {
+        debug!("No save directory {}.", settings.directory);
+        slots.slots.clear();
+        return;
+    };
+
+    // Reuse the thumbnail handles so the browser keeps its textures
+    let mut thumbnails: HashMap<String, Handle<Image>> = slots
+        .slots
+        .drain(..)
+        .filter_map(|slot| Some((slot.meta.id, slot.thumbnail?)))
+        .collect();
+
+    let paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .collect();
+    let mut metas: Vec<SaveSlotMeta> = paths
+        .iter()
+        .filter(|path| path.to_string_lossy().ends_with(".meta.ron"))
+        .filter_map(|path| read_meta(path))
+        .collect();
+    // Saves without metadata are listed too, so they can be loaded and migrated
+    let legacy: Vec<SaveSlotMeta> = paths
+        .iter()
+        .filter_map(|path| legacy_meta(path))
+        .filter(|legacy| !metas.iter().any(|meta| meta.id == legacy.id))
+        .collect();
+    metas.extend(legacy);
+
+    let mut found: Vec<SaveSlot> =
+        metas
+            .into_iter()
+            .map(|meta| {
+                let thumbnail = load_thumbnail(&slot_file(&settings.directory, &meta.id, "png"))
+                    .map(|image| match thumbnails.remove(&meta.id) {
+                        Some(handle) => {
+                            images.insert(&handle, image);
+                            handle
+                        }
+                        None => images.add(image),
+                    });
+                SaveSlot { meta, thumbnail }
+            })
+            .collect();
+    found.sort_by(|a, b| b.meta.saved_at_secs.cmp(&a.meta.saved_at_secs));
+    slots.slots = found;
+}
+
+/// `3 min ago`, `2 h ago`, ...
+fn format_age(saved_at_secs: u64) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let age = now.saturating_sub(saved_at_secs);
+    match age {
+        0..60 => "just now".to_string(),
+        60..3600 => format!("{} min ago", age / 60),
+        3600..86400 => format!("{} h ago", age / 3600),
+        _ => format!("{} days ago", age / 86400),
+    }
+}
+
+fn save_browser_window(
+    mut contexts: EguiContexts,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    slots: Res<SaveSlots>,
+    mut settings: ResMut<SaveSlotSettings>,
+    mut browser: ResMut<SaveBrowser>,
+    mut commands: EventWriter<SaveSlotCommand>,
+) {
+    // Register the thumbnails before borrowing the context
+    let thumbnails: Vec<Option<egui::TextureId>> = slots
+        .slots
+        .iter()
+        .map(|slot| {
+            slot.thumbnail
+                .as_ref()
+                .map(|handle| contexts.add_image(handle.clone()))
+        })
+        .collect();
+
+    let Ok(ctx) = contexts.ctx_mut() else {
+        debug!("No Egui context found for the save browser.");
+        return;
+    };
+    if keyboard.just_pressed(KeyCode::F2) && !ctx.wants_keyboard_input() {
+        browser.open = !browser.open;
+    }
+    if !browser.open {
+        return;
+    }
+
+    let mut still_open = true;
+    egui::Window::new("Saves")
+        .open(&mut still_open)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.add(egui::TextEdit::singleline(&mut browser.new_slot_name).hint_text("Name"));
+                let name = browser.new_slot_name.trim().to_string();
+                if ui
+                    .add_enabled(!name.is_empty(), egui::Button::new("Save new"))
+                    .clicked()
+                {
+                    commands.write(SaveSlotCommand::Save(name));
+                    browser.new_slot_name.clear();
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Format");
+                egui::ComboBox::from_id_salt("save_format")
+                    .selected_text(format!("{:?}", settings.format))
+                    .show_ui(ui, |ui| {
+                        for format in SaveFormat::ALL {
+                            ui.selectable_value(
+                                &mut settings.format,
+                                format,
+                                format!("{format:?}"),
+                            );
+                        }
+                    });
+                let mut gzip = settings.compression == SaveCompression::Gzip;
+                if ui.checkbox(&mut gzip, "Gzip").changed() {
+                    settings.compression = if gzip {
+                        SaveCompression::Gzip
+                    } else {
+                        SaveCompression::None
+                    };
+                }
+            });
+            ui.separator();
+
+            if slots.slots.is_empty() {
+                ui.label("No saves yet.");
+            }
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                for (slot, thumbnail) in slots.slots.iter().zip(&thumbnails) {
+                    ui.horizontal(|ui| {
+                        let size = egui::vec2(128.0, 72.0);
+                        match thumbnail {
+                            Some(texture) => {
+                                ui.image(egui::load::SizedTexture::new(*texture, size));
+                            }
+                            None => {
+                                ui.allocate_space(size);
+                            }
+                        }
+                        ui.vertical(|ui| {
+                            ui.strong(&slot.meta.name);
+                            ui.label(format_age(slot.meta.saved_at_secs));
+                            if let Some(level) = &slot.meta.level {
+                                ui.small(level);
+                            }
+                            ui.horizontal(|ui| {
+                                if ui.button("Load").clicked() {
+                                    commands.write(SaveSlotCommand::Load(slot.meta.id.clone()));
+                                }
+                                if ui.button("Overwrite").clicked() {
+                                    commands
+                                        .write(SaveSlotCommand::Overwrite(slot.meta.id.clone()));
+                                }
+                                if ui.button("Delete").clicked() {
+                                    commands.write(SaveSlotCommand::Delete(slot.meta.id.clone()));
+                                }
+                            });
+                        });
+                    });
+                }
+            });
+        });
+    browser.open &= still_open;
+}
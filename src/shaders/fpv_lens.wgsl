@@ -0,0 +1,69 @@
+// Shows the FPV camera feed through a barrel-distorting (fisheye-like) lens, with the
+// artefacts of an analog video link on top.
+
+#import bevy_ui::ui_vertex_output::UiVertexOutput
+
+struct FpvLens {
+    // Barrel distortion coefficient, 0 keeps the image rectilinear.
+    distortion: f32,
+}
+
+struct AnalogVideo {
+    noise: f32,
+    breakup: f32,
+    color_bleed: f32,
+    time: f32,
+}
+
+@group(1) @binding(0) var<uniform> lens: FpvLens;
+@group(1) @binding(1) var feed_texture: texture_2d<f32>;
+@group(1) @binding(2) var feed_sampler: sampler;
+@group(1) @binding(3) var<uniform> video: AnalogVideo;
+
+// Analog receivers show about this many lines.
+const LINES: f32 = 480.0;
+
+fn hash(p: vec2<f32>) -> f32 {
+    return fract(sin(dot(p, vec2(12.9898, 78.233))) * 43758.5453);
+}
+
+fn lens_uv(uv: vec2<f32>, size: vec2<f32>) -> vec2<f32> {
+    let aspect = size.x / max(size.y, 1.0);
+    let centered = uv * 2.0 - 1.0;
+    let point = vec2(centered.x * aspect, centered.y);
+    let corner = aspect * aspect + 1.0;
+
+    // Sample further out towards the edges, scaled so the corners stay at the corners
+    let scale = (1.0 + lens.distortion * dot(point, point)) / (1.0 + lens.distortion * corner);
+    return centered * scale * 0.5 + 0.5;
+}
+
+@fragment
+fn fragment(in: UiVertexOutput) -> @location(0) vec4<f32> {
+    var uv = lens_uv(in.uv, in.size);
+    let line = floor(uv.y * LINES);
+    let frame = floor(video.time * 30.0);
+
+    // Breakup tears whole lines sideways and drops some of them to static
+    let tear = hash(vec2(line, frame)) - 0.5;
+    uv.x += tear * 0.2 * video.breakup * video.breakup;
+    let dropout = step(1.0 - video.breakup * 0.5, hash(vec2(frame, line * 0.37)));
+
+    // Colour bleed smears the chroma to the right of the luma
+    let luma_sample = textureSample(feed_texture, feed_sampler, uv).rgb;
+    let chroma_sample = textureSample(feed_texture, feed_sampler, uv - vec2(video.color_bleed, 0.0)).rgb;
+    let luma_weights = vec3(0.299, 0.587, 0.114);
+    let luma = dot(luma_sample, luma_weights);
+    var color = chroma_sample - vec3(dot(chroma_sample, luma_weights)) + vec3(luma);
+
+    // Rolling lines: a brighter band drifting down the picture
+    let band = fract(uv.y * 0.5 - video.time * 0.25);
+    color += vec3(smoothstep(0.9, 1.0, band) * 0.15 * video.noise);
+
+    // Static grows with the noise level
+    let grain = hash(in.uv * in.size + vec2(video.time * 113.0, video.time * 71.0));
+    color = mix(color, vec3(grain), clamp(video.noise * video.noise, 0.0, 1.0));
+    color = mix(color, vec3(grain), dropout);
+
+    return vec4(clamp(color, vec3(0.0), vec3(1.0)), 1.0);
+}
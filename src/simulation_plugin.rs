@@ -0,0 +1,362 @@
+use crate::drone_plugin::{DroneBattery, DroneKinematics, DronePosition, MotorOutputs};
+use crate::flight_controller_plugin::FlightController;
+use avian3d::prelude::{AngularVelocity, LinearVelocity, Physics, PhysicsTime};
+use bevy::app::FixedMain;
+use bevy::prelude::*;
+use bevy::scene::SceneFilter;
+use bevy_rapier3d::prelude::{RapierConfiguration, TimestepMode, Velocity};
+
+/// Time scales `SimulationBindings::slower` and `faster` step through.
+const TIME_SCALES: [f32; 7] = [0.1, 0.25, 0.5, 1.0, 1.5, 2.0, 4.0];
+
+/// Play, pause, single-step, slow motion and restart of the simulation.
+///
+/// Pausing stops virtual time, which stops `FixedUpdate` and everything driven by it, and the
+/// Avian and Rapier physics time when those engines are present. Restart puts every level
+/// entity back to the snapshot taken after startup. Keys are set in [`SimulationBindings`],
+/// other UI drives the simulation with [`SimulationCommand`] events.
+pub struct SimulationPlugin;
+
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_state::<SimulationState>()
+            .init_resource::<SimulationSettings>()
+            .init_resource::<SimulationBindings>()
+            .init_resource::<RestartComponents>()
+            .add_event::<SimulationCommand>()
+            .add_systems(Startup, setup_hud)
+            .add_systems(PostStartup, capture_restart_snapshot)
+            .add_systems(OnEnter(SimulationState::Running), apply_time_control)
+            .add_systems(OnEnter(SimulationState::Paused), apply_time_control)
+            .add_systems(
+                OnEnter(SimulationState::SingleStep),
+                (apply_time_control, step_fixed_schedule).chain(),
+            )
+            .add_systems(
+                Update,
+                (
+                    send_simulation_commands.in_set(SimulationInputSet),
+                    apply_simulation_commands,
+                    apply_time_control.run_if(resource_changed::<SimulationSettings>),
+                    update_hud,
+                )
+                    .chain(),
+            )
+            .register_type::<SimulationSettings>()
+            .register_type::<SimulationBindings>();
+    }
+}
+
+/// Reads the simulation keys during `Update`, apps gate it to keep them away from menus.
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SimulationInputSet;
+
+#[derive(States, Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SimulationState {
+    #[default]
+    Running,
+    Paused,
+    /// Paused, advancing a single fixed timestep before going back to `Paused`.
+    SingleStep,
+}
+
+#[derive(Resource, Reflect, Clone)]
+#[reflect(Resource)]
+pub struct SimulationSettings {
+    /// Simulated seconds per real second, `0.1` to `4`.
+    pub time_scale: f32,
+}
+
+impl Default for SimulationSettings {
+    fn default() -> Self {
+        Self { time_scale: 1.0 }
+    }
+}
+
+/// Keys of the simulation controls.
+#[derive(Resource, Reflect, Clone)]
+#[reflect(Resource)]
+pub struct SimulationBindings {
+    pub toggle_pause: KeyCode,
+    pub step: KeyCode,
+    pub restart: KeyCode,
+    pub slower: KeyCode,
+    pub faster: KeyCode,
+}
+
+impl Default for SimulationBindings {
+    fn default() -> Self {
+        Self {
+            toggle_pause: KeyCode::F7,
+            step: KeyCode::F8,
+            restart: KeyCode::F9,
+            slower: KeyCode::BracketLeft,
+            faster: KeyCode::BracketRight,
+        }
+    }
+}
+
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub enum SimulationCommand {
+    TogglePause,
+    Pause,
+    Resume,
+    /// Advance one fixed timestep, pausing first if running.
+    Step,
+    /// Reset the level to its restart snapshot.
+    Restart,
+    /// Take a new restart snapshot, e.g. after a level finished loading.
+    CaptureSnapshot,
+    SetTimeScale(f32),
+}
+
+/// Components saved in the restart snapshot. Transforms, the drone state and rigid body
+/// velocities of both physics engines by default.
+#[derive(Resource, Clone)]
+pub struct RestartComponents(pub SceneFilter);
+
+impl Default for RestartComponents {
+    fn default() -> Self {
+        Self(
+            SceneFilter::deny_all()
+                .allow::<Transform>()
+                .allow::<DronePosition>()
+                .allow::<DroneKinematics>()
+                .allow::<DroneBattery>()
+                .allow::<MotorOutputs>()
+                .allow::<FlightController>()
+                .allow::<LinearVelocity>()
+                .allow::<AngularVelocity>()
+                .allow::<Velocity>(),
+        )
+    }
+}
+
+/// State of the level entities to restart from.
+#[derive(Resource)]
+pub struct RestartSnapshot(pub DynamicScene);
+
+/// Shows the simulation state and time scale.
+#[derive(Component)]
+struct SimulationHud;
+
+fn setup_hud(mut commands: Commands) {
+    commands.spawn((
+        Name::new("Simulation HUD"),
+        Node {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.),
+            top: Val::Px(5.),
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        children![(SimulationHud, Text::new(""))],
+    ));
+}
+
+fn update_hud(
+    state: Res<State<SimulationState>>,
+    settings: Res<SimulationSettings>,
+    bindings: Res<SimulationBindings>,
+    mut hud: Query<&mut Text, With<SimulationHud>>,
+) {
+    if !state.is_changed() && !settings.is_changed() {
+        return;
+    }
+    let Ok(mut text) = hud.single_mut() else {
+        debug!("No simulation HUD found.");
+        return;
+    };
+    text.0 = match state.get() {
+        SimulationState::Running if settings.time_scale == 1.0 => String::new(),
+        SimulationState::Running => format!("{:.2}x", settings.time_scale),
+        SimulationState::Paused | SimulationState::SingleStep => format!(
+            "PAUSED {:.2}x - {:?} resume, {:?} step, {:?} restart",
+            settings.time_scale, bindings.toggle_pause, bindings.step, bindings.restart
+        ),
+    };
+}
+
+fn send_simulation_commands(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    bindings: Res<SimulationBindings>,
+    settings: Res<SimulationSettings>,
+    mut commands: EventWriter<SimulationCommand>,
+) {
+    if keyboard.just_pressed(bindings.toggle_pause) {
+        commands.write(SimulationCommand::TogglePause);
+    }
+    if keyboard.just_pressed(bindings.step) {
+        commands.write(SimulationCommand::Step);
+    }
+    if keyboard.just_pressed(bindings.restart) {
+        commands.write(SimulationCommand::Restart);
+    }
+
+    let current = TIME_SCALES
+        .iter()
+        .position(|&scale| scale >= settings.time_scale)
+        .unwrap_or(TIME_SCALES.len() - 1);
+    if keyboard.just_pressed(bindings.slower) {
+        commands.write(SimulationCommand::SetTimeScale(
+            TIME_SCALES[current.saturating_sub(1)],
+        ));
+    }
+    if keyboard.just_pressed(bindings.faster) {
+        commands.write(SimulationCommand::SetTimeScale(
+            TIME_SCALES[(current + 1).min(TIME_SCALES.len() - 1)],
+        ));
+    }
+}
+
+fn apply_simulation_commands(
+    mut commands: Commands,
+    mut events: EventReader<SimulationCommand>,
+    state: Res<State<SimulationState>>,
+    mut next_state: ResMut<NextState<SimulationState>>,
+    mut settings: ResMut<SimulationSettings>,
+) {
+    for command in events.read() {
+        match *command {
+            SimulationCommand::TogglePause => {
+                let paused = *state.get() != SimulationState::Running;
+                next_state.set(if paused {
+                    SimulationState::Running
+                } else {
+                    SimulationState::Paused
+                });
+                info!("Simulation {}", if paused { "resumed" } else { "paused" });
+            }
+            SimulationCommand::Pause => next_state.set(SimulationState::Paused),
+            SimulationCommand::Resume => next_state.set(SimulationState::Running),
+            // Already stepping, the step ends paused this frame
+            SimulationCommand::Step if *state.get() == SimulationState::SingleStep => {}
+            SimulationCommand::Step => next_state.set(SimulationState::SingleStep),
+            SimulationCommand::Restart => commands.run_system_cached(restore_restart_snapshot),
+            SimulationCommand::CaptureSnapshot => {
+                commands.run_system_cached(capture_restart_snapshot)
+            }
+            SimulationCommand::SetTimeScale(scale) => {
+                settings.time_scale =
+                    scale.clamp(TIME_SCALES[0], TIME_SCALES[TIME_SCALES.len() - 1]);
+                info!("Simulation time scale: {:.2}x", settings.time_scale);
+            }
+        }
+    }
+}
+
+/// Points virtual time and the physics engines at the simulation state and time scale.
+fn apply_time_control(
+    state: Res<State<SimulationState>>,
+    settings: Res<SimulationSettings>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+    fixed_time: Res<Time<Fixed>>,
+    avian_time: Option<ResMut<Time<Physics>>>,
+    rapier_timestep: Option<ResMut<TimestepMode>>,
+    mut rapier_configs: Query<&mut RapierConfiguration>,
+) {
+    let state = *state.get();
+    let running = state == SimulationState::Running;
+    if running {
+        virtual_time.unpause();
+    } else {
+        virtual_time.pause();
+    }
+    virtual_time.set_relative_speed(settings.time_scale);
+
+    // Avian steps in the fixed schedule, its own clock must only run when stepping
+    if let Some(mut avian_time) = avian_time {
+        if state == SimulationState::Paused {
+            avian_time.pause();
+        } else {
+            avian_time.unpause();
+        }
+    }
+
+    // Rapier steps every frame with the frame time, which is zero while paused. A single step
+    // switches it to one fixed timestep instead.
+    for mut config in rapier_configs.iter_mut() {
+        config.physics_pipeline_active = state != SimulationState::Paused;
+    }
+    if let Some(mut timestep) = rapier_timestep {
+        *timestep = match state {
+            SimulationState::SingleStep => TimestepMode::Fixed {
+                dt: fixed_time.timestep().as_secs_f32(),
+                substeps: 1,
+            },
+            // Let fast forward take frames longer than Rapier's default limit
+            _ => TimestepMode::Variable {
+                max_dt: settings.time_scale.max(1.0) / 60.0,
+                time_scale: 1.0,
+                substeps: 1,
+            },
+        };
+    }
+}
+
+/// Runs `FixedMain` once while virtual time is paused, then pauses again.
+fn step_fixed_schedule(world: &mut World) {
+    let mut fixed_time = world.resource_mut::<Time<Fixed>>();
+    let timestep = fixed_time.timestep();
+    fixed_time.advance_by(timestep);
+    *world.resource_mut::<Time>() = world.resource::<Time<Fixed>>().as_generic();
+    world.run_schedule(FixedMain);
+    *world.resource_mut::<Time>() = world.resource::<Time<Virtual>>().as_generic();
+
+    world
+        .resource_mut::<NextState<SimulationState>>()
+        .set(SimulationState::Paused);
+    debug!("Simulation stepped by {timestep:?}");
+}
+
+/// Snapshots every level entity, cameras and UI aside.
+fn capture_restart_snapshot(world: &mut World) {
+    let filter = world.resource::<RestartComponents>().0.clone();
+    let entities: Vec<Entity> = world
+        .query_filtered::<Entity, (With<Transform>, Without<Camera>, Without<Node>)>()
+        .iter(world)
+        .collect();
+    let scene = DynamicSceneBuilder::from_world(world)
+        .with_component_filter(filter)
+        .deny_all_resources()
+        .extract_entities(entities.into_iter())
+        .build();
+    info!(
+        "Captured the restart snapshot of {} entities",
+        scene.entities.len()
+    );
+    world.insert_resource(RestartSnapshot(scene));
+}
+
+/// Writes the restart snapshot back onto the entities it was taken from. Entities despawned
+/// since are skipped, entities spawned since are left alone.
+fn restore_restart_snapshot(world: &mut World) {
+    let Some(snapshot) = world.remove_resource::<RestartSnapshot>() else {
+        warn!("No restart snapshot to restart from.");
+        return;
+    };
+    let registry = world.resource::<AppTypeRegistry>().clone();
+    let registry = registry.read();
+
+    let mut restored = 0;
+    for snapshot_entity in &snapshot.0.entities {
+        let Ok(mut entity) = world.get_entity_mut(snapshot_entity.entity) else {
+            continue;
+        };
+        for component in &snapshot_entity.components {
+            let Some(reflect_component) = component
+                .get_represented_type_info()
+                .and_then(|info| registry.get(info.type_id()))
+                .and_then(|registration| registration.data::<ReflectComponent>())
+            else {
+                continue;
+            };
+            reflect_component.insert(&mut entity, component.as_partial_reflect(), &registry);
+        }
+        restored += 1;
+    }
+    drop(registry);
+
+    world.insert_resource(snapshot);
+    info!("Level restarted, {restored} entities restored");
+}
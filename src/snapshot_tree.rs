@@ -0,0 +1,585 @@
+//! Reads save files into the JSON value tree that [`SnapshotData`](crate::save_migration::SnapshotData)
+//! migrations edit, shaped like the JSON file of the same snapshot whatever the format.
+//!
+//! Map keys become strings, as JSON has no other keys, and the serde data model's enums are
+//! externally tagged, `{"Variant": value}`. JSON can't hold NaN or infinities, they become
+//! `null`.
+
+use serde::de::{DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde_json::{Map, Number, Value};
+use std::fmt;
+
+/// Reads a self-describing format, e.g. MessagePack, into the tree.
+pub fn from_self_describing<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Value, D::Error> {
+    TreeSeed.deserialize(deserializer)
+}
+
+/// Reads RON into the tree. RON isn't read through serde, which drops enum variant names on
+/// self-describing reads, so this parses the text itself.
+pub fn from_ron(text: &str) -> Result<Value, String> {
+    let mut parser = RonParser {
+        bytes: text.as_bytes(),
+        pos: 0,
+    };
+    parser.skip_attributes()?;
+    let value = parser.value()?;
+    parser.skip_whitespace()?;
+    if parser.pos < parser.bytes.len() {
+        return Err(parser.error("trailing characters"));
+    }
+    Ok(value)
+}
+
+fn float(value: f64) -> Value {
+    Number::from_f64(value).map_or(Value::Null, Value::Number)
+}
+
+/// A map key as a string, `4294967296: ...` is `"4294967296"`.
+fn key_string(key: Value) -> String {
+    match key {
+        Value::String(key) => key,
+        key => key.to_string(),
+    }
+}
+
+struct TreeSeed;
+
+impl<'de> DeserializeSeed<'de> for TreeSeed {
+    type Value = Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for TreeSeed {
+    type Value = Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("any value")
+    }
+
+    fn visit_bool<E>(self, value: bool) -> Result<Value, E> {
+        Ok(Value::Bool(value))
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Value, E> {
+        Ok(Value::from(value))
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<Value, E> {
+        Ok(Value::from(value))
+    }
+
+    fn visit_f64<E>(self, value: f64) -> Result<Value, E> {
+        Ok(float(value))
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Value, E> {
+        Ok(Value::from(value))
+    }
+
+    fn visit_bytes<E>(self, value: &[u8]) -> Result<Value, E> {
+        Ok(Value::from(value))
+    }
+
+    fn visit_none<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_unit<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        self.deserialize(deserializer)
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Value, D::Error> {
+        self.deserialize(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut values = Vec::new();
+        while let Some(value) = seq.next_element_seed(TreeSeed)? {
+            values.push(value);
+        }
+        Ok(Value::Array(values))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut values = Map::new();
+        while let Some((key, value)) = map.next_entry_seed(TreeSeed, TreeSeed)? {
+            values.insert(key_string(key), value);
+        }
+        Ok(Value::Object(values))
+    }
+}
+
+struct RonParser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl RonParser<'_> {
+    fn error(&self, reason: &str) -> String {
+        let line = 1 + self.bytes[..self.pos]
+            .iter()
+            .filter(|&&b| b == b'\n')
+            .count();
+        format!("{reason} on line {line}")
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<u8> {
+        self.bytes.get(self.pos + offset).copied()
+    }
+
+    fn skip_whitespace(&mut self) -> Result<(), String> {
+        loop {
+            match (self.peek(), self.peek_at(1)) {
+                (Some(b), _) if b.is_ascii_whitespace() => self.pos += 1,
+                (Some(b'/'), Some(b'/')) => {
+                    while self.peek().is_some_and(|b| b != b'\n') {
+                        self.pos += 1;
+                    }
+                }
+                (Some(b'/'), Some(b'*')) => {
+                    let end = self.bytes[self.pos + 2..]
+                        .windows(2)
+                        .position(|window| window == b"*/")
+                        .ok_or_else(|| self.error("unterminated comment"))?;
+                    self.pos += end + 4;
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    /// Skips `#![enable(...)]` attributes at the start of the file.
+    fn skip_attributes(&mut self) -> Result<(), String> {
+        loop {
+            self.skip_whitespace()?;
+            if self.peek() != Some(b'#') {
+                return Ok(());
+            }
+            let end = self.bytes[self.pos..]
+                .iter()
+                .position(|&b| b == b']')
+                .ok_or_else(|| self.error("unterminated attribute"))?;
+            self.pos += end + 1;
+        }
+    }
+
+    fn eat(&mut self, byte: u8) -> Result<bool, String> {
+        self.skip_whitespace()?;
+        let eaten = self.peek() == Some(byte);
+        if eaten {
+            self.pos += 1;
+        }
+        Ok(eaten)
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), String> {
+        if self.eat(byte)? {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected `{}`", byte as char)))
+        }
+    }
+
+    fn identifier(&mut self) -> Option<String> {
+        let start = self.pos;
+        if !self
+            .peek()
+            .is_some_and(|b| b.is_ascii_alphabetic() || b == b'_')
+        {
+            return None;
+        }
+        while self
+            .peek()
+            .is_some_and(|b| b.is_ascii_alphanumeric() || b == b'_')
+        {
+            self.pos += 1;
+        }
+        Some(String::from_utf8_lossy(&self.bytes[start..self.pos]).into_owned())
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        self.skip_whitespace()?;
+        match (self.peek(), self.peek_at(1)) {
+            (None, _) => Err(self.error("unexpected end")),
+            (Some(b'"'), _) => self.string().map(Value::String),
+            (Some(b'r'), Some(b'"' | b'#')) => self.raw_string().map(Value::String),
+            (Some(b'b'), Some(b'"')) => {
+                self.pos += 1;
+                self.string().map(|text| Value::from(text.as_bytes()))
+            }
+            (Some(b'\''), _) => self.char().map(|c| Value::String(c.to_string())),
+            (Some(b'['), _) => {
+                self.pos += 1;
+                let mut values = Vec::new();
+                while !self.eat(b']')? {
+                    values.push(self.value()?);
+                    if !self.eat(b',')? {
+                        self.expect(b']')?;
+                        break;
+                    }
+                }
+                Ok(Value::Array(values))
+            }
+            (Some(b'{'), _) => {
+                self.pos += 1;
+                let mut values = Map::new();
+                while !self.eat(b'}')? {
+                    let key = key_string(self.value()?);
+                    self.expect(b':')?;
+                    values.insert(key, self.value()?);
+                    if !self.eat(b',')? {
+                        self.expect(b'}')?;
+                        break;
+                    }
+                }
+                Ok(Value::Object(values))
+            }
+            (Some(b'('), _) => self.group(),
+            (Some(b'0'..=b'9' | b'+' | b'-' | b'.'), _) => self.number(),
+            _ => {
+                let Some(identifier) = self.identifier() else {
+                    return Err(self.error("unexpected character"));
+                };
+                match identifier.as_str() {
+                    "true" => Ok(Value::Bool(true)),
+                    "false" => Ok(Value::Bool(false)),
+                    "None" => Ok(Value::Null),
+                    "inf" | "NaN" => Ok(Value::Null),
+                    "Some" => {
+                        self.expect(b'(')?;
+                        let value = self.value()?;
+                        self.eat(b',')?;
+                        self.expect(b')')?;
+                        Ok(value)
+                    }
+                    _ => {
+                        self.skip_whitespace()?;
+                        if self.peek() == Some(b'(') {
+                            // Struct names aren't written, so this is an enum variant
+                            let value = self.group()?;
+                            Ok(Value::Object(Map::from_iter([(identifier, value)])))
+                        } else {
+                            Ok(Value::String(identifier))
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// `(...)`: a struct with named fields, a tuple, or the one value of a newtype.
+    fn group(&mut self) -> Result<Value, String> {
+        self.expect(b'(')?;
+        if self.eat(b')')? {
+            return Ok(Value::Null);
+        }
+        self.skip_whitespace()?;
+        let start = self.pos;
+        let named = self.identifier().is_some() && {
+            self.skip_whitespace()?;
+            self.peek() == Some(b':')
+        };
+        self.pos = start;
+
+        if named {
+            let mut fields = Map::new();
+            while !self.eat(b')')? {
+                self.skip_whitespace()?;
+                let Some(name) = self.identifier() else {
+                    return Err(self.error("expected a field name"));
+                };
+                self.expect(b':')?;
+                fields.insert(name, self.value()?);
+                if !self.eat(b',')? {
+                    self.expect(b')')?;
+                    break;
+                }
+            }
+            return Ok(Value::Object(fields));
+        }
+
+        let mut values = Vec::new();
+        while !self.eat(b')')? {
+            values.push(self.value()?);
+            if !self.eat(b',')? {
+                self.expect(b')')?;
+                break;
+            }
+        }
+        // Newtypes are written in parentheses, JSON has just the value
+        Ok(match <[Value; 1]>::try_from(values) {
+            Ok([value]) => value,
+            Err(values) => Value::Array(values),
+        })
+    }
+
+    fn number(&mut self) -> Result<Value, String> {
+        let start = self.pos;
+        if matches!(self.peek(), Some(b'+' | b'-')) {
+            self.pos += 1;
+        }
+        if self.identifier().is_some_and(|name| name == "inf") {
+            return Ok(Value::Null);
+        }
+        self.pos = start;
+        while self
+            .peek()
+            .is_some_and(|b| b.is_ascii_alphanumeric() || matches!(b, b'+' | b'-' | b'.' | b'_'))
+        {
+            // A sign only follows an exponent
+            if matches!(self.peek(), Some(b'+' | b'-'))
+                && self.pos > start
+                && !matches!(self.bytes[self.pos - 1], b'e' | b'E')
+            {
+                break;
+            }
+            self.pos += 1;
+        }
+        let text: String = std::str::from_utf8(&self.bytes[start..self.pos])
+            .unwrap_or_default()
+            .chars()
+            .filter(|&c| c != '_')
+            .collect();
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, text.strip_prefix('+').unwrap_or(&text)),
+        };
+        let radix = match digits.get(..2) {
+            Some("0x") => Some(16),
+            Some("0o") => Some(8),
+            Some("0b") => Some(2),
+            _ => None,
+        };
+        let value = match radix {
+            Some(radix) => u64::from_str_radix(&digits[2..], radix)
+                .ok()
+                .map(|value| signed(value, negative)),
+            None if digits.contains(['.', 'e', 'E']) => text.parse::<f64>().ok().map(float),
+            None => text
+                .parse::<i64>()
+                .map(Value::from)
+                .or_else(|_| text.parse::<u64>().map(Value::from))
+                .ok(),
+        };
+        value.ok_or_else(|| self.error(&format!("invalid number `{text}`")))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.pos += 1;
+        let mut text = Vec::new();
+        loop {
+            match self.peek() {
+                None => return Err(self.error("unterminated string")),
+                Some(b'"') => {
+                    self.pos += 1;
+                    return String::from_utf8(text).map_err(|_| self.error("invalid UTF-8"));
+                }
+                Some(b'\\') => {
+                    self.pos += 1;
+                    let c = self.escape()?;
+                    text.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                }
+                Some(b) => {
+                    text.push(b);
+                    self.pos += 1;
+                }
+            }
+        }
+    }
+
+    fn raw_string(&mut self) -> Result<String, String> {
+        self.pos += 1;
+        let hashes = self.bytes[self.pos..]
+            .iter()
+            .take_while(|&&b| b == b'#')
+            .count();
+        self.pos += hashes;
+        self.expect(b'"')?;
+        let terminator = [b"\"".as_slice(), &vec![b'#'; hashes]].concat();
+        let end = self.bytes[self.pos..]
+            .windows(terminator.len())
+            .position(|window| window == terminator)
+            .ok_or_else(|| self.error("unterminated raw string"))?;
+        let text = String::from_utf8(self.bytes[self.pos..self.pos + end].to_vec())
+            .map_err(|_| self.error("invalid UTF-8"))?;
+        self.pos += end + terminator.len();
+        Ok(text)
+    }
+
+    fn char(&mut self) -> Result<char, String> {
+        self.pos += 1;
+        let c = match self.peek() {
+            Some(b'\\') => {
+                self.pos += 1;
+                self.escape()?
+            }
+            _ => {
+                let rest = std::str::from_utf8(&self.bytes[self.pos..])
+                    .map_err(|_| self.error("invalid UTF-8"))?;
+                let c = rest
+                    .chars()
+                    .next()
+                    .ok_or_else(|| self.error("unterminated char"))?;
+                self.pos += c.len_utf8();
+                c
+            }
+        };
+        if self.peek() != Some(b'\'') {
+            return Err(self.error("unterminated char"));
+        }
+        self.pos += 1;
+        Ok(c)
+    }
+
+    /// The character after a `\`.
+    fn escape(&mut self) -> Result<char, String> {
+        let escape = self
+            .peek()
+            .ok_or_else(|| self.error("unterminated escape"))?;
+        self.pos += 1;
+        let hex = |parser: &mut Self, len: usize| {
+            let digits = parser
+                .bytes
+                .get(parser.pos..parser.pos + len)
+                .and_then(|digits| std::str::from_utf8(digits).ok())
+                .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+                .and_then(char::from_u32)
+                .ok_or_else(|| parser.error("invalid escape"))?;
+            parser.pos += len;
+            Ok(digits)
+        };
+        match escape {
+            b'n' => Ok('\n'),
+            b'r' => Ok('\r'),
+            b't' => Ok('\t'),
+            b'0' => Ok('\0'),
+            b'\\' | b'"' | b'\'' => Ok(escape as char),
+            b'x' => hex(self, 2),
+            b'u' if self.peek() == Some(b'{') => {
+                self.pos += 1;
+                let len = self.bytes[self.pos..]
+                    .iter()
+                    .position(|&b| b == b'}')
+                    .ok_or_else(|| self.error("invalid escape"))?;
+                let c = hex(self, len)?;
+                self.pos += 1;
+                Ok(c)
+            }
+            b'u' => hex(self, 4),
+            _ => Err(self.error("invalid escape")),
+        }
+    }
+}
+
+fn signed(value: u64, negative: bool) -> Value {
+    if negative {
+        Value::from(-(value as i128) as i64)
+    } else {
+        Value::from(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    struct Meters(f32);
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    enum Shape {
+        Point,
+        Cube { size: f32 },
+        Sphere(Meters),
+        Segment(f32, f32),
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    struct Snapshot {
+        entities: BTreeMap<u64, Vec<Shape>>,
+        gravity: Option<f32>,
+        name: String,
+        tag: char,
+        pair: (i32, bool),
+        unit: (),
+    }
+
+    fn snapshot() -> Snapshot {
+        Snapshot {
+            entities: BTreeMap::from([
+                (4294967296, vec![Shape::Point, Shape::Cube { size: 1.0 }]),
+                (
+                    4294967297,
+                    vec![Shape::Sphere(Meters(0.5)), Shape::Segment(-1.0, 0.125)],
+                ),
+            ]),
+            gravity: Some(-9.75),
+            name: "Cube \"1\"\n\u{e9}".to_string(),
+            tag: '\'',
+            pair: (-3, true),
+            unit: (),
+        }
+    }
+
+    /// The tree of every format matches the JSON file and reads back into the snapshot.
+    #[test]
+    fn trees_match_json() {
+        let json = serde_json::to_value(snapshot()).unwrap();
+
+        let ron = ron::ser::to_string_pretty(&snapshot(), Default::default()).unwrap();
+        assert_eq!(from_ron(&ron).unwrap(), json, "{ron}");
+        let ron = ron::ser::to_string(&snapshot()).unwrap();
+        assert_eq!(from_ron(&ron).unwrap(), json, "{ron}");
+
+        let bytes = rmp_serde::to_vec_named(&snapshot()).unwrap();
+        let tree = from_self_describing(&mut rmp_serde::Deserializer::new(bytes.as_slice()));
+        assert_eq!(tree.unwrap(), json);
+
+        assert_eq!(Snapshot::deserialize(json).unwrap(), snapshot());
+    }
+
+    #[test]
+    fn ron_by_hand() {
+        let text = r##"
+            #![enable(implicit_some)]
+            // A comment
+            (
+                /* another */ hex: 0xff, big: 18446744073709551615, float: 1_000.5, exponent: -1e-3,
+                raw: r#"a "quoted" text"#, escaped: "\u{1F600}\x41",
+                list: [1, 2,], map: {"a": None, 3: Some(4), true: inf},
+            )
+        "##;
+        assert_eq!(
+            from_ron(text).unwrap(),
+            json!({
+                "hex": 255, "big": 18446744073709551615u64, "float": 1000.5, "exponent": -0.001,
+                "raw": "a \"quoted\" text", "escaped": "\u{1F600}A",
+                "list": [1, 2], "map": { "a": null, "3": 4, "true": null },
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_ron_is_an_error() {
+        for text in ["(a: 1", "[1, 2", "\"text", "(a: 1) x", "(a 1)", "@"] {
+            assert!(from_ron(text).is_err(), "{text}");
+        }
+    }
+}
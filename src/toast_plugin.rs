@@ -0,0 +1,132 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::bevy_egui::{EguiContexts, EguiPrimaryContextPass};
+use bevy_inspector_egui::egui;
+use std::collections::VecDeque;
+
+/// Toasts fade out over this many seconds at the end of their lifetime.
+const FADE_SECS: f32 = 0.5;
+
+/// Short notifications in the bottom right corner, for results the user should see without
+/// reading the log. Send [`Toast`] events to show them.
+pub struct ToastPlugin;
+
+impl Plugin for ToastPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ToastSettings>()
+            .init_resource::<ActiveToasts>()
+            .add_event::<Toast>()
+            .add_systems(Update, collect_toasts)
+            .add_systems(EguiPrimaryContextPass, show_toasts)
+            .register_type::<ToastSettings>();
+    }
+}
+
+#[derive(Resource, Reflect, Clone)]
+#[reflect(Resource)]
+pub struct ToastSettings {
+    /// How long info toasts stay, in seconds. Warnings and errors stay twice as long.
+    pub duration_secs: f32,
+    /// Older toasts are dropped beyond this many.
+    pub max_visible: usize,
+}
+
+impl Default for ToastSettings {
+    fn default() -> Self {
+        Self {
+            duration_secs: 4.0,
+            max_visible: 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToastKind {
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct Toast {
+    pub kind: ToastKind,
+    pub text: String,
+}
+
+impl Toast {
+    pub fn info(text: impl Into<String>) -> Self {
+        Self {
+            kind: ToastKind::Info,
+            text: text.into(),
+        }
+    }
+
+    pub fn warning(text: impl Into<String>) -> Self {
+        Self {
+            kind: ToastKind::Warning,
+            text: text.into(),
+        }
+    }
+
+    pub fn error(text: impl Into<String>) -> Self {
+        Self {
+            kind: ToastKind::Error,
+            text: text.into(),
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+struct ActiveToasts(VecDeque<(Toast, f32)>);
+
+fn collect_toasts(
+    mut toasts: EventReader<Toast>,
+    mut active: ResMut<ActiveToasts>,
+    settings: Res<ToastSettings>,
+    // Toasts keep fading while the simulation is paused
+    time: Res<Time<Real>>,
+) {
+    for (_, remaining) in active.0.iter_mut() {
+        *remaining -= time.delta_secs();
+    }
+    active.0.retain(|(_, remaining)| *remaining > 0.0);
+
+    for toast in toasts.read() {
+        let duration = match toast.kind {
+            ToastKind::Info => settings.duration_secs,
+            ToastKind::Warning | ToastKind::Error => settings.duration_secs * 2.0,
+        };
+        active.0.push_back((toast.clone(), duration));
+    }
+    while active.0.len() > settings.max_visible {
+        active.0.pop_front();
+    }
+}
+
+fn show_toasts(mut contexts: EguiContexts, active: Res<ActiveToasts>) {
+    if active.0.is_empty() {
+        return;
+    }
+    let Ok(ctx) = contexts.ctx_mut() else {
+        debug!("No Egui context found for the toasts.");
+        return;
+    };
+
+    egui::Area::new(egui::Id::new("toasts"))
+        .anchor(egui::Align2::RIGHT_BOTTOM, [-12.0, -12.0])
+        .interactable(false)
+        .show(ctx, |ui| {
+            for (toast, remaining) in &active.0 {
+                let color = match toast.kind {
+                    ToastKind::Info => ui.visuals().text_color(),
+                    ToastKind::Warning => ui.visuals().warn_fg_color,
+                    ToastKind::Error => ui.visuals().error_fg_color,
+                };
+                ui.scope(|ui| {
+                    ui.set_opacity((remaining / FADE_SECS).clamp(0.0, 1.0));
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.colored_label(color, &toast.text);
+                    });
+                });
+            }
+        });
+}